@@ -0,0 +1,406 @@
+// Option handling and matching shared by the pgrep and pkill binaries
+use std::ffi::CString;
+use std::io::{self, Write};
+use std::process;
+
+use libc::{c_int, uid_t};
+use libproc::libproc::proc_matcher::{MatchTarget, ProcessMatcher};
+use libproc::libproc::proc_pid;
+
+// Exit codes, as used by the BSD and procps versions of pgrep and pkill
+const EXIT_MATCHED: i32 = 0;
+const EXIT_NO_MATCH: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_FATAL: i32 = 3;
+
+const SIGNALS: &[(&str, c_int)] = &[
+    ("HUP", libc::SIGHUP),
+    ("INT", libc::SIGINT),
+    ("QUIT", libc::SIGQUIT),
+    ("ILL", libc::SIGILL),
+    ("TRAP", libc::SIGTRAP),
+    ("ABRT", libc::SIGABRT),
+    ("FPE", libc::SIGFPE),
+    ("KILL", libc::SIGKILL),
+    ("BUS", libc::SIGBUS),
+    ("SEGV", libc::SIGSEGV),
+    ("SYS", libc::SIGSYS),
+    ("PIPE", libc::SIGPIPE),
+    ("ALRM", libc::SIGALRM),
+    ("TERM", libc::SIGTERM),
+    ("URG", libc::SIGURG),
+    ("STOP", libc::SIGSTOP),
+    ("TSTP", libc::SIGTSTP),
+    ("CONT", libc::SIGCONT),
+    ("CHLD", libc::SIGCHLD),
+    ("TTIN", libc::SIGTTIN),
+    ("TTOU", libc::SIGTTOU),
+    ("IO", libc::SIGIO),
+    ("XCPU", libc::SIGXCPU),
+    ("XFSZ", libc::SIGXFSZ),
+    ("VTALRM", libc::SIGVTALRM),
+    ("PROF", libc::SIGPROF),
+    ("WINCH", libc::SIGWINCH),
+    ("USR1", libc::SIGUSR1),
+    ("USR2", libc::SIGUSR2),
+];
+
+// Each binary only constructs its own variant
+#[allow(dead_code)]
+#[derive(Copy, Clone, PartialEq)]
+pub enum Tool {
+    Pgrep,
+    Pkill,
+}
+
+impl Tool {
+    fn name(self) -> &'static str {
+        match self {
+            Tool::Pgrep => "pgrep",
+            Tool::Pkill => "pkill",
+        }
+    }
+
+    fn usage(self) -> String {
+        let (tool_options, output_options) = match self {
+            Tool::Pgrep => (
+                "[-d <delimiter>] [-c] [-l] [-a]",
+                "    -d <delimiter>  separate the pids printed with delimiter (default newline)
+    -c              print the number of matching processes instead of their pids
+    -l              print the process name (or command line with -f) after each pid
+    -a              print the command line after each pid",
+            ),
+            Tool::Pkill => (
+                "[-<signal>] [--signal <signal>] [-e]",
+                "    -<signal>, --signal <signal>
+                    send this signal, by name or number, instead of TERM
+    -e              print the pids that were signalled",
+            ),
+        };
+
+        format!(
+            "Usage: {} {} [-f] [-x] [-i] [-v] [-n|-o] [-u <euid>,...] [-U <uid>,...]
+             [-P <ppid>,...] [-g <pgrp>,...] [-t <tty>,...] [<pattern>]
+
+{}
+    -f              match the pattern against the full command line
+    -x              only match processes where the pattern matches the whole name
+    -i              match the pattern ignoring case
+    -v              select the processes that do not match
+    -n              select only the newest matching process
+    -o              select only the oldest matching process
+    -u <euid>,...   match effective user ids or names
+    -U <uid>,...    match real user ids or names
+    -P <ppid>,...   match parent process ids
+    -g <pgrp>,...   match process group ids
+    -t <tty>,...    match controlling terminal names (e.g. ttys001)
+
+Exits with 0 if any process matched, 1 if none did, 2 on a usage error and 3 on any other error",
+            self.name(),
+            tool_options,
+            output_options
+        )
+    }
+}
+
+struct Options {
+    matcher: ProcessMatcher,
+    target: MatchTarget,
+    delimiter: String,
+    count: bool,
+    long: bool,
+    full: bool,
+    echo: bool,
+    signal: c_int,
+}
+
+fn usage_error(tool: Tool, message: &str) -> ! {
+    writeln!(
+        &mut io::stderr(),
+        "{}: {}\n\n{}",
+        tool.name(),
+        message,
+        tool.usage()
+    )
+    .unwrap();
+    process::exit(EXIT_USAGE);
+}
+
+fn fatal(tool: Tool, message: &str) -> ! {
+    writeln!(&mut io::stderr(), "{}: {}", tool.name(), message).unwrap();
+    process::exit(EXIT_FATAL);
+}
+
+fn parse_signal(name: &str) -> Option<c_int> {
+    if let Ok(number) = name.parse::<c_int>() {
+        return Some(number);
+    }
+
+    let name = name.to_uppercase();
+    let name = name.trim_start_matches("SIG");
+    SIGNALS
+        .iter()
+        .find(|&&(signal_name, _)| signal_name == name)
+        .map(|&(_, signal)| signal)
+}
+
+fn parse_uid(tool: Tool, user: &str) -> uid_t {
+    if let Ok(uid) = user.parse::<uid_t>() {
+        return uid;
+    }
+
+    let name = match CString::new(user) {
+        Ok(name) => name,
+        Err(_) => usage_error(tool, &format!("Invalid user name '{}'", user)),
+    };
+    let passwd = unsafe { libc::getpwnam(name.as_ptr()) };
+    if passwd.is_null() {
+        usage_error(tool, &format!("Unknown user '{}'", user));
+    }
+    unsafe { (*passwd).pw_uid }
+}
+
+fn parse_id(tool: Tool, id: &str) -> u32 {
+    match id.parse::<u32>() {
+        Ok(id) => id,
+        Err(_) => usage_error(tool, &format!("Invalid id '{}'", id)),
+    }
+}
+
+// The controlling terminal is reported as a device number, so look up the device of the named tty
+fn parse_tty(tool: Tool, tty: &str) -> u32 {
+    let path = if tty.starts_with("/dev/") {
+        tty.to_string()
+    } else {
+        format!("/dev/{}", tty)
+    };
+
+    let path = match CString::new(path) {
+        Ok(path) => path,
+        Err(_) => usage_error(tool, &format!("Invalid terminal '{}'", tty)),
+    };
+    let mut stat: libc::stat = unsafe { std::mem::zeroed() };
+    if unsafe { libc::stat(path.as_ptr(), &mut stat) } != 0 {
+        usage_error(tool, &format!("Unknown terminal '{}'", tty));
+    }
+    stat.st_rdev as u32
+}
+
+fn parse_args(tool: Tool, args: Vec<String>) -> Options {
+    let mut options = Options {
+        matcher: ProcessMatcher::new(),
+        target: MatchTarget::Name,
+        delimiter: "\n".to_string(),
+        count: false,
+        long: false,
+        full: false,
+        echo: false,
+        signal: libc::SIGTERM,
+    };
+    let mut pattern = None;
+
+    let mut args = args.into_iter().skip(1);
+    while let Some(arg) = args.next() {
+        if !arg.starts_with('-') || arg == "-" {
+            if pattern.is_some() {
+                usage_error(
+                    tool,
+                    &format!("Only one pattern can be given, found '{}'", arg),
+                );
+            }
+            pattern = Some(arg);
+            continue;
+        }
+
+        if arg == "-h" || arg == "--help" {
+            println!("{}", tool.usage());
+            process::exit(EXIT_MATCHED);
+        }
+
+        if tool == Tool::Pkill {
+            if arg == "--signal" {
+                let name = args
+                    .next()
+                    .unwrap_or_else(|| usage_error(tool, "'--signal' requires a signal"));
+                options.signal = parse_signal(&name)
+                    .unwrap_or_else(|| usage_error(tool, &format!("Unknown signal '{}'", name)));
+                continue;
+            }
+            // Only numbers and upper case names are signals, so that grouped flags such as
+            // '-io' are not taken for the signal of the same name
+            let name = &arg[1..];
+            if name
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+            {
+                if let Some(signal) = parse_signal(name) {
+                    options.signal = signal;
+                    continue;
+                }
+            }
+        }
+
+        // Single letter flags may be grouped, as in '-fx'
+        let flags: Vec<char> = arg[1..].chars().collect();
+        for (index, flag) in flags.iter().enumerate() {
+            match (flag, tool) {
+                ('f', _) => options.target = MatchTarget::CommandLine,
+                ('x', _) => options.matcher = options.matcher.exact(true),
+                ('i', _) => options.matcher = options.matcher.ignore_case(true),
+                ('v', _) => options.matcher = options.matcher.inverse(true),
+                ('n', _) => options.matcher = options.matcher.newest(),
+                ('o', _) => options.matcher = options.matcher.oldest(),
+                ('c', Tool::Pgrep) => options.count = true,
+                ('l', Tool::Pgrep) => options.long = true,
+                ('a', Tool::Pgrep) => options.full = true,
+                ('e', Tool::Pkill) => options.echo = true,
+                ('d', Tool::Pgrep) | ('u', _) | ('U', _) | ('P', _) | ('g', _) | ('t', _) => {
+                    // The value is either the rest of this argument or the next argument
+                    let rest: String = flags[index + 1..].iter().collect();
+                    let value = if rest.is_empty() {
+                        args.next().unwrap_or_else(|| {
+                            usage_error(tool, &format!("'-{}' requires a value", flag))
+                        })
+                    } else {
+                        rest
+                    };
+
+                    if *flag == 'd' {
+                        options.delimiter = value;
+                        break;
+                    }
+
+                    for item in value.split(',').filter(|item| !item.is_empty()) {
+                        options.matcher = match flag {
+                            'u' => options.matcher.uid(parse_uid(tool, item)),
+                            'U' => options.matcher.ruid(parse_uid(tool, item)),
+                            'P' => options.matcher.ppid(parse_id(tool, item)),
+                            'g' => options.matcher.pgid(parse_id(tool, item)),
+                            _ => options.matcher.tty(parse_tty(tool, item)),
+                        };
+                    }
+                    break;
+                }
+                _ => usage_error(tool, &format!("Unknown option '-{}'", flag)),
+            }
+        }
+    }
+
+    options.matcher = options.matcher.target(options.target);
+    if let Some(pattern) = pattern {
+        options.matcher = options.matcher.pattern(&pattern);
+    }
+
+    options
+}
+
+// What -l and -a print after the pid
+fn describe(pid: i32, options: &Options) -> Option<String> {
+    let description =
+        if options.full || (options.long && options.target == MatchTarget::CommandLine) {
+            proc_pid::cmdline(pid)
+                .ok()
+                .or_else(|| proc_pid::name(pid).ok())
+        } else if options.long {
+            proc_pid::name(pid).ok()
+        } else {
+            None
+        };
+
+    description.map(|description| format!("{} {}", pid, description))
+}
+
+/// Run pgrep or pkill with the arguments of this process, and exit
+pub fn run(tool: Tool) -> ! {
+    let mut options = parse_args(tool, std::env::args().collect());
+
+    // Never match ourselves, leaving ourselves out before -n or -o pick a process
+    options.matcher = options.matcher.exclude_pid(process::id() as i32);
+    let pids: Vec<i32> = match options.matcher.find() {
+        Ok(pids) => pids,
+        Err(ref err) if err.kind() == io::ErrorKind::InvalidInput => {
+            usage_error(tool, &err.to_string())
+        }
+        Err(err) => fatal(tool, &err.to_string()),
+    };
+
+    let mut matched = false;
+    match tool {
+        Tool::Pgrep => {
+            let lines: Vec<String> = pids
+                .iter()
+                .map(|&pid| describe(pid, &options).unwrap_or_else(|| pid.to_string()))
+                .collect();
+            matched = !lines.is_empty();
+
+            if options.count {
+                println!("{}", lines.len());
+            } else if matched {
+                print!("{}", lines.join(&options.delimiter));
+                println!();
+            }
+        }
+        Tool::Pkill => {
+            for pid in pids {
+                if unsafe { libc::kill(pid, options.signal) } == 0 {
+                    matched = true;
+                    if options.echo {
+                        println!("killed {}", pid);
+                    }
+                } else {
+                    let err = io::Error::last_os_error();
+                    // A process that has already exited is not an error
+                    if err.raw_os_error() != Some(libc::ESRCH) {
+                        writeln!(
+                            &mut io::stderr(),
+                            "pkill: killing pid {} failed: {}",
+                            pid,
+                            err
+                        )
+                        .unwrap();
+                    }
+                }
+            }
+        }
+    }
+
+    process::exit(if matched { EXIT_MATCHED } else { EXIT_NO_MATCH });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn grouped_flags_are_not_a_signal_test() {
+        let options = parse_args(Tool::Pkill, args(&["pkill", "-io", "foo"]));
+        assert_eq!(options.signal, libc::SIGTERM);
+        assert_eq!(
+            options.matcher,
+            ProcessMatcher::new()
+                .ignore_case(true)
+                .oldest()
+                .target(MatchTarget::Name)
+                .pattern("foo")
+        );
+    }
+
+    #[test]
+    fn signal_option_test() {
+        for (arg, signal) in &[
+            ("-9", libc::SIGKILL),
+            ("-HUP", libc::SIGHUP),
+            ("-SIGIO", libc::SIGIO),
+        ] {
+            let options = parse_args(Tool::Pkill, args(&["pkill", arg, "foo"]));
+            assert_eq!(options.signal, *signal);
+        }
+
+        let options = parse_args(Tool::Pkill, args(&["pkill", "--signal", "io", "foo"]));
+        assert_eq!(options.signal, libc::SIGIO);
+    }
+}
@@ -1,4 +1,3 @@
-use libproc;
 use libproc::libproc::kmesg_buffer;
 use std::io::Write;
 
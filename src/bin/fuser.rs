@@ -0,0 +1,165 @@
+use std::env;
+use std::ffi::CStr;
+use std::io::{self, Write};
+use std::process;
+
+use libproc::libproc::proc_files::{self, FileUse};
+use libproc::libproc::proc_pid::{self, BSDInfo};
+
+// Exit codes, as with fuser(1) it is a failure when no process is using any of the paths
+const EXIT_NOT_USED: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_ERROR: i32 = 3;
+
+const USAGE: &str = "Usage: fuser [-m] [-v] <path>...
+
+Prints the processes using each file or directory, followed by how they are using it:
+    c  current working directory
+    r  root directory
+    e  executable
+    m  memory mapped file or shared library
+    f  open file (F when open for writing)
+
+    -m    show the processes using any file on the filesystem the path is on, such as a mount point
+    -v    verbose output, a line for each process with its user, access and command
+
+Exits with 1 if no process is using any of the paths, 2 on a usage error and 3 on any other
+error. Processes that may not be inspected are left out, run as root to see all of them.";
+
+struct Options {
+    mount: bool,
+    verbose: bool,
+    paths: Vec<String>,
+}
+
+fn usage_error(message: &str) -> ! {
+    writeln!(&mut io::stderr(), "fuser: {}\n\n{}", message, USAGE).unwrap();
+    process::exit(EXIT_USAGE);
+}
+
+fn parse_args(args: Vec<String>) -> Options {
+    let mut options = Options {
+        mount: false,
+        verbose: false,
+        paths: vec![],
+    };
+
+    for arg in args.into_iter().skip(1) {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            _ if arg.starts_with('-') && arg.len() > 1 => {
+                for flag in arg[1..].chars() {
+                    match flag {
+                        'm' => options.mount = true,
+                        'v' => options.verbose = true,
+                        _ => usage_error(&format!("Unknown option '-{}'", flag)),
+                    }
+                }
+            }
+            _ => options.paths.push(arg),
+        }
+    }
+
+    if options.paths.is_empty() {
+        usage_error("No path supplied");
+    }
+    options
+}
+
+fn user_name(uid: libc::uid_t) -> String {
+    let passwd = unsafe { libc::getpwuid(uid) };
+    if passwd.is_null() {
+        uid.to_string()
+    } else {
+        unsafe { CStr::from_ptr((*passwd).pw_name) }
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+// The uses of each process, in pid order, with the letters of the ways it is using the path
+fn by_process(uses: &[FileUse]) -> Vec<(i32, String)> {
+    let mut processes: Vec<(i32, String)> = vec![];
+    for file_use in uses {
+        let code = file_use.access.code();
+        match processes.last_mut() {
+            Some((pid, codes)) if *pid == file_use.pid => {
+                if !codes.contains(code) {
+                    codes.push(code);
+                }
+            }
+            _ => processes.push((file_use.pid, code.to_string())),
+        }
+    }
+    processes
+}
+
+fn print_uses(out: &mut impl Write, path: &str, uses: &[FileUse], verbose: bool) -> io::Result<()> {
+    let processes = by_process(uses);
+    if !verbose {
+        let mut line = format!("{}:", path);
+        for (pid, codes) in processes {
+            line.push_str(&format!(" {}{}", pid, codes));
+        }
+        return writeln!(out, "{}", line);
+    }
+
+    writeln!(
+        out,
+        "{:<20} {:>10} {:>7} {:<6} COMMAND",
+        "PATH", "USER", "PID", "ACCESS"
+    )?;
+    let mut first = true;
+    for (pid, codes) in processes {
+        let user = proc_pid::pidinfo::<BSDInfo>(pid, 0)
+            .map(|bsd| user_name(bsd.pbi_uid))
+            .unwrap_or_default();
+        let command = proc_pid::name(pid).unwrap_or_default();
+        let shown_path = if first { path } else { "" };
+        first = false;
+        writeln!(
+            out,
+            "{:<20} {:>10} {:>7} {:<6} {}",
+            shown_path, user, pid, codes, command
+        )?;
+    }
+    if first {
+        writeln!(out, "{:<20}", path)?;
+    }
+    Ok(())
+}
+
+fn main() {
+    let options = parse_args(env::args().collect());
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let mut exit_code = EXIT_NOT_USED;
+    for path in &options.paths {
+        let uses = if options.mount {
+            proc_files::processes_using_mount(path)
+        } else {
+            proc_files::processes_using(path)
+        };
+
+        match uses {
+            Ok(uses) => {
+                if !uses.is_empty() && exit_code == EXIT_NOT_USED {
+                    exit_code = 0;
+                }
+                if let Err(err) = print_uses(&mut out, path, &uses, options.verbose) {
+                    writeln!(&mut io::stderr(), "fuser: {}", err).unwrap();
+                    process::exit(EXIT_ERROR);
+                }
+            }
+            Err(err) => {
+                writeln!(&mut io::stderr(), "fuser: {}: {}", path, err).unwrap();
+                exit_code = EXIT_ERROR;
+            }
+        }
+    }
+    process::exit(exit_code);
+}
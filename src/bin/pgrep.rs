@@ -0,0 +1,7 @@
+mod common;
+
+use common::Tool;
+
+fn main() {
+    common::run(Tool::Pgrep);
+}
@@ -0,0 +1,303 @@
+use std::cmp::Reverse;
+use std::env;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process;
+
+use libproc::libproc::proc_pid::{self, MemoryRegion, RegionUsage, ShareMode};
+
+// Exit codes, the same as those of procinfo
+const EXIT_NO_SUCH_PROCESS: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_ERROR: i32 = 3;
+
+const USAGE: &str = "Usage: pmap [-x] [-s] <pid>...
+
+Prints the memory mappings of processes, with their address, size in kilobytes, resident size,
+permissions and backing file.
+
+    -x    extended output, adding the proportional, dirty and swapped sizes of each mapping
+    -s    summarize the mappings of each backing file instead of listing every mapping
+
+Exits with 1 if a process does not exist, 2 on a usage error and 3 on any other error";
+
+const ANONYMOUS: &str = "[ anon ]";
+
+struct Options {
+    extended: bool,
+    summary: bool,
+    pids: Vec<i32>,
+}
+
+fn usage_error(message: &str) -> ! {
+    writeln!(&mut io::stderr(), "pmap: {}\n\n{}", message, USAGE).unwrap();
+    process::exit(EXIT_USAGE);
+}
+
+fn parse_args(args: Vec<String>) -> Options {
+    let mut options = Options {
+        extended: false,
+        summary: false,
+        pids: vec![],
+    };
+
+    for arg in args.into_iter().skip(1) {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            _ if arg.starts_with('-') && arg.len() > 1 => {
+                for flag in arg[1..].chars() {
+                    match flag {
+                        'x' => options.extended = true,
+                        's' => options.summary = true,
+                        _ => usage_error(&format!("Unknown option '-{}'", flag)),
+                    }
+                }
+            }
+            _ => match arg.parse() {
+                Ok(pid) => options.pids.push(pid),
+                Err(_) => usage_error(&format!("'{}' is not a pid", arg)),
+            },
+        }
+    }
+
+    if options.pids.is_empty() {
+        usage_error("No pid supplied");
+    }
+    options
+}
+
+fn kilobytes(bytes: u64) -> String {
+    (bytes / 1024).to_string()
+}
+
+fn optional_kilobytes(bytes: Option<u64>) -> String {
+    bytes.map_or_else(|| "-".to_string(), kilobytes)
+}
+
+fn mode(region: &MemoryRegion) -> String {
+    let shared = match region.share_mode {
+        ShareMode::Shared | ShareMode::TrueShared | ShareMode::SharedAliased => 's',
+        _ => 'p',
+    };
+    format!("{}{}", region.protection, shared)
+}
+
+fn mapping_name(region: &MemoryRegion) -> String {
+    region.path.as_ref().map_or_else(
+        || ANONYMOUS.to_string(),
+        |path| path.to_string_lossy().into_owned(),
+    )
+}
+
+// The usage of regions is not always available (such as the regions of a process we may not
+// inspect fully), then the regions are still listed without it
+fn regions(pid: i32) -> io::Result<Vec<(MemoryRegion, Option<RegionUsage>)>> {
+    let mut regions: Vec<(MemoryRegion, Option<RegionUsage>)> = match proc_pid::pidregionusage(pid)
+    {
+        Ok(regions) => regions
+            .into_iter()
+            .map(|(region, usage)| (region, Some(usage)))
+            .collect(),
+        Err(ref err) if err.raw_os_error() == Some(libc::ESRCH) => {
+            return Err(io::Error::from_raw_os_error(libc::ESRCH))
+        }
+        Err(_) => proc_pid::pidregions(pid)?
+            .map(|region| region.map(|region| (region, None)))
+            .collect::<io::Result<_>>()?,
+    };
+
+    // Mac OS X does not give the path of every region backed by a file along with the region,
+    // but can still tell the name of the file mapped at its address
+    for (region, _) in regions.iter_mut() {
+        if region.path.is_none() && region.inode != 0 {
+            region.path = proc_pid::regionfilename(pid, region.start)
+                .ok()
+                .map(PathBuf::from);
+        }
+    }
+    Ok(regions)
+}
+
+#[derive(Default)]
+struct Totals {
+    size: u64,
+    resident: Option<u64>,
+    proportional: Option<u64>,
+    dirty: Option<u64>,
+    swapped: Option<u64>,
+    mappings: usize,
+}
+
+impl Totals {
+    fn add(&mut self, region: &MemoryRegion, usage: &Option<RegionUsage>) {
+        fn add_to(total: &mut Option<u64>, value: Option<u64>) {
+            if let Some(value) = value {
+                *total = Some(total.unwrap_or(0) + value);
+            }
+        }
+
+        self.size += region.size();
+        self.mappings += 1;
+        if let Some(usage) = usage {
+            add_to(&mut self.resident, Some(usage.resident));
+            add_to(&mut self.proportional, usage.proportional);
+            add_to(&mut self.dirty, Some(usage.dirty));
+            add_to(&mut self.swapped, Some(usage.swapped));
+        }
+    }
+
+    fn columns(&self, extended: bool) -> Vec<String> {
+        let mut columns = vec![kilobytes(self.size), optional_kilobytes(self.resident)];
+        if extended {
+            columns.push(optional_kilobytes(self.proportional));
+            columns.push(optional_kilobytes(self.dirty));
+            columns.push(optional_kilobytes(self.swapped));
+        }
+        columns
+    }
+}
+
+fn size_header(extended: bool) -> Vec<&'static str> {
+    if extended {
+        vec!["Kbytes", "RSS", "PSS", "Dirty", "Swap"]
+    } else {
+        vec!["Kbytes", "RSS"]
+    }
+}
+
+fn print_table(out: &mut impl Write, rows: Vec<Vec<String>>, left_first: bool) -> io::Result<()> {
+    // Right align the numbers, the name of the mapping is last
+    let columns = rows.first().map_or(0, Vec::len);
+    let widths: Vec<usize> = (0..columns)
+        .map(|column| rows.iter().map(|row| row[column].len()).max().unwrap_or(0))
+        .collect();
+
+    for row in rows {
+        let mut line = String::new();
+        for (column, value) in row.iter().enumerate() {
+            if column + 1 == columns {
+                line.push_str(value);
+            } else if column == 0 && left_first {
+                line.push_str(&format!("{:<width$} ", value, width = widths[column]));
+            } else {
+                line.push_str(&format!("{:>width$} ", value, width = widths[column]));
+            }
+        }
+        writeln!(out, "{}", line.trim_end())?;
+    }
+    Ok(())
+}
+
+fn print_mappings(
+    out: &mut impl Write,
+    regions: &[(MemoryRegion, Option<RegionUsage>)],
+    options: &Options,
+) -> io::Result<()> {
+    let mut header = vec!["Address"];
+    header.extend(size_header(options.extended));
+    header.extend(["Mode", "Mapping"]);
+    let mut rows = vec![header.into_iter().map(String::from).collect()];
+
+    let mut totals = Totals::default();
+    for (region, usage) in regions {
+        let mut single = Totals::default();
+        single.add(region, usage);
+        totals.add(region, usage);
+
+        let mut row = vec![format!("{:016x}", region.start)];
+        row.extend(single.columns(options.extended));
+        row.push(mode(region));
+        row.push(mapping_name(region));
+        rows.push(row);
+    }
+
+    let mut row = vec!["total kB".to_string()];
+    row.extend(totals.columns(options.extended));
+    row.extend([String::new(), String::new()]);
+    rows.push(row);
+
+    print_table(out, rows, true)
+}
+
+fn print_summary(
+    out: &mut impl Write,
+    regions: &[(MemoryRegion, Option<RegionUsage>)],
+    options: &Options,
+) -> io::Result<()> {
+    let mut files: Vec<(String, Totals)> = vec![];
+    let mut totals = Totals::default();
+    for (region, usage) in regions {
+        let name = mapping_name(region);
+        let index = match files.iter().position(|(file, _)| *file == name) {
+            Some(index) => index,
+            None => {
+                files.push((name, Totals::default()));
+                files.len() - 1
+            }
+        };
+        files[index].1.add(region, usage);
+        totals.add(region, usage);
+    }
+
+    // Largest first
+    files.sort_by_key(|(_, file)| Reverse((file.resident, file.size)));
+
+    let mut header = vec!["Mappings"];
+    header.extend(size_header(options.extended));
+    header.push("File");
+    let mut rows = vec![header.into_iter().map(String::from).collect()];
+    for (name, file) in files {
+        let mut row = vec![file.mappings.to_string()];
+        row.extend(file.columns(options.extended));
+        row.push(name);
+        rows.push(row);
+    }
+
+    let mut row = vec![totals.mappings.to_string()];
+    row.extend(totals.columns(options.extended));
+    row.push("total kB".to_string());
+    rows.push(row);
+
+    print_table(out, rows, false)
+}
+
+fn pmap(pid: i32, options: &Options) -> io::Result<()> {
+    let regions = regions(pid)?;
+    let command = proc_pid::cmdline(pid)
+        .ok()
+        .filter(|command| !command.is_empty())
+        .or_else(|| proc_pid::name(pid).ok())
+        .unwrap_or_default();
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    writeln!(out, "{}:   {}", pid, command)?;
+    if options.summary {
+        print_summary(&mut out, &regions, options)
+    } else {
+        print_mappings(&mut out, &regions, options)
+    }
+}
+
+fn main() {
+    let options = parse_args(env::args().collect());
+
+    let mut exit_code = 0;
+    for &pid in &options.pids {
+        match pmap(pid, &options) {
+            Ok(()) => {}
+            Err(ref err) if err.raw_os_error() == Some(libc::ESRCH) => {
+                writeln!(&mut io::stderr(), "pmap: no such process {}", pid).unwrap();
+                exit_code = exit_code.max(EXIT_NO_SUCH_PROCESS);
+            }
+            Err(err) => {
+                writeln!(&mut io::stderr(), "pmap: {}: {}", pid, err).unwrap();
+                exit_code = EXIT_ERROR;
+            }
+        }
+    }
+    process::exit(exit_code);
+}
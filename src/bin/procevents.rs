@@ -0,0 +1,166 @@
+use std::env;
+use std::io::{self, Write};
+use std::process;
+
+const EXIT_USAGE: i32 = 2;
+const EXIT_ERROR: i32 = 3;
+
+const USAGE: &str = "Usage: procevents [<pid>,...]
+
+Prints processes being created, executing programs, changing ids and exiting, as it happens.
+If pids are supplied only the events of those processes are printed.
+
+Many kernels only allow this with the CAP_NET_ADMIN capability, usually that means running as
+root. Linux only.";
+
+fn usage_error(message: &str) -> ! {
+    writeln!(&mut io::stderr(), "procevents: {}\n\n{}", message, USAGE).unwrap();
+    process::exit(EXIT_USAGE);
+}
+
+fn parse_args(args: Vec<String>) -> Vec<i32> {
+    let mut pids = vec![];
+    for arg in args.into_iter().skip(1) {
+        if arg == "-h" || arg == "--help" {
+            println!("{}", USAGE);
+            process::exit(0);
+        }
+        for pid in arg.split(',').filter(|pid| !pid.is_empty()) {
+            match pid.parse() {
+                Ok(pid) => pids.push(pid),
+                Err(_) => usage_error(&format!("Invalid pid '{}'", pid)),
+            }
+        }
+    }
+    pids
+}
+
+#[cfg(target_os = "linux")]
+mod events {
+    use libproc::libproc::proc_events::{ProcEvent, ProcEventKind};
+
+    // The process the event is about, and the line describing it
+    pub fn describe(event: &ProcEvent) -> (i32, String) {
+        let (tgid, description) = match event.kind {
+            ProcEventKind::Fork {
+                parent_pid,
+                parent_tgid,
+                child_pid,
+                child_tgid,
+            } => (
+                parent_tgid,
+                if child_pid == child_tgid {
+                    format!("fork    {} -> {}", parent_tgid, child_tgid)
+                } else {
+                    format!(
+                        "thread  {}/{} -> {}/{}",
+                        parent_tgid, parent_pid, child_tgid, child_pid
+                    )
+                },
+            ),
+            ProcEventKind::Exec { tgid, .. } => (
+                tgid,
+                format!(
+                    "exec    {} {}",
+                    tgid,
+                    libproc::libproc::proc_pid::cmdline(tgid).unwrap_or_default()
+                ),
+            ),
+            ProcEventKind::Uid {
+                tgid, ruid, euid, ..
+            } => (
+                tgid,
+                format!("uid     {} ruid={} euid={}", tgid, ruid, euid),
+            ),
+            ProcEventKind::Gid {
+                tgid, rgid, egid, ..
+            } => (
+                tgid,
+                format!("gid     {} rgid={} egid={}", tgid, rgid, egid),
+            ),
+            ProcEventKind::Sid { tgid, .. } => (tgid, format!("setsid  {}", tgid)),
+            ProcEventKind::Ptrace {
+                tgid, tracer_tgid, ..
+            } => (
+                tgid,
+                if tracer_tgid == 0 {
+                    format!("ptrace  {} detached", tgid)
+                } else {
+                    format!("ptrace  {} traced by {}", tgid, tracer_tgid)
+                },
+            ),
+            ProcEventKind::Comm { tgid, ref comm, .. } => {
+                (tgid, format!("comm    {} {}", tgid, comm))
+            }
+            ProcEventKind::Coredump { tgid, .. } => (tgid, format!("core    {}", tgid)),
+            ProcEventKind::Exit { pid, tgid, .. } => {
+                let status = match (event.kind.exited_with(), event.kind.killed_by()) {
+                    (Some(code), _) => format!("exit code {}", code),
+                    (_, Some(signal)) => format!("killed by signal {}", signal),
+                    _ => String::new(),
+                };
+                if pid == tgid {
+                    (tgid, format!("exit    {} {}", tgid, status))
+                } else {
+                    (tgid, format!("texit   {}/{} {}", tgid, pid, status))
+                }
+            }
+            ProcEventKind::Unknown { what } => (0, format!("unknown event {:#x}", what)),
+        };
+
+        (
+            tgid,
+            format!(
+                "{}.{:06} {}",
+                event.timestamp_ns / 1_000_000_000,
+                event.timestamp_ns % 1_000_000_000 / 1000,
+                description
+            ),
+        )
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn main() {
+    use libproc::libproc::proc_events::ProcEventSubscriber;
+
+    let pids = parse_args(env::args().collect());
+
+    let subscriber = match ProcEventSubscriber::subscribe() {
+        Ok(subscriber) => subscriber,
+        Err(err) => {
+            writeln!(&mut io::stderr(), "procevents: {}", err).unwrap();
+            process::exit(EXIT_ERROR);
+        }
+    };
+
+    for event in subscriber {
+        match event {
+            Ok(event) => {
+                let (pid, line) = events::describe(&event);
+                if pids.is_empty() || pids.contains(&pid) {
+                    println!("{}", line);
+                }
+            }
+            // Events were dropped because we did not keep up, say so and carry on
+            Err(ref err) if err.raw_os_error() == Some(libc::ENOBUFS) => {
+                writeln!(&mut io::stderr(), "procevents: some events were lost").unwrap();
+            }
+            Err(err) => {
+                writeln!(&mut io::stderr(), "procevents: {}", err).unwrap();
+                process::exit(EXIT_ERROR);
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn main() {
+    parse_args(env::args().collect());
+    writeln!(
+        &mut io::stderr(),
+        "procevents: process events are only supported on Linux"
+    )
+    .unwrap();
+    process::exit(EXIT_ERROR);
+}
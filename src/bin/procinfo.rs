@@ -1,109 +1,642 @@
 use std::env;
-use std::io::Write;
+use std::io::{self, Write};
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::process;
 
-use libproc;
+use libproc::libproc::proc_files;
+use libproc::libproc::proc_ipc::{IpcEndpoint, IpcGraph};
+use libproc::libproc::proc_limits;
 use libproc::libproc::proc_pid;
-use libproc::libproc::proc_pid::{name, pidfdinfo, ProcFDType, SocketFDInfo, SocketInfoKind};
-use std::convert::TryInto;
-
-mod c {
-    extern crate libc;
-
-    extern "C" {
-        pub fn getpid() -> libc::pid_t;
-    }
-}
-
-fn getpid() -> i32 {
-    unsafe { c::getpid() }
-}
-
-fn procinfo(pid: i32) {
-    match proc_pid::libversion() {
-        Ok((major, minor)) => println!("Libversion: {}.{}", major, minor),
-        Err(err) => writeln!(&mut std::io::stderr(), "Error: {}", err).unwrap(),
-    }
-
-    println!("Pid: {}", pid);
-
-    match proc_pid::pidpath(pid) {
-        Ok(path) => println!("Path: {}", path),
-        Err(err) => writeln!(&mut std::io::stderr(), "Error: {}", err).unwrap(),
-    }
-
-    match proc_pid::name(pid) {
-        Ok(name) => println!("Name: {}", name),
-        Err(err) => writeln!(&mut std::io::stderr(), "Error: {}", err).unwrap(),
-    }
-
-    match proc_pid::regionfilename(pid, 0) {
-        Ok(regionfilename) => println!("Region Filename (at address 0): {}", regionfilename),
-        Err(err) => writeln!(&mut std::io::stderr(), "Error: {}", err).unwrap(),
-    }
-
-    match proc_pid::listpids(proc_pid::ProcType::ProcAllPIDS, 0) {
-        Ok(pids) => {
-            println!("There are currently {} processes active", pids.len());
-            for pid in pids {
-                let pid = pid.try_into().unwrap();
-                println!("pid: {}, name: {}", pid, name(pid).unwrap());
-                for fd in proc_pid::listpidinfo::<proc_pid::ListFDs>(pid, 4000).unwrap() {
-                    match fd.proc_fdtype.into() {
-                        ProcFDType::Socket => {
-                            if let Ok(socket) = pidfdinfo::<SocketFDInfo>(pid, fd.proc_fd) {
-                                match socket.psi.soi_kind.into() {
-                                    SocketInfoKind::Tcp => {
-                                        // access to the member of `soi_proto` is unsafe becasuse of union type.
-                                        let info = unsafe { socket.psi.soi_proto.pri_tcp };
-
-                                        // change endian and cut off because insi_lport is network endian and 16bit witdh.
-                                        let mut port = 0;
-                                        port |= info.tcpsi_ini.insi_lport >> 8 & 0x00ff;
-                                        port |= info.tcpsi_ini.insi_lport << 8 & 0xff00;
-
-                                        // access to the member of `insi_laddr` is unsafe becasuse of union type.
-                                        let s_addr = unsafe {
-                                            info.tcpsi_ini.insi_laddr.ina_46.i46a_addr4.s_addr
-                                        };
-
-                                        // change endian because insi_laddr is network endian.
-                                        let mut addr = 0;
-                                        addr |= s_addr >> 24 & 0x000000ff;
-                                        addr |= s_addr >> 8 & 0x0000ff00;
-                                        addr |= s_addr << 8 & 0x00ff0000;
-                                        addr |= s_addr << 24 & 0xff000000;
-
-                                        println!(
-                                            "{}.{}.{}.{}:{}",
-                                            addr >> 24 & 0xff,
-                                            addr >> 16 & 0xff,
-                                            addr >> 8 & 0xff,
-                                            addr & 0xff,
-                                            port
-                                        );
-                                    }
-                                    _ => (),
-                                }
-                            }
-                        }
-                        _ => (),
+use libproc::libproc::proc_pid::{
+    c_chars_to_string, BSDInfo, InSockInfo, ListFDs, ListThreads, ProcFDType, ProcType,
+    SocketFDInfo, SocketInfoKind, TaskInfo, TcpSIState, ThreadInfo, VnodePathFDInfo,
+};
+
+// Exit codes, so that scripts can tell a process that has gone away from any other failure
+const EXIT_NO_SUCH_PROCESS: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_ERROR: i32 = 3;
+
+// See http://opensource.apple.com//source/xnu/xnu-1456.1.26/bsd/sys/proc_info.h
+const INI_IPV6: u8 = 0x2;
+
+const USAGE: &str = "Usage: procinfo [--json] [--fields <field>,...] [<command>] [<pid>]
+
+Commands:
+    info      general information about the process (default)
+    threads   the threads of the process
+    fds       the open file descriptors of the process
+    sockets   the open sockets of the process (Mac OS X only)
+    limits    the resource limits of the process
+    peers     the processes at the other end of the pipes and Unix domain sockets of the process
+    list      a summary of every running process (takes no pid)
+    deleted   the deleted files still held open by every process, largest first (takes no pid)
+
+If no pid is supplied the pid of procinfo itself is used.";
+
+#[derive(Copy, Clone, PartialEq)]
+enum Command {
+    Info,
+    Threads,
+    Fds,
+    Sockets,
+    Limits,
+    Peers,
+    List,
+    Deleted,
+}
+
+impl Command {
+    fn parse(name: &str) -> Option<Command> {
+        match name {
+            "info" => Some(Command::Info),
+            "threads" => Some(Command::Threads),
+            "fds" => Some(Command::Fds),
+            "sockets" => Some(Command::Sockets),
+            "peers" => Some(Command::Peers),
+            "limits" => Some(Command::Limits),
+            "list" => Some(Command::List),
+            "deleted" => Some(Command::Deleted),
+            _ => None,
+        }
+    }
+
+    // The fields each command can output, in their default order
+    fn fields(self) -> &'static [&'static str] {
+        match self {
+            Command::Info => &[
+                "pid",
+                "ppid",
+                "name",
+                "path",
+                "cwd",
+                "uid",
+                "gid",
+                "ruid",
+                "rgid",
+                "pgid",
+                "nice",
+                "start_time",
+                "files",
+                "threads",
+                "virtual_size",
+                "resident_size",
+                "libversion",
+            ],
+            Command::Threads => &[
+                "tid",
+                "name",
+                "user_time",
+                "system_time",
+                "cpu_usage",
+                "policy",
+                "run_state",
+                "priority",
+                "max_priority",
+            ],
+            Command::Fds => &["fd", "type", "offset", "path"],
+            Command::Sockets => &["fd", "kind", "protocol", "local", "remote", "state"],
+            Command::Limits => &["resource", "soft", "hard", "units"],
+            Command::Peers => &["fd", "kind", "end", "peer_pid", "peer_name", "peer_fd"],
+            Command::List => &["pid", "ppid", "uid", "name", "path"],
+            Command::Deleted => &["pid", "name", "fd", "size", "path"],
+        }
+    }
+}
+
+struct Options {
+    json: bool,
+    fields: Vec<String>,
+    command: Command,
+    pid: i32,
+}
+
+enum Value {
+    Null,
+    Int(i64),
+    Str(String),
+}
+
+impl Value {
+    fn text(&self) -> String {
+        match self {
+            Value::Null => "-".to_string(),
+            Value::Int(value) => value.to_string(),
+            Value::Str(value) => value.clone(),
+        }
+    }
+
+    fn json(&self) -> String {
+        match self {
+            Value::Null => "null".to_string(),
+            Value::Int(value) => value.to_string(),
+            Value::Str(value) => json_string(value),
+        }
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Value {
+        value.map_or(Value::Null, Into::into)
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Value {
+        Value::Str(value)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Value {
+        Value::Int(value)
+    }
+}
+
+type Record = Vec<(&'static str, Value)>;
+
+fn json_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    for ch in value.chars() {
+        match ch {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => escaped.push(ch),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+fn is_no_such_process(err: &io::Error) -> bool {
+    err.raw_os_error() == Some(libc::ESRCH)
+}
+
+// Information we are not allowed to see (or that is otherwise unavailable) is reported as missing,
+// but a process that has gone away, or information this platform cannot provide at all, is still
+// an error for the caller to handle
+fn optional<T>(result: io::Result<T>) -> io::Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(err) => {
+            if is_no_such_process(&err) || err.kind() == io::ErrorKind::Unsupported {
+                Err(err)
+            } else {
+                Ok(None)
+            }
+        }
+    }
+}
+
+fn info(pid: i32) -> io::Result<Vec<Record>> {
+    let bsd = proc_pid::pidinfo::<BSDInfo>(pid, 0)?;
+    let task = optional(proc_pid::pidinfo::<TaskInfo>(pid, 0))?;
+    let path = optional(proc_pid::pidpath(pid))?;
+    let cwd = optional(proc_pid::pidcwd(pid))?.map(|cwd| cwd.to_string_lossy().into_owned());
+    let name = optional(proc_pid::name(pid))?.unwrap_or_else(|| c_chars_to_string(&bsd.pbi_comm));
+    let libversion = proc_pid::libversion()
+        .ok()
+        .map(|(major, minor)| format!("{}.{}", major, minor));
+
+    Ok(vec![vec![
+        ("pid", Value::Int(bsd.pbi_pid as i64)),
+        ("ppid", Value::Int(bsd.pbi_ppid as i64)),
+        ("name", Value::Str(name)),
+        ("path", path.into()),
+        ("cwd", cwd.into()),
+        ("uid", Value::Int(bsd.pbi_uid as i64)),
+        ("gid", Value::Int(bsd.pbi_gid as i64)),
+        ("ruid", Value::Int(bsd.pbi_ruid as i64)),
+        ("rgid", Value::Int(bsd.pbi_rgid as i64)),
+        ("pgid", Value::Int(bsd.pbi_pgid as i64)),
+        ("nice", Value::Int(bsd.pbi_nice as i64)),
+        ("start_time", Value::Int(bsd.pbi_start_tvsec as i64)),
+        ("files", Value::Int(bsd.pbi_nfiles as i64)),
+        (
+            "threads",
+            task.as_ref().map(|t| t.pti_threadnum as i64).into(),
+        ),
+        (
+            "virtual_size",
+            task.as_ref().map(|t| t.pti_virtual_size as i64).into(),
+        ),
+        (
+            "resident_size",
+            task.as_ref().map(|t| t.pti_resident_size as i64).into(),
+        ),
+        ("libversion", libversion.into()),
+    ]])
+}
+
+fn threads(pid: i32) -> io::Result<Vec<Record>> {
+    let task = proc_pid::pidinfo::<TaskInfo>(pid, 0)?;
+    let thread_ids = proc_pid::listpidinfo::<ListThreads>(pid, task.pti_threadnum as usize)?;
+
+    let mut records = Vec::with_capacity(thread_ids.len());
+    for thread_id in thread_ids {
+        // Threads can exit between listing them and asking about them
+        let thread = match proc_pid::pidinfo::<ThreadInfo>(pid, thread_id) {
+            Ok(thread) => thread,
+            Err(ref err) if is_no_such_process(err) => continue,
+            Err(err) => return Err(err),
+        };
+
+        records.push(vec![
+            ("tid", Value::Int(thread_id as i64)),
+            ("name", Value::Str(c_chars_to_string(&thread.pth_name))),
+            ("user_time", Value::Int(thread.pth_user_time as i64)),
+            ("system_time", Value::Int(thread.pth_system_time as i64)),
+            ("cpu_usage", Value::Int(thread.pth_cpu_usage as i64)),
+            ("policy", Value::Int(thread.pth_policy as i64)),
+            ("run_state", Value::Int(thread.pth_run_state as i64)),
+            ("priority", Value::Int(thread.pth_priority as i64)),
+            ("max_priority", Value::Int(thread.pth_maxpriority as i64)),
+        ]);
+    }
+
+    Ok(records)
+}
+
+fn fd_list(pid: i32) -> io::Result<Vec<proc_pid::ProcFDInfo>> {
+    let bsd = proc_pid::pidinfo::<BSDInfo>(pid, 0)?;
+    proc_pid::listpidinfo::<ListFDs>(pid, bsd.pbi_nfiles as usize)
+}
+
+fn fds(pid: i32) -> io::Result<Vec<Record>> {
+    let mut records = vec![];
+    for fd in fd_list(pid)? {
+        let fd_type = ProcFDType::from(fd.proc_fdtype);
+        // The descriptor may have been closed since it was listed
+        let vnode = match fd_type {
+            ProcFDType::VNode => optional(proc_pid::pidfdinfo::<VnodePathFDInfo>(pid, fd.proc_fd))?,
+            _ => None,
+        };
+
+        records.push(vec![
+            ("fd", Value::Int(fd.proc_fd as i64)),
+            ("type", Value::Str(format!("{:?}", fd_type))),
+            (
+                "offset",
+                vnode
+                    .as_ref()
+                    .map_or(Value::Null, |vnode| Value::Int(vnode.pfi.fi_offset)),
+            ),
+            (
+                "path",
+                vnode.as_ref().map_or(Value::Null, |vnode| {
+                    Value::Str(vnode.pvip.path().to_string_lossy().into_owned())
+                }),
+            ),
+        ]);
+    }
+    Ok(records)
+}
+
+// insi_lport and insi_fport hold a 16bit port in network byte order
+fn socket_address(info: &InSockInfo, local: bool) -> String {
+    let (addr, port) = if local {
+        (&info.insi_laddr, info.insi_lport)
+    } else {
+        (&info.insi_faddr, info.insi_fport)
+    };
+    let port = u16::from_be(port as u16);
+
+    // access to the members of `InSIAddr` is unsafe because it is a union type.
+    if info.insi_vflag & INI_IPV6 != 0 {
+        let addr = Ipv6Addr::from(unsafe { addr.ina_6.s6_addr });
+        format!("[{}]:{}", addr, port)
+    } else {
+        let addr = Ipv4Addr::from(u32::from_be(unsafe { addr.ina_46.i46a_addr4.s_addr }));
+        format!("{}:{}", addr, port)
+    }
+}
+
+fn sockets(pid: i32) -> io::Result<Vec<Record>> {
+    let mut records = vec![];
+    for fd in fd_list(pid)? {
+        match fd.proc_fdtype.into() {
+            ProcFDType::Socket => {}
+            _ => continue,
+        }
+
+        // The descriptor may have been closed since it was listed
+        let socket = match optional(proc_pid::pidfdinfo::<SocketFDInfo>(pid, fd.proc_fd))? {
+            Some(socket) => socket,
+            None => continue,
+        };
+
+        let kind = SocketInfoKind::from(socket.psi.soi_kind);
+        // access to the members of `soi_proto` is unsafe because it is a union type.
+        let (local, remote, state) = match kind {
+            SocketInfoKind::In => {
+                let info = unsafe { socket.psi.soi_proto.pri_in };
+                (
+                    Some(socket_address(&info, true)),
+                    Some(socket_address(&info, false)),
+                    None,
+                )
+            }
+            SocketInfoKind::Tcp => {
+                let info = unsafe { socket.psi.soi_proto.pri_tcp };
+                (
+                    Some(socket_address(&info.tcpsi_ini, true)),
+                    Some(socket_address(&info.tcpsi_ini, false)),
+                    Some(format!("{:?}", TcpSIState::from(info.tcpsi_state))),
+                )
+            }
+            _ => (None, None, None),
+        };
+
+        records.push(vec![
+            ("fd", Value::Int(fd.proc_fd as i64)),
+            ("kind", Value::Str(format!("{:?}", kind))),
+            ("protocol", Value::Int(socket.psi.soi_protocol as i64)),
+            ("local", local.into()),
+            ("remote", remote.into()),
+            ("state", state.into()),
+        ]);
+    }
+
+    Ok(records)
+}
+
+fn limits(pid: i32) -> io::Result<Vec<Record>> {
+    let limit = |value: Option<u64>| match value {
+        Some(value) => Value::Int(value as i64),
+        None => Value::Str("unlimited".to_string()),
+    };
+
+    Ok(proc_limits::pidlimits(pid)?
+        .into_iter()
+        .map(|(resource, value)| {
+            vec![
+                ("resource", Value::Str(format!("{:?}", resource))),
+                ("soft", limit(value.soft)),
+                ("hard", limit(value.hard)),
+                ("units", Value::Str(resource.unit().to_string())),
+            ]
+        })
+        .collect())
+}
+
+// One record for each peer of each pipe and Unix domain socket, or with no peer if there is none
+fn peers(pid: i32) -> io::Result<Vec<Record>> {
+    // Fail for a process that does not exist, rather than finding no endpoints for it
+    proc_pid::pidinfo::<BSDInfo>(pid, 0)?;
+    let graph = IpcGraph::build()?;
+
+    let mut records = vec![];
+    for endpoint in graph
+        .endpoints()
+        .iter()
+        .filter(|endpoint| endpoint.pid == pid)
+    {
+        let record = |peer: Option<&IpcEndpoint>| {
+            vec![
+                ("fd", Value::Int(endpoint.fd as i64)),
+                ("kind", Value::Str(format!("{:?}", endpoint.kind))),
+                ("end", endpoint.end.map(|end| format!("{:?}", end)).into()),
+                (
+                    "peer_pid",
+                    peer.map_or(Value::Null, |peer| Value::Int(peer.pid as i64)),
+                ),
+                (
+                    "peer_name",
+                    peer.and_then(|peer| graph.name(peer.pid))
+                        .map(String::from)
+                        .into(),
+                ),
+                (
+                    "peer_fd",
+                    peer.map_or(Value::Null, |peer| Value::Int(peer.fd as i64)),
+                ),
+            ]
+        };
+
+        let peers = graph.peers(pid, endpoint.fd);
+        if peers.is_empty() {
+            records.push(record(None));
+        }
+        records.extend(peers.into_iter().map(|peer| record(Some(peer))));
+    }
+
+    Ok(records)
+}
+
+fn list() -> io::Result<Vec<Record>> {
+    let pids = proc_pid::listpids(ProcType::ProcAllPIDS, 0)?;
+
+    let mut records = Vec::with_capacity(pids.len());
+    for pid in pids {
+        let pid = pid as i32;
+        // Processes that exit while we are listing are simply left out
+        let bsd = match optional(proc_pid::pidinfo::<BSDInfo>(pid, 0)) {
+            Ok(bsd) => bsd,
+            Err(_) => continue,
+        };
+        let name = match optional(proc_pid::name(pid)) {
+            Ok(name) => name.or_else(|| bsd.as_ref().map(|b| c_chars_to_string(&b.pbi_comm))),
+            Err(_) => continue,
+        };
+        let path = match optional(proc_pid::pidpath(pid)) {
+            Ok(path) => path,
+            Err(_) => continue,
+        };
+
+        records.push(vec![
+            ("pid", Value::Int(pid as i64)),
+            ("ppid", bsd.as_ref().map(|b| b.pbi_ppid as i64).into()),
+            ("uid", bsd.as_ref().map(|b| b.pbi_uid as i64).into()),
+            ("name", name.into()),
+            ("path", path.into()),
+        ]);
+    }
+
+    Ok(records)
+}
+
+fn select_fields(record: Record, fields: &[String]) -> Record {
+    if fields.is_empty() {
+        return record;
+    }
+
+    let mut record: Vec<Option<(&'static str, Value)>> = record.into_iter().map(Some).collect();
+    let mut selected = Vec::with_capacity(fields.len());
+    for field in fields {
+        if let Some(entry) = record
+            .iter_mut()
+            .find(|entry| entry.as_ref().is_some_and(|(name, _)| name == field))
+        {
+            selected.push(entry.take().unwrap());
+        }
+    }
+    selected
+}
+
+fn print_records(records: Vec<Record>, options: &Options) -> io::Result<()> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let single = options.command == Command::Info;
+
+    if options.json {
+        let objects: Vec<String> = records
+            .iter()
+            .map(|record| {
+                let members: Vec<String> = record
+                    .iter()
+                    .map(|(name, value)| format!("{}:{}", json_string(name), value.json()))
+                    .collect();
+                format!("{{{}}}", members.join(","))
+            })
+            .collect();
+
+        if single {
+            writeln!(out, "{}", objects.join(","))
+        } else {
+            writeln!(out, "[{}]", objects.join(","))
+        }
+    } else if single {
+        for record in &records {
+            for (name, value) in record {
+                writeln!(out, "{}: {}", name, value.text())?;
+            }
+        }
+        Ok(())
+    } else {
+        let header: Vec<&str> = match records.first() {
+            Some(record) => record.iter().map(|(name, _)| *name).collect(),
+            None => return Ok(()),
+        };
+        writeln!(out, "{}", header.join("\t"))?;
+        for record in &records {
+            let values: Vec<String> = record.iter().map(|(_, value)| value.text()).collect();
+            writeln!(out, "{}", values.join("\t"))?;
+        }
+        Ok(())
+    }
+}
+
+fn usage_error(message: &str) -> ! {
+    writeln!(&mut io::stderr(), "Error: {}\n\n{}", message, USAGE).unwrap();
+    process::exit(EXIT_USAGE);
+}
+
+fn parse_args(args: Vec<String>) -> Options {
+    let mut json = false;
+    let mut fields = vec![];
+    let mut command = None;
+    let mut pid = None;
+
+    let mut args = args.into_iter().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            "--json" => json = true,
+            "--fields" => match args.next() {
+                Some(list) => fields.extend(list.split(',').map(String::from)),
+                None => usage_error("'--fields' requires a comma separated list of fields"),
+            },
+            _ if arg.starts_with("--fields=") => {
+                fields.extend(arg["--fields=".len()..].split(',').map(String::from))
+            }
+            _ if arg.starts_with('-') => usage_error(&format!("Unknown option '{}'", arg)),
+            _ => {
+                if command.is_none() && pid.is_none() {
+                    if let Some(parsed) = Command::parse(&arg) {
+                        command = Some(parsed);
+                        continue;
                     }
                 }
+                if pid.is_some() {
+                    usage_error(&format!("Unexpected argument '{}'", arg));
+                }
+                match arg.parse::<i32>() {
+                    Ok(parsed) => pid = Some(parsed),
+                    Err(_) => usage_error(&format!("'{}' is not a command or a pid", arg)),
+                }
             }
         }
-        Err(err) => writeln!(&mut std::io::stderr(), "Error: {}", err).unwrap(),
     }
+
+    let command = command.unwrap_or(Command::Info);
+    if (command == Command::List || command == Command::Deleted) && pid.is_some() {
+        usage_error("'list' and 'deleted' do not take a pid");
+    }
+
+    fields.retain(|field| !field.is_empty());
+    for field in &fields {
+        if !command.fields().contains(&field.as_str()) {
+            usage_error(&format!(
+                "Unknown field '{}', available fields are: {}",
+                field,
+                command.fields().join(",")
+            ));
+        }
+    }
+
+    Options {
+        json,
+        fields,
+        command,
+        pid: pid.unwrap_or(process::id() as i32),
+    }
+}
+
+fn deleted() -> io::Result<Vec<Record>> {
+    Ok(proc_files::deleted_files()?
+        .into_iter()
+        .map(|file| {
+            vec![
+                ("pid", Value::Int(file.pid as i64)),
+                ("name", proc_pid::name(file.pid).ok().into()),
+                ("fd", Value::Int(file.fd as i64)),
+                ("size", Value::Int(file.size as i64)),
+                ("path", Value::Str(file.path.to_string_lossy().into_owned())),
+            ]
+        })
+        .collect())
 }
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    let pid;
+    let options = parse_args(env::args().collect());
 
-    if args.len() == 1 {
-        pid = getpid();
-    } else {
-        pid = args[1].clone().parse::<i32>().unwrap();
-    }
+    let result = match options.command {
+        Command::Info => info(options.pid),
+        Command::Threads => threads(options.pid),
+        Command::Fds => fds(options.pid),
+        Command::Sockets => sockets(options.pid),
+        Command::Peers => peers(options.pid),
+        Command::Limits => limits(options.pid),
+        Command::List => list(),
+        Command::Deleted => deleted(),
+    };
 
-    procinfo(pid);
+    match result {
+        Ok(records) => {
+            let records = records
+                .into_iter()
+                .map(|record| select_fields(record, &options.fields))
+                .collect();
+            if let Err(err) = print_records(records, &options) {
+                writeln!(&mut io::stderr(), "Error: {}", err).unwrap();
+                process::exit(EXIT_ERROR);
+            }
+        }
+        Err(ref err) if is_no_such_process(err) => {
+            writeln!(&mut io::stderr(), "Error: no such process {}", options.pid).unwrap();
+            process::exit(EXIT_NO_SUCH_PROCESS);
+        }
+        Err(err) => {
+            writeln!(&mut io::stderr(), "Error: {}", err).unwrap();
+            process::exit(EXIT_ERROR);
+        }
+    }
 }
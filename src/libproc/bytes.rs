@@ -0,0 +1,32 @@
+// Reading the native endian fields of kernel structures out of the buffers they are returned in
+#[cfg(target_os = "linux")]
+use std::io::{Error, ErrorKind};
+
+#[cfg(target_os = "linux")]
+pub(crate) fn u16_at(data: &[u8], offset: usize) -> u16 {
+    u16::from_ne_bytes([data[offset], data[offset + 1]])
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) fn u32_at(data: &[u8], offset: usize) -> u32 {
+    let mut bytes = [0u8; 4];
+    bytes.copy_from_slice(&data[offset..offset + 4]);
+    u32::from_ne_bytes(bytes)
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) fn i32_at(data: &[u8], offset: usize) -> i32 {
+    u32_at(data, offset) as i32
+}
+
+pub(crate) fn u64_at(data: &[u8], offset: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&data[offset..offset + 8]);
+    u64::from_ne_bytes(bytes)
+}
+
+// The error for a buffer that ends before the structure in it, such as a "process event"
+#[cfg(target_os = "linux")]
+pub(crate) fn truncated(what: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("Truncated {}", what))
+}
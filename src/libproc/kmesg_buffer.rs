@@ -1,8 +1,17 @@
+#[cfg(target_os = "macos")]
 use libc::c_int;
 
-use std::fmt;
-use std::io::{Error, ErrorKind, Result};
-use std::{mem, ptr};
+#[cfg(target_os = "linux")]
+use std::fs::OpenOptions;
+use std::io::Result;
+#[cfg(target_os = "macos")]
+use std::io::{Error, ErrorKind};
+#[cfg(target_os = "linux")]
+use std::io::{ErrorKind, Read};
+#[cfg(target_os = "linux")]
+use std::os::unix::fs::OpenOptionsExt;
+#[cfg(target_os = "macos")]
+use std::{fmt, mem, ptr};
 
 #[cfg(test)]
 use std::io;
@@ -10,10 +19,13 @@ use std::io;
 use std::io::Write;
 
 // See https://opensource.apple.com/source/xnu/xnu-1456.1.26/bsd/sys/msgbuf.h
-const MAX_MSG_BSIZE: c_int = (1 * 1024 * 1024);
+#[cfg(target_os = "macos")]
+const MAX_MSG_BSIZE: c_int = 1024 * 1024;
+#[cfg(target_os = "macos")]
 const MSG_MAGIC: c_int = 0x063061;
 
 // See /usr/include/sys/msgbuf.h on your Mac.
+#[cfg(target_os = "macos")]
 #[repr(C)]
 struct MessageBuffer {
     pub msg_magic: c_int,
@@ -23,6 +35,7 @@ struct MessageBuffer {
     pub msg_bufc: *mut u8, // buffer
 }
 
+#[cfg(target_os = "macos")]
 impl Default for MessageBuffer {
     fn default() -> MessageBuffer {
         MessageBuffer {
@@ -35,6 +48,7 @@ impl Default for MessageBuffer {
     }
 }
 
+#[cfg(target_os = "macos")]
 impl fmt::Debug for MessageBuffer {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
@@ -45,6 +59,7 @@ impl fmt::Debug for MessageBuffer {
     }
 }
 
+#[cfg(target_os = "macos")]
 // this extern block links to the libproc library
 // Original signatures of functions can be found at http://opensource.apple.com/source/Libc/Libc-594.9.4/darwin/libproc.c
 #[link(name = "proc", kind = "dylib")]
@@ -70,6 +85,7 @@ extern "C" {
 ///         writeln!(&mut std::io::stderr(), "Must be run as root").unwrap()
 ///     }
 // See http://opensource.apple.com//source/system_cmds/system_cmds-336.6/dmesg.tproj/dmesg.c
+#[cfg(target_os = "macos")]
 pub fn kmsgbuf() -> Result<String> {
     let mut message_buffer: MessageBuffer = Default::default();
     let ret: i32;
@@ -157,6 +173,41 @@ pub fn kmsgbuf() -> Result<String> {
     }
 }
 
+/// Get the messages in the kernel message buffer from /dev/kmsg - as used by dmesg
+// Each read returns one record of the form "priority,sequence,timestamp,flags;message", see
+// https://www.kernel.org/doc/Documentation/ABI/testing/dev-kmsg
+#[cfg(target_os = "linux")]
+pub fn kmsgbuf() -> Result<String> {
+    let mut kmsg = OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open("/dev/kmsg")?;
+
+    let mut messages = vec![];
+    let mut record = [0; 8192];
+    loop {
+        match kmsg.read(&mut record) {
+            Ok(0) => break,
+            Ok(len) => {
+                let record = String::from_utf8_lossy(&record[..len]);
+                if let Some(start) = record.find(';') {
+                    // Continuation lines carrying key/value metadata start with a space
+                    if let Some(message) = record[start + 1..].lines().next() {
+                        messages.push(message.to_string());
+                    }
+                }
+            }
+            // No more records to read
+            Err(ref err) if err.kind() == ErrorKind::WouldBlock => break,
+            // The record we were about to read has been overwritten, carry on with the next one
+            Err(ref err) if err.raw_os_error() == Some(libc::EPIPE) => continue,
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(messages.join("\n"))
+}
+
 pub fn am_root() -> bool {
     unsafe { libc::getuid() == 0 }
 }
@@ -167,7 +218,7 @@ fn kmessagebuffer_test() {
     if am_root() {
         match kmsgbuf() {
             Ok(buffer) => println!("Buffer: {:?}", buffer),
-            Err(message) => assert!(false, message),
+            Err(message) => panic!("{}", message),
         }
     } else {
         writeln!(&mut io::stdout(), "test libproc::kmesg_buffer::kmessagebuffer_test ... skipped as it needs to be run as root").unwrap();
@@ -1,2 +1,29 @@
+mod bytes;
 pub mod kmesg_buffer;
+#[cfg(target_os = "linux")]
+pub mod proc_caps;
+#[cfg(target_os = "linux")]
+pub mod proc_cgroup;
+#[cfg(target_os = "linux")]
+pub mod proc_events;
+#[cfg(target_os = "linux")]
+pub mod proc_fdinfo;
+pub mod proc_fdusage;
+pub mod proc_files;
+pub mod proc_handle;
+pub mod proc_ipc;
+pub mod proc_limits;
+pub mod proc_matcher;
+#[cfg(target_os = "linux")]
+pub mod proc_ns;
+#[cfg(target_os = "linux")]
+pub mod proc_oom;
+#[cfg(target_os = "linux")]
+pub mod proc_origin;
 pub mod proc_pid;
+pub mod proc_sched;
+pub mod proc_watcher;
+#[cfg(target_os = "linux")]
+mod procfs;
+#[cfg(target_os = "linux")]
+mod sock_diag;
@@ -0,0 +1,359 @@
+// The capabilities, no_new_privs flag and seccomp mode of processes on Linux
+// See http://man7.org/linux/man-pages/man7/capabilities.7.html
+use std::fmt;
+use std::io::{Error, ErrorKind, Result};
+
+use crate::libproc::procfs;
+
+/// A Linux capability, numbered as in linux/capability.h
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Capability {
+    Chown = 0,
+    DacOverride = 1,
+    DacReadSearch = 2,
+    Fowner = 3,
+    Fsetid = 4,
+    Kill = 5,
+    Setgid = 6,
+    Setuid = 7,
+    Setpcap = 8,
+    LinuxImmutable = 9,
+    NetBindService = 10,
+    NetBroadcast = 11,
+    NetAdmin = 12,
+    NetRaw = 13,
+    IpcLock = 14,
+    IpcOwner = 15,
+    SysModule = 16,
+    SysRawio = 17,
+    SysChroot = 18,
+    SysPtrace = 19,
+    SysPacct = 20,
+    SysAdmin = 21,
+    SysBoot = 22,
+    SysNice = 23,
+    SysResource = 24,
+    SysTime = 25,
+    SysTtyConfig = 26,
+    Mknod = 27,
+    Lease = 28,
+    AuditWrite = 29,
+    AuditControl = 30,
+    Setfcap = 31,
+    MacOverride = 32,
+    MacAdmin = 33,
+    Syslog = 34,
+    WakeAlarm = 35,
+    BlockSuspend = 36,
+    AuditRead = 37,
+    Perfmon = 38,
+    Bpf = 39,
+    CheckpointRestore = 40,
+}
+
+impl Capability {
+    /// Every capability, in the order of their numbers
+    pub fn all() -> &'static [Capability] {
+        &[
+            Capability::Chown,
+            Capability::DacOverride,
+            Capability::DacReadSearch,
+            Capability::Fowner,
+            Capability::Fsetid,
+            Capability::Kill,
+            Capability::Setgid,
+            Capability::Setuid,
+            Capability::Setpcap,
+            Capability::LinuxImmutable,
+            Capability::NetBindService,
+            Capability::NetBroadcast,
+            Capability::NetAdmin,
+            Capability::NetRaw,
+            Capability::IpcLock,
+            Capability::IpcOwner,
+            Capability::SysModule,
+            Capability::SysRawio,
+            Capability::SysChroot,
+            Capability::SysPtrace,
+            Capability::SysPacct,
+            Capability::SysAdmin,
+            Capability::SysBoot,
+            Capability::SysNice,
+            Capability::SysResource,
+            Capability::SysTime,
+            Capability::SysTtyConfig,
+            Capability::Mknod,
+            Capability::Lease,
+            Capability::AuditWrite,
+            Capability::AuditControl,
+            Capability::Setfcap,
+            Capability::MacOverride,
+            Capability::MacAdmin,
+            Capability::Syslog,
+            Capability::WakeAlarm,
+            Capability::BlockSuspend,
+            Capability::AuditRead,
+            Capability::Perfmon,
+            Capability::Bpf,
+            Capability::CheckpointRestore,
+        ]
+    }
+
+    /// The capability with a number, `None` if it is one this crate does not know of
+    pub fn from_number(number: u32) -> Option<Capability> {
+        Capability::all().get(number as usize).copied()
+    }
+
+    /// The number of the capability, which is its bit in a capability set
+    pub fn number(self) -> u32 {
+        self as u32
+    }
+
+    /// The name of the capability, such as "CAP_NET_ADMIN"
+    pub fn name(self) -> &'static str {
+        match self {
+            Capability::Chown => "CAP_CHOWN",
+            Capability::DacOverride => "CAP_DAC_OVERRIDE",
+            Capability::DacReadSearch => "CAP_DAC_READ_SEARCH",
+            Capability::Fowner => "CAP_FOWNER",
+            Capability::Fsetid => "CAP_FSETID",
+            Capability::Kill => "CAP_KILL",
+            Capability::Setgid => "CAP_SETGID",
+            Capability::Setuid => "CAP_SETUID",
+            Capability::Setpcap => "CAP_SETPCAP",
+            Capability::LinuxImmutable => "CAP_LINUX_IMMUTABLE",
+            Capability::NetBindService => "CAP_NET_BIND_SERVICE",
+            Capability::NetBroadcast => "CAP_NET_BROADCAST",
+            Capability::NetAdmin => "CAP_NET_ADMIN",
+            Capability::NetRaw => "CAP_NET_RAW",
+            Capability::IpcLock => "CAP_IPC_LOCK",
+            Capability::IpcOwner => "CAP_IPC_OWNER",
+            Capability::SysModule => "CAP_SYS_MODULE",
+            Capability::SysRawio => "CAP_SYS_RAWIO",
+            Capability::SysChroot => "CAP_SYS_CHROOT",
+            Capability::SysPtrace => "CAP_SYS_PTRACE",
+            Capability::SysPacct => "CAP_SYS_PACCT",
+            Capability::SysAdmin => "CAP_SYS_ADMIN",
+            Capability::SysBoot => "CAP_SYS_BOOT",
+            Capability::SysNice => "CAP_SYS_NICE",
+            Capability::SysResource => "CAP_SYS_RESOURCE",
+            Capability::SysTime => "CAP_SYS_TIME",
+            Capability::SysTtyConfig => "CAP_SYS_TTY_CONFIG",
+            Capability::Mknod => "CAP_MKNOD",
+            Capability::Lease => "CAP_LEASE",
+            Capability::AuditWrite => "CAP_AUDIT_WRITE",
+            Capability::AuditControl => "CAP_AUDIT_CONTROL",
+            Capability::Setfcap => "CAP_SETFCAP",
+            Capability::MacOverride => "CAP_MAC_OVERRIDE",
+            Capability::MacAdmin => "CAP_MAC_ADMIN",
+            Capability::Syslog => "CAP_SYSLOG",
+            Capability::WakeAlarm => "CAP_WAKE_ALARM",
+            Capability::BlockSuspend => "CAP_BLOCK_SUSPEND",
+            Capability::AuditRead => "CAP_AUDIT_READ",
+            Capability::Perfmon => "CAP_PERFMON",
+            Capability::Bpf => "CAP_BPF",
+            Capability::CheckpointRestore => "CAP_CHECKPOINT_RESTORE",
+        }
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A set of capabilities, as the bit mask the kernel keeps it in
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CapabilitySet(pub u64);
+
+impl CapabilitySet {
+    /// Parse the hexadecimal mask of a line such as "CapEff: 000001ffffffffff"
+    pub fn parse(mask: &str) -> Result<CapabilitySet> {
+        u64::from_str_radix(mask.trim(), 16)
+            .map(CapabilitySet)
+            .map_err(|_| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Could not parse capability set '{}'", mask),
+                )
+            })
+    }
+
+    pub fn contains(&self, capability: Capability) -> bool {
+        self.0 & (1 << capability.number()) != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Whether the set has every capability this crate knows of
+    pub fn is_full(&self) -> bool {
+        Capability::all()
+            .iter()
+            .all(|&capability| self.contains(capability))
+    }
+
+    /// The capabilities in the set, in the order of their numbers
+    pub fn capabilities(&self) -> Vec<Capability> {
+        Capability::all()
+            .iter()
+            .copied()
+            .filter(|&capability| self.contains(capability))
+            .collect()
+    }
+
+    /// The numbers of the capabilities in the set that this crate does not know of, which a newer
+    /// kernel may have
+    pub fn unknown(&self) -> Vec<u32> {
+        (Capability::all().len() as u32..64)
+            .filter(|number| self.0 & (1 << number) != 0)
+            .collect()
+    }
+}
+
+impl fmt::Display for CapabilitySet {
+    /// The names of the capabilities, separated by commas
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: Vec<String> = self
+            .capabilities()
+            .iter()
+            .map(|capability| capability.name().to_string())
+            .chain(self.unknown().iter().map(|number| format!("{}", number)))
+            .collect();
+        f.write_str(&names.join(","))
+    }
+}
+
+/// The seccomp mode of a process, see seccomp(2)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SeccompMode {
+    /// The process may make any system call
+    Disabled,
+    /// The process may only read, write, _exit and sigreturn
+    Strict,
+    /// The system calls of the process are checked by BPF filters
+    Filter,
+}
+
+impl SeccompMode {
+    fn from_number(number: u64) -> Result<SeccompMode> {
+        match number {
+            0 => Ok(SeccompMode::Disabled),
+            1 => Ok(SeccompMode::Strict),
+            2 => Ok(SeccompMode::Filter),
+            _ => Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Unknown seccomp mode {}", number),
+            )),
+        }
+    }
+}
+
+/// The privileges of a process: its capability sets, whether it may gain privileges by executing
+/// a set-user-ID program or one with file capabilities, and how its system calls are restricted.
+/// Together with the uids and gids of `BSDInfo` it says what a process may do.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Capabilities {
+    /// The capabilities the kernel checks when the process does something privileged
+    pub effective: CapabilitySet,
+    /// The capabilities the process may make effective
+    pub permitted: CapabilitySet,
+    /// The capabilities kept across an execve(2) of a program with them in its inheritable set
+    pub inheritable: CapabilitySet,
+    /// The limit on the capabilities the process, and any it executes, may gain
+    pub bounding: CapabilitySet,
+    /// The capabilities kept across an execve(2) of a program without file capabilities
+    pub ambient: CapabilitySet,
+    /// Whether the no_new_privs flag is set, see prctl(2) PR_SET_NO_NEW_PRIVS
+    pub no_new_privs: bool,
+    pub seccomp: SeccompMode,
+}
+
+impl Capabilities {
+    /// Parse the contents of /proc/<pid>/status. Lines that older kernels do not have, such as
+    /// "CapAmb" and "NoNewPrivs", are taken to be empty or unset.
+    pub fn parse_procfs(contents: &str) -> Result<Capabilities> {
+        let status = procfs::Status::parse(contents);
+        let set = |key: &str| match status.get(key) {
+            Some(mask) => CapabilitySet::parse(mask),
+            None => Ok(CapabilitySet::default()),
+        };
+
+        Ok(Capabilities {
+            effective: set("CapEff")?,
+            permitted: set("CapPrm")?,
+            inheritable: set("CapInh")?,
+            bounding: set("CapBnd")?,
+            ambient: set("CapAmb")?,
+            no_new_privs: status.number("NoNewPrivs").unwrap_or(0) != 0,
+            seccomp: SeccompMode::from_number(status.number("Seccomp").unwrap_or(0))?,
+        })
+    }
+}
+
+/// Returns the capabilities, no_new_privs flag and seccomp mode of the process with the
+/// specified pid
+///
+/// # Examples
+///
+/// ```
+/// use std::process;
+/// use libproc::libproc::proc_caps::{pidcapabilities, Capability};
+/// use libproc::libproc::proc_pid::{pidinfo, BSDInfo};
+///
+/// let pid = process::id() as i32;
+/// let caps = pidcapabilities(pid).unwrap();
+/// let uid = pidinfo::<BSDInfo>(pid, 0).unwrap().pbi_uid;
+/// println!("uid {} may kill any process: {}", uid, caps.effective.contains(Capability::Kill));
+/// ```
+pub fn pidcapabilities(pid: i32) -> Result<Capabilities> {
+    Capabilities::parse_procfs(&procfs::read_pid_file(pid, "status")?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::process;
+
+    #[test]
+    fn parse_procfs_capabilities_test() {
+        let status = "Name:\tping\nCapInh:\t0000000000000000\nCapPrm:\t0000000000003000\n\
+                      CapEff:\t0000000000002000\nCapBnd:\t000001ffffffffff\n\
+                      CapAmb:\t0000000000000000\nNoNewPrivs:\t1\nSeccomp:\t2\n";
+        let caps = Capabilities::parse_procfs(status).unwrap();
+
+        assert_eq!(caps.effective.capabilities(), vec![Capability::NetRaw]);
+        assert_eq!(
+            caps.permitted.capabilities(),
+            vec![Capability::NetAdmin, Capability::NetRaw]
+        );
+        assert!(caps.inheritable.is_empty());
+        assert!(caps.bounding.is_full());
+        assert!(caps.no_new_privs);
+        assert_eq!(caps.seccomp, SeccompMode::Filter);
+        assert_eq!(caps.permitted.to_string(), "CAP_NET_ADMIN,CAP_NET_RAW");
+
+        assert!(Capabilities::parse_procfs("CapEff:\tnot hex\n").is_err());
+    }
+
+    #[test]
+    fn capability_numbers_test() {
+        for (number, &capability) in Capability::all().iter().enumerate() {
+            assert_eq!(capability.number(), number as u32);
+            assert_eq!(Capability::from_number(number as u32), Some(capability));
+        }
+        assert_eq!(Capability::from_number(64), None);
+        assert_eq!(CapabilitySet(1 << 41 | 1).unknown(), vec![41]);
+        assert_eq!(CapabilitySet(1 << 41 | 1).to_string(), "CAP_CHOWN,41");
+    }
+
+    #[test]
+    fn pidcapabilities_test() {
+        let caps = pidcapabilities(process::id() as i32).unwrap();
+        // The effective set is always a subset of the permitted set
+        assert_eq!(caps.effective.0 & !caps.permitted.0, 0);
+        assert!(!caps.bounding.is_empty());
+    }
+}
@@ -0,0 +1,523 @@
+// The control groups processes are in on Linux, and the resource use of cgroup v2 groups
+// See http://man7.org/linux/man-pages/man7/cgroups.7.html and
+// https://docs.kernel.org/admin-guide/cgroup-v2.html
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+
+use crate::libproc::procfs;
+
+fn invalid_data(what: &str, line: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("Could not parse {} '{}'", what, line),
+    )
+}
+
+/// A line of /proc/<pid>/cgroup: the cgroup a process is in within one hierarchy
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CgroupEntry {
+    /// The id of the hierarchy, 0 for the cgroup v2 unified hierarchy
+    pub hierarchy: u32,
+    /// The cgroup v1 controllers bound to the hierarchy, such as "cpu" or "name=systemd", empty
+    /// for cgroup v2
+    pub controllers: Vec<String>,
+    /// The path of the cgroup, relative to the root of the hierarchy, such as
+    /// "/system.slice/sshd.service"
+    pub path: String,
+}
+
+impl CgroupEntry {
+    /// Whether this is the cgroup v2 unified hierarchy
+    pub fn is_v2(&self) -> bool {
+        self.hierarchy == 0 && self.controllers.is_empty()
+    }
+}
+
+/// Parse the contents of /proc/<pid>/cgroup
+pub fn parse_procfs_cgroups(contents: &str) -> Result<Vec<CgroupEntry>> {
+    contents
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let mut fields = line.splitn(3, ':');
+            match (fields.next(), fields.next(), fields.next()) {
+                (Some(hierarchy), Some(controllers), Some(path)) => Ok(CgroupEntry {
+                    hierarchy: hierarchy
+                        .parse()
+                        .map_err(|_| invalid_data("cgroup line", line))?,
+                    controllers: controllers
+                        .split(',')
+                        .filter(|controller| !controller.is_empty())
+                        .map(String::from)
+                        .collect(),
+                    path: path.to_string(),
+                }),
+                _ => Err(invalid_data("cgroup line", line)),
+            }
+        })
+        .collect()
+}
+
+/// Returns the cgroups, in each hierarchy, that the process with the specified pid is in
+pub fn pidcgroups(pid: i32) -> Result<Vec<CgroupEntry>> {
+    parse_procfs_cgroups(&procfs::read_pid_file(pid, "cgroup")?)
+}
+
+/// Returns the path of the cgroup v2 group the process with the specified pid is in, such as
+/// "/system.slice/sshd.service", `None` if there is no unified hierarchy
+pub fn pidcgroup_path(pid: i32) -> Result<Option<String>> {
+    Ok(pidcgroups(pid)?
+        .into_iter()
+        .find(|entry| entry.is_v2())
+        .map(|entry| entry.path))
+}
+
+// A cgroup filesystem from /proc/self/mountinfo
+#[derive(Debug, PartialEq)]
+struct CgroupMount {
+    // The directory of the hierarchy that is mounted, "/" unless mounted in a cgroup namespace
+    root: String,
+    mount_point: PathBuf,
+    v2: bool,
+    // The super options, which for cgroup v1 name the controllers of the hierarchy
+    options: Vec<String>,
+}
+
+// Spaces, tabs, newlines and backslashes in the paths of mountinfo are escaped as "\ooo" in octal
+fn unescape(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut unescaped = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = match bytes.get(i + 1..i + 4) {
+            Some(digits) if bytes[i] == b'\\' => std::str::from_utf8(digits)
+                .ok()
+                .and_then(|digits| u8::from_str_radix(digits, 8).ok()),
+            _ => None,
+        };
+        match escaped {
+            Some(byte) => {
+                unescaped.push(byte);
+                i += 4;
+            }
+            None => {
+                unescaped.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&unescaped).into_owned()
+}
+
+fn parse_mountinfo(contents: &str) -> Vec<CgroupMount> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let (mount, filesystem) = line.split_once(" - ")?;
+            let mount: Vec<&str> = mount.split(' ').collect();
+            let filesystem: Vec<&str> = filesystem.split(' ').collect();
+            let v2 = match filesystem.first() {
+                Some(&"cgroup2") => true,
+                Some(&"cgroup") => false,
+                _ => return None,
+            };
+            Some(CgroupMount {
+                root: unescape(mount.get(3)?),
+                mount_point: PathBuf::from(unescape(mount.get(4)?)),
+                v2,
+                options: filesystem
+                    .get(2)
+                    .map(|options| options.split(',').map(String::from).collect())
+                    .unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+impl CgroupMount {
+    fn has_hierarchy(&self, entry: &CgroupEntry) -> bool {
+        if entry.is_v2() {
+            self.v2
+        } else {
+            !self.v2
+                && entry
+                    .controllers
+                    .iter()
+                    .all(|controller| self.options.contains(controller))
+        }
+    }
+
+    // The directory of a cgroup of the hierarchy, if it is under the root that is mounted
+    fn directory(&self, path: &str) -> Option<PathBuf> {
+        let relative = if self.root == "/" {
+            path
+        } else {
+            path.strip_prefix(self.root.as_str())
+                .filter(|rest| rest.is_empty() || rest.starts_with('/'))?
+        };
+        Some(self.mount_point.join(relative.trim_start_matches('/')))
+    }
+}
+
+// A limit of a cgroup, such as memory.max, which is "max" when there is no limit
+fn parse_limit(contents: &str) -> Result<Option<u64>> {
+    match contents.trim() {
+        "max" => Ok(None),
+        value => value
+            .parse()
+            .map(Some)
+            .map_err(|_| invalid_data("cgroup limit", value)),
+    }
+}
+
+// The "key value" lines of a file such as cpu.stat
+fn key_values(contents: &str) -> HashMap<&str, u64> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once(' ')?;
+            Some((key, value.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+/// The CPU use of a cgroup, from its cpu.stat. The throttling counts are only there when the cpu
+/// controller is enabled for the group, and are 0 when it is not.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CpuStat {
+    pub usage_usec: u64,
+    pub user_usec: u64,
+    pub system_usec: u64,
+    /// The number of periods of the cpu.max quota that have elapsed
+    pub nr_periods: u64,
+    /// The number of those periods in which the group used all of its quota
+    pub nr_throttled: u64,
+    /// The time the group was stopped from running because it used all of its quota
+    pub throttled_usec: u64,
+}
+
+impl CpuStat {
+    /// Parse the contents of a cpu.stat file
+    pub fn parse(contents: &str) -> CpuStat {
+        let values = key_values(contents);
+        let value = |key: &str| values.get(key).copied().unwrap_or(0);
+        CpuStat {
+            usage_usec: value("usage_usec"),
+            user_usec: value("user_usec"),
+            system_usec: value("system_usec"),
+            nr_periods: value("nr_periods"),
+            nr_throttled: value("nr_throttled"),
+            throttled_usec: value("throttled_usec"),
+        }
+    }
+}
+
+/// The I/O of a cgroup on one device, a line of its io.stat
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct IoStat {
+    pub major: u32,
+    pub minor: u32,
+    pub rbytes: u64,
+    pub wbytes: u64,
+    pub rios: u64,
+    pub wios: u64,
+    /// The bytes discarded
+    pub dbytes: u64,
+    pub dios: u64,
+}
+
+impl IoStat {
+    /// Parse the contents of an io.stat file, such as
+    /// "8:16 rbytes=1459200 wbytes=314773504 rios=192 wios=353 dbytes=0 dios=0"
+    pub fn parse(contents: &str) -> Result<Vec<IoStat>> {
+        contents
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                let mut fields = line.split_whitespace();
+                let (major, minor) = fields
+                    .next()
+                    .and_then(|device| device.split_once(':'))
+                    .and_then(|(major, minor)| Some((major.parse().ok()?, minor.parse().ok()?)))
+                    .ok_or_else(|| invalid_data("io.stat line", line))?;
+
+                let mut stat = IoStat {
+                    major,
+                    minor,
+                    ..IoStat::default()
+                };
+                for field in fields {
+                    let (key, value) = match field.split_once('=') {
+                        Some((key, value)) => (key, value.parse().unwrap_or(0)),
+                        None => continue,
+                    };
+                    match key {
+                        "rbytes" => stat.rbytes = value,
+                        "wbytes" => stat.wbytes = value,
+                        "rios" => stat.rios = value,
+                        "wios" => stat.wios = value,
+                        "dbytes" => stat.dbytes = value,
+                        "dios" => stat.dios = value,
+                        _ => {}
+                    }
+                }
+                Ok(stat)
+            })
+            .collect()
+    }
+}
+
+/// The resource use of a cgroup v2 group. Each is `None` when its controller is not enabled for
+/// the group, as it is not for the root group.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CgroupStats {
+    /// The memory used by the group and its descendants, in bytes
+    pub memory_current: Option<u64>,
+    /// The limit on the memory of the group, `Some(None)` if there is no limit
+    pub memory_max: Option<Option<u64>>,
+    /// The CPU used by the group and its descendants
+    pub cpu: Option<CpuStat>,
+    /// The I/O of the group and its descendants on each device
+    pub io: Option<Vec<IoStat>>,
+    /// The number of processes in the group and its descendants
+    pub pids_current: Option<u64>,
+}
+
+/// A cgroup, as the directory of it in a mounted cgroup filesystem
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::process;
+/// use libproc::libproc::proc_cgroup::Cgroup;
+///
+/// let cgroup = Cgroup::of_pid(process::id() as i32).unwrap();
+/// let stats = cgroup.stats().unwrap();
+/// println!("{:?} bytes of memory used by {:?}", stats.memory_current, cgroup.directory());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cgroup {
+    directory: PathBuf,
+}
+
+impl Cgroup {
+    /// The cgroup at a directory of a cgroup filesystem, such as
+    /// "/sys/fs/cgroup/system.slice/sshd.service"
+    pub fn new<P: AsRef<Path>>(directory: P) -> Cgroup {
+        Cgroup {
+            directory: directory.as_ref().to_path_buf(),
+        }
+    }
+
+    /// The cgroup of a line of /proc/<pid>/cgroup, in the filesystem its hierarchy is mounted on.
+    /// Fails with `NotFound` when that is not mounted.
+    pub fn from_entry(entry: &CgroupEntry) -> Result<Cgroup> {
+        let mounts = parse_mountinfo(&fs::read_to_string("/proc/self/mountinfo")?);
+        mounts
+            .iter()
+            .filter(|mount| mount.has_hierarchy(entry))
+            .find_map(|mount| mount.directory(&entry.path))
+            .map(Cgroup::new)
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::NotFound,
+                    format!("The cgroup filesystem of '{}' is not mounted", entry.path),
+                )
+            })
+    }
+
+    /// The cgroup v2 group with a path such as "/system.slice/sshd.service"
+    pub fn from_path(path: &str) -> Result<Cgroup> {
+        Cgroup::from_entry(&CgroupEntry {
+            hierarchy: 0,
+            controllers: vec![],
+            path: path.to_string(),
+        })
+    }
+
+    /// The cgroup v2 group that the process with the specified pid is in
+    pub fn of_pid(pid: i32) -> Result<Cgroup> {
+        match pidcgroup_path(pid)? {
+            Some(path) => Cgroup::from_path(&path),
+            None => Err(Error::new(
+                ErrorKind::NotFound,
+                "The process is not in a cgroup v2 hierarchy",
+            )),
+        }
+    }
+
+    /// The directory of the cgroup in the cgroup filesystem
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    // The contents of a file of the cgroup, `None` when the controller it is of is not enabled
+    fn read(&self, file: &str) -> Result<Option<String>> {
+        match fs::read_to_string(self.directory.join(file)) {
+            Ok(contents) => Ok(Some(contents)),
+            Err(ref err) if err.kind() == ErrorKind::NotFound && self.directory.exists() => {
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    fn read_number(&self, file: &str) -> Result<Option<u64>> {
+        match self.read(file)? {
+            Some(contents) => contents
+                .trim()
+                .parse()
+                .map(Some)
+                .map_err(|_| invalid_data(file, contents.trim())),
+            None => Ok(None),
+        }
+    }
+
+    /// The resource use of a cgroup v2 group, from its memory.current, memory.max, cpu.stat,
+    /// io.stat and pids.current
+    pub fn stats(&self) -> Result<CgroupStats> {
+        Ok(CgroupStats {
+            memory_current: self.read_number("memory.current")?,
+            memory_max: match self.read("memory.max")? {
+                Some(contents) => Some(parse_limit(&contents)?),
+                None => None,
+            },
+            cpu: self
+                .read("cpu.stat")?
+                .map(|contents| CpuStat::parse(&contents)),
+            io: match self.read("io.stat")? {
+                Some(contents) => Some(IoStat::parse(&contents)?),
+                None => None,
+            },
+            pids_current: self.read_number("pids.current")?,
+        })
+    }
+
+    /// The pids of the processes in the cgroup, not including those in its descendants, from its
+    /// cgroup.procs
+    pub fn pids(&self) -> Result<Vec<i32>> {
+        let contents = fs::read_to_string(self.directory.join("cgroup.procs"))?;
+        let mut pids: Vec<i32> = contents
+            .lines()
+            .filter_map(|pid| pid.trim().parse().ok())
+            .collect();
+        pids.sort_unstable();
+        Ok(pids)
+    }
+}
+
+/// Returns the PIDs of the processes in a cgroup, or any of its descendants, in the manner of
+/// `proc_pid::listpids`. `path` is the path of the cgroup in any hierarchy, v1 or v2, such as
+/// "/system.slice/sshd.service", as /proc/<pid>/cgroup shows it.
+///
+/// Unlike `Cgroup::pids` this does not need the cgroup filesystem to be mounted.
+pub fn listpids_in_cgroup(path: &str) -> Result<Vec<u32>> {
+    let prefix = format!("{}/", path.trim_end_matches('/'));
+    let in_cgroup = |entry: &CgroupEntry| entry.path == path || entry.path.starts_with(&prefix);
+
+    Ok(procfs::pids()?
+        .into_iter()
+        .filter(|&pid| pidcgroups(pid).is_ok_and(|entries| entries.iter().any(in_cgroup)))
+        .map(|pid| pid as u32)
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::process;
+
+    #[test]
+    fn parse_procfs_cgroups_test() {
+        let contents = "12:pids:/user.slice/user-1000.slice\n\
+                        4:cpu,cpuacct:/user.slice\n\
+                        1:name=systemd:/user.slice/user-1000.slice/session-2.scope\n\
+                        0::/user.slice/user-1000.slice/session-2.scope\n";
+        let entries = parse_procfs_cgroups(contents).unwrap();
+
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[1].controllers, vec!["cpu", "cpuacct"]);
+        assert!(!entries[2].is_v2());
+        assert!(entries[3].is_v2());
+        assert_eq!(
+            entries[3].path,
+            "/user.slice/user-1000.slice/session-2.scope"
+        );
+        assert!(parse_procfs_cgroups("not a cgroup line\n").is_err());
+    }
+
+    #[test]
+    fn unescape_test() {
+        assert_eq!(unescape("/mnt/my\\040dir"), "/mnt/my dir");
+        assert_eq!(unescape("/a\\011b\\012c\\134d"), "/a\tb\nc\\d");
+        assert_eq!(unescape("/not\\08octal\\"), "/not\\08octal\\");
+    }
+
+    #[test]
+    fn parse_mountinfo_test() {
+        let contents = "25 19 0:22 / /sys/fs/cgroup rw - cgroup2 cgroup2 rw,nsdelegate\n\
+                        33 32 0:29 / /sys/fs/cgroup/cpu rw - cgroup cgroup rw,cpu,cpuacct\n\
+                        40 25 0:40 /kubepods/pod1 /sys/fs/cgroup rw shared:1 - cgroup2 cgroup2 rw\n\
+                        22 1 8:1 / / rw - ext4 /dev/sda1 rw\n";
+        let mounts = parse_mountinfo(contents);
+        assert_eq!(mounts.len(), 3);
+
+        let cpu = CgroupEntry {
+            hierarchy: 4,
+            controllers: vec!["cpu".to_string()],
+            path: "/user.slice".to_string(),
+        };
+        assert!(!mounts[0].has_hierarchy(&cpu));
+        assert!(mounts[1].has_hierarchy(&cpu));
+        assert_eq!(
+            mounts[1].directory(&cpu.path),
+            Some(PathBuf::from("/sys/fs/cgroup/cpu/user.slice"))
+        );
+        assert_eq!(
+            mounts[2].directory("/kubepods/pod1/ctr"),
+            Some(PathBuf::from("/sys/fs/cgroup/ctr"))
+        );
+        assert_eq!(mounts[2].directory("/kubepods/pod12"), None);
+    }
+
+    #[test]
+    fn parse_stats_test() {
+        let cpu = CpuStat::parse(
+            "usage_usec 516540422\nuser_usec 413531357\nsystem_usec 103009065\n\
+             nr_periods 10\nnr_throttled 2\nthrottled_usec 3000\n",
+        );
+        assert_eq!(cpu.usage_usec, 516_540_422);
+        assert_eq!(cpu.nr_throttled, 2);
+
+        let io = IoStat::parse(
+            "8:16 rbytes=1459200 wbytes=314773504 rios=192 wios=353 dbytes=0 dios=0\n\
+             253:0 rbytes=4096 wbytes=0 rios=1 wios=0 dbytes=0 dios=0\n",
+        )
+        .unwrap();
+        assert_eq!(io.len(), 2);
+        assert_eq!((io[0].major, io[0].minor), (8, 16));
+        assert_eq!(io[0].wbytes, 314_773_504);
+        assert_eq!(io[1].rios, 1);
+
+        assert_eq!(parse_limit("max\n").unwrap(), None);
+        assert_eq!(parse_limit("536870912\n").unwrap(), Some(536_870_912));
+    }
+
+    #[test]
+    fn pid_cgroup_test() {
+        let pid = process::id() as i32;
+        let entries = pidcgroups(pid).unwrap();
+        assert!(!entries.is_empty());
+
+        let path = &entries[0].path;
+        let pids = listpids_in_cgroup(path).unwrap();
+        assert!(pids.contains(&(pid as u32)));
+
+        // The cgroup filesystem may not be mounted, as in some containers
+        if let Ok(cgroup) = Cgroup::of_pid(pid) {
+            assert!(cgroup.stats().is_ok());
+        }
+    }
+}
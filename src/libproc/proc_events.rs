@@ -0,0 +1,690 @@
+// Process lifecycle events from the Linux kernel's process events connector
+// See https://www.kernel.org/doc/html/latest/driver-api/connector.html and linux/cn_proc.h
+use libc::{c_int, c_void, sa_family_t};
+use std::collections::VecDeque;
+use std::io::{Error, ErrorKind, Result};
+use std::mem;
+use std::time::Duration;
+
+use crate::libproc::bytes::{i32_at, truncated, u16_at, u32_at, u64_at};
+use crate::libproc::proc_caps::{self, Capability};
+
+// From linux/netlink.h and linux/connector.h
+const NETLINK_CONNECTOR: c_int = 11;
+const CN_IDX_PROC: u32 = 1;
+const CN_VAL_PROC: u32 = 1;
+const NLMSG_ERROR: u16 = 2;
+const NLMSG_DONE: u16 = 3;
+const NLMSG_OVERRUN: u16 = 4;
+
+// From linux/cn_proc.h
+const PROC_CN_MCAST_LISTEN: u32 = 1;
+const PROC_CN_MCAST_IGNORE: u32 = 2;
+
+const PROC_EVENT_NONE: u32 = 0x0000_0000;
+const PROC_EVENT_FORK: u32 = 0x0000_0001;
+const PROC_EVENT_EXEC: u32 = 0x0000_0002;
+const PROC_EVENT_UID: u32 = 0x0000_0004;
+const PROC_EVENT_GID: u32 = 0x0000_0040;
+const PROC_EVENT_SID: u32 = 0x0000_0080;
+const PROC_EVENT_PTRACE: u32 = 0x0000_0100;
+const PROC_EVENT_COMM: u32 = 0x0000_0200;
+const PROC_EVENT_NONZERO_EXIT: u32 = 0x2000_0000;
+const PROC_EVENT_COREDUMP: u32 = 0x4000_0000;
+const PROC_EVENT_EXIT: u32 = 0x8000_0000;
+
+// Sizes of struct nlmsghdr, struct cn_msg and the header of struct proc_event
+const NLMSG_HDRLEN: usize = 16;
+const CN_MSG_LEN: usize = 20;
+const PROC_EVENT_HDRLEN: usize = 16;
+
+const RECEIVE_BUFFER_SIZE: usize = 8192;
+
+// How long to wait for the kernel to acknowledge a subscription
+const ACK_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[repr(C)]
+struct SockaddrNl {
+    nl_family: sa_family_t,
+    nl_pad: u16,
+    nl_pid: u32,
+    nl_groups: u32,
+}
+
+/// The kind of change to a process that a `ProcEvent` reports, with the ids involved
+///
+/// `pid` is the id of the thread that changed and `tgid` the id of the process it belongs to, the
+/// two are equal for the main thread of a process.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProcEventKind {
+    /// A new process or thread was created
+    Fork {
+        parent_pid: i32,
+        parent_tgid: i32,
+        child_pid: i32,
+        child_tgid: i32,
+    },
+    /// A process executed a new program
+    Exec { pid: i32, tgid: i32 },
+    /// The real or effective user id of a process changed
+    Uid {
+        pid: i32,
+        tgid: i32,
+        ruid: u32,
+        euid: u32,
+    },
+    /// The real or effective group id of a process changed
+    Gid {
+        pid: i32,
+        tgid: i32,
+        rgid: u32,
+        egid: u32,
+    },
+    /// A process started a new session with setsid(2)
+    Sid { pid: i32, tgid: i32 },
+    /// A process was attached to or detached from a tracer, a `tracer_pid` of 0 means detached
+    Ptrace {
+        pid: i32,
+        tgid: i32,
+        tracer_pid: i32,
+        tracer_tgid: i32,
+    },
+    /// The command name of a process changed, for example with prctl(PR_SET_NAME)
+    Comm { pid: i32, tgid: i32, comm: String },
+    /// A process dumped core
+    Coredump { pid: i32, tgid: i32 },
+    /// A process or thread exited
+    ///
+    /// `exit_code` is the status as returned by wait(2), so use `libc::WIFEXITED` and friends to
+    /// decode it, or `exited_with` and `killed_by`.
+    Exit {
+        pid: i32,
+        tgid: i32,
+        exit_code: u32,
+        exit_signal: u32,
+    },
+    /// An event type this library does not know about
+    Unknown { what: u32 },
+}
+
+impl ProcEventKind {
+    /// The exit status passed to exit(2) for an `Exit` event of a process that exited normally
+    pub fn exited_with(&self) -> Option<i32> {
+        match *self {
+            ProcEventKind::Exit { exit_code, .. } if exit_code & 0x7f == 0 => {
+                Some(((exit_code >> 8) & 0xff) as i32)
+            }
+            _ => None,
+        }
+    }
+
+    /// The signal that terminated the process for an `Exit` event of a process that was killed
+    pub fn killed_by(&self) -> Option<i32> {
+        match *self {
+            ProcEventKind::Exit { exit_code, .. } if exit_code & 0x7f != 0 => {
+                Some((exit_code & 0x7f) as i32)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A change to a process reported by the kernel
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProcEvent {
+    /// The cpu the event happened on
+    pub cpu: u32,
+    /// When the event happened, in nanoseconds since boot
+    pub timestamp_ns: u64,
+    pub kind: ProcEventKind,
+}
+
+// What the kernel sends back for a subscription request, which is a proc_event of type NONE
+enum Message {
+    Event(ProcEvent),
+    Ack(u32),
+}
+
+impl ProcEvent {
+    /// Parse a `struct proc_event`, the payload of a process events connector message
+    pub fn parse(data: &[u8]) -> Result<ProcEvent> {
+        match ProcEvent::parse_message(data)? {
+            Message::Event(event) => Ok(event),
+            Message::Ack(_) => Err(Error::new(
+                ErrorKind::InvalidData,
+                "Not a process event but an acknowledgement",
+            )),
+        }
+    }
+
+    fn parse_message(data: &[u8]) -> Result<Message> {
+        if data.len() < PROC_EVENT_HDRLEN {
+            return Err(truncated("process event"));
+        }
+        let what = u32_at(data, 0);
+        let cpu = u32_at(data, 4);
+        let timestamp_ns = u64_at(data, 8);
+        let event = &data[PROC_EVENT_HDRLEN..];
+
+        // The size of the event data for each type, so we can check it is all there
+        let needed = match what {
+            PROC_EVENT_NONE => 4,
+            PROC_EVENT_FORK | PROC_EVENT_UID | PROC_EVENT_GID | PROC_EVENT_PTRACE => 16,
+            PROC_EVENT_EXEC | PROC_EVENT_SID | PROC_EVENT_COREDUMP => 8,
+            PROC_EVENT_COMM => 24,
+            PROC_EVENT_EXIT | PROC_EVENT_NONZERO_EXIT => 16,
+            _ => 0,
+        };
+        if event.len() < needed {
+            return Err(truncated("process event"));
+        }
+
+        let pid = || i32_at(event, 0);
+        let tgid = || i32_at(event, 4);
+        let kind = match what {
+            PROC_EVENT_NONE => return Ok(Message::Ack(u32_at(event, 0))),
+            PROC_EVENT_FORK => ProcEventKind::Fork {
+                parent_pid: i32_at(event, 0),
+                parent_tgid: i32_at(event, 4),
+                child_pid: i32_at(event, 8),
+                child_tgid: i32_at(event, 12),
+            },
+            PROC_EVENT_EXEC => ProcEventKind::Exec {
+                pid: pid(),
+                tgid: tgid(),
+            },
+            PROC_EVENT_UID => ProcEventKind::Uid {
+                pid: pid(),
+                tgid: tgid(),
+                ruid: u32_at(event, 8),
+                euid: u32_at(event, 12),
+            },
+            PROC_EVENT_GID => ProcEventKind::Gid {
+                pid: pid(),
+                tgid: tgid(),
+                rgid: u32_at(event, 8),
+                egid: u32_at(event, 12),
+            },
+            PROC_EVENT_SID => ProcEventKind::Sid {
+                pid: pid(),
+                tgid: tgid(),
+            },
+            PROC_EVENT_PTRACE => ProcEventKind::Ptrace {
+                pid: pid(),
+                tgid: tgid(),
+                tracer_pid: i32_at(event, 8),
+                tracer_tgid: i32_at(event, 12),
+            },
+            PROC_EVENT_COMM => {
+                let comm = &event[8..24];
+                let len = comm.iter().position(|&c| c == 0).unwrap_or(comm.len());
+                ProcEventKind::Comm {
+                    pid: pid(),
+                    tgid: tgid(),
+                    comm: String::from_utf8_lossy(&comm[..len]).into_owned(),
+                }
+            }
+            PROC_EVENT_COREDUMP => ProcEventKind::Coredump {
+                pid: pid(),
+                tgid: tgid(),
+            },
+            PROC_EVENT_EXIT | PROC_EVENT_NONZERO_EXIT => ProcEventKind::Exit {
+                pid: pid(),
+                tgid: tgid(),
+                exit_code: u32_at(event, 8),
+                exit_signal: u32_at(event, 12),
+            },
+            what => ProcEventKind::Unknown { what },
+        };
+
+        Ok(Message::Event(ProcEvent {
+            cpu,
+            timestamp_ns,
+            kind,
+        }))
+    }
+}
+
+// Build a netlink message to the process events connector asking it to start or stop sending
+fn mcast_request(op: u32) -> Vec<u8> {
+    let len = NLMSG_HDRLEN + CN_MSG_LEN + 4;
+    let mut request = Vec::with_capacity(len);
+
+    // struct nlmsghdr
+    request.extend_from_slice(&(len as u32).to_ne_bytes());
+    request.extend_from_slice(&NLMSG_DONE.to_ne_bytes());
+    request.extend_from_slice(&0u16.to_ne_bytes()); // flags
+    request.extend_from_slice(&0u32.to_ne_bytes()); // seq
+    request.extend_from_slice(&std::process::id().to_ne_bytes());
+
+    // struct cn_msg
+    request.extend_from_slice(&CN_IDX_PROC.to_ne_bytes());
+    request.extend_from_slice(&CN_VAL_PROC.to_ne_bytes());
+    request.extend_from_slice(&0u32.to_ne_bytes()); // seq
+    request.extend_from_slice(&0u32.to_ne_bytes()); // ack
+    request.extend_from_slice(&4u16.to_ne_bytes()); // len
+    request.extend_from_slice(&0u16.to_ne_bytes()); // flags
+
+    // enum proc_cn_mcast_op
+    request.extend_from_slice(&op.to_ne_bytes());
+    request
+}
+
+// Split a datagram into the proc_event payloads of the netlink messages in it
+fn payloads(datagram: &[u8]) -> Result<Vec<&[u8]>> {
+    let mut payloads = vec![];
+    let mut offset = 0;
+    // The last message need not be padded to the alignment, so offset can pass the end
+    while offset + NLMSG_HDRLEN <= datagram.len() {
+        let len = u32_at(datagram, offset) as usize;
+        let message_type = u16_at(datagram, offset + 4);
+        if len < NLMSG_HDRLEN || offset + len > datagram.len() {
+            return Err(truncated("process event"));
+        }
+
+        match message_type {
+            NLMSG_ERROR if len < NLMSG_HDRLEN + 4 => return Err(truncated("process event")),
+            NLMSG_ERROR => {
+                let errno = i32_at(datagram, offset + NLMSG_HDRLEN);
+                if errno != 0 {
+                    return Err(Error::from_raw_os_error(-errno));
+                }
+            }
+            NLMSG_OVERRUN => return Err(Error::from_raw_os_error(libc::ENOBUFS)),
+            _ if len >= NLMSG_HDRLEN + CN_MSG_LEN => {
+                payloads.push(&datagram[offset + NLMSG_HDRLEN + CN_MSG_LEN..offset + len])
+            }
+            _ => {}
+        }
+
+        // Messages are aligned to 4 bytes
+        offset += (len + 3) & !3;
+    }
+    Ok(payloads)
+}
+
+fn permission_denied() -> Error {
+    Error::new(
+        ErrorKind::PermissionDenied,
+        "Listening for process events requires the CAP_NET_ADMIN capability",
+    )
+}
+
+/// A subscription to the process events connector of the Linux kernel, which reports processes
+/// being created, executing programs, changing ids and exiting as they happen.
+///
+/// Many kernels only allow processes with the `CAP_NET_ADMIN` capability (usually that means
+/// running as root) to subscribe, and `subscribe` returns an error of kind `PermissionDenied` when
+/// that is the reason it failed.
+///
+/// Events are only sent while at least one process is subscribed, so nothing that happened
+/// before `subscribe` is reported. If events arrive faster than they are read the kernel drops
+/// them, and the next read returns the raw OS error ENOBUFS before carrying on.
+///
+/// # Examples
+///
+/// ```no_run
+/// use libproc::libproc::proc_events::{ProcEventKind, ProcEventSubscriber};
+///
+/// let subscriber = ProcEventSubscriber::subscribe().unwrap();
+/// for event in subscriber {
+///     if let Ok(ProcEventKind::Exec { pid, .. }) = event.map(|event| event.kind) {
+///         println!("{} executed a new program", pid);
+///     }
+/// }
+/// ```
+pub struct ProcEventSubscriber {
+    fd: c_int,
+    buffer: Vec<u8>,
+    pending: VecDeque<ProcEvent>,
+}
+
+impl ProcEventSubscriber {
+    /// Subscribe to process events
+    pub fn subscribe() -> Result<ProcEventSubscriber> {
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+                NETLINK_CONNECTOR,
+            )
+        };
+        if fd < 0 {
+            return Err(Error::last_os_error());
+        }
+
+        let mut subscriber = ProcEventSubscriber {
+            fd,
+            buffer: vec![0; RECEIVE_BUFFER_SIZE],
+            pending: VecDeque::new(),
+        };
+
+        let address = SockaddrNl {
+            nl_family: libc::AF_NETLINK as sa_family_t,
+            nl_pad: 0,
+            nl_pid: 0,
+            nl_groups: CN_IDX_PROC,
+        };
+        let ret = unsafe {
+            libc::bind(
+                fd,
+                &address as *const SockaddrNl as *const libc::sockaddr,
+                mem::size_of::<SockaddrNl>() as libc::socklen_t,
+            )
+        };
+        if ret != 0 {
+            return Err(subscribe_error(Error::last_os_error()));
+        }
+
+        subscriber
+            .send(PROC_CN_MCAST_LISTEN)
+            .map_err(subscribe_error)?;
+        subscriber.wait_for_ack()?;
+        Ok(subscriber)
+    }
+
+    fn send(&self, op: u32) -> Result<()> {
+        let request = mcast_request(op);
+        let ret =
+            unsafe { libc::send(self.fd, request.as_ptr() as *const c_void, request.len(), 0) };
+        if ret < 0 {
+            Err(Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+
+    // Newer kernels acknowledge the request, with EPERM if we lack CAP_NET_ADMIN. Any events that
+    // arrive before the acknowledgement are kept to be returned later.
+    fn wait_for_ack(&mut self) -> Result<()> {
+        self.set_timeout(Some(ACK_TIMEOUT))?;
+        let result = loop {
+            match self.receive() {
+                Ok(Some(0)) => break Ok(()),
+                Ok(Some(errno)) if errno as c_int == libc::EPERM => break Err(permission_denied()),
+                Ok(Some(errno)) => break Err(Error::from_raw_os_error(errno as c_int)),
+                Ok(None) => continue,
+                Err(ref err)
+                    if err.kind() == ErrorKind::WouldBlock || err.kind() == ErrorKind::TimedOut =>
+                {
+                    break Err(no_ack())
+                }
+                Err(err) => break Err(err),
+            }
+        };
+        self.set_timeout(None)?;
+        result
+    }
+
+    /// Stop waiting for the next event after `timeout`, or never if that is `None`, which is the
+    /// default. A read that times out returns an error of kind `WouldBlock`.
+    pub fn set_timeout(&self, timeout: Option<Duration>) -> Result<()> {
+        let timeout = timeout.unwrap_or_else(|| Duration::from_secs(0));
+        let timeval = libc::timeval {
+            tv_sec: timeout.as_secs() as libc::time_t,
+            tv_usec: timeout.subsec_micros() as libc::suseconds_t,
+        };
+        let ret = unsafe {
+            libc::setsockopt(
+                self.fd,
+                libc::SOL_SOCKET,
+                libc::SO_RCVTIMEO,
+                &timeval as *const libc::timeval as *const c_void,
+                mem::size_of::<libc::timeval>() as libc::socklen_t,
+            )
+        };
+        if ret != 0 {
+            Err(Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+
+    // Read one datagram, queueing the events in it and returning the error of any acknowledgement
+    fn receive(&mut self) -> Result<Option<u32>> {
+        let len = loop {
+            let ret = unsafe {
+                libc::recv(
+                    self.fd,
+                    self.buffer.as_mut_ptr() as *mut c_void,
+                    self.buffer.len(),
+                    0,
+                )
+            };
+            if ret >= 0 {
+                break ret as usize;
+            }
+            let err = Error::last_os_error();
+            if err.kind() != ErrorKind::Interrupted {
+                return Err(err);
+            }
+        };
+
+        let mut ack = None;
+        for payload in payloads(&self.buffer[..len])? {
+            match ProcEvent::parse_message(payload)? {
+                Message::Event(event) => self.pending.push_back(event),
+                Message::Ack(errno) => ack = Some(errno),
+            }
+        }
+        Ok(ack)
+    }
+
+    /// Wait for the next event
+    pub fn next_event(&mut self) -> Result<ProcEvent> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Ok(event);
+            }
+            self.receive()?;
+        }
+    }
+}
+
+impl Iterator for ProcEventSubscriber {
+    type Item = Result<ProcEvent>;
+
+    /// Waits for the next event, this never returns `None`
+    fn next(&mut self) -> Option<Result<ProcEvent>> {
+        Some(self.next_event())
+    }
+}
+
+impl Drop for ProcEventSubscriber {
+    fn drop(&mut self) {
+        // Let the kernel stop sending events once nobody is listening
+        let _ = self.send(PROC_CN_MCAST_IGNORE);
+        unsafe {
+            libc::close(self.fd);
+        }
+    }
+}
+
+// Some kernels ignore, rather than refuse, subscriptions from processes without CAP_NET_ADMIN
+fn no_ack() -> Error {
+    let pid = std::process::id() as i32;
+    match proc_caps::pidcapabilities(pid) {
+        Ok(caps) if !caps.effective.contains(Capability::NetAdmin) => permission_denied(),
+        _ => Error::new(
+            ErrorKind::TimedOut,
+            "The kernel did not acknowledge the subscription to process events",
+        ),
+    }
+}
+
+fn subscribe_error(err: Error) -> Error {
+    match err.raw_os_error() {
+        Some(libc::EPERM) | Some(libc::EACCES) => permission_denied(),
+        _ => err,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn event(what: u32, data: &[u32]) -> Vec<u8> {
+        let mut bytes = vec![];
+        bytes.extend_from_slice(&what.to_ne_bytes());
+        bytes.extend_from_slice(&3u32.to_ne_bytes());
+        bytes.extend_from_slice(&123_456_789u64.to_ne_bytes());
+        for value in data {
+            bytes.extend_from_slice(&value.to_ne_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn parse_fork_test() {
+        let event = ProcEvent::parse(&event(PROC_EVENT_FORK, &[100, 100, 200, 200])).unwrap();
+        assert_eq!(event.cpu, 3);
+        assert_eq!(event.timestamp_ns, 123_456_789);
+        assert_eq!(
+            event.kind,
+            ProcEventKind::Fork {
+                parent_pid: 100,
+                parent_tgid: 100,
+                child_pid: 200,
+                child_tgid: 200
+            }
+        );
+    }
+
+    #[test]
+    fn parse_exit_test() {
+        // exit(2) as reported by wait(2), and killed by SIGKILL
+        let exited = ProcEvent::parse(&event(PROC_EVENT_EXIT, &[7, 7, 2 << 8, 17, 1, 1])).unwrap();
+        assert_eq!(
+            exited.kind,
+            ProcEventKind::Exit {
+                pid: 7,
+                tgid: 7,
+                exit_code: 2 << 8,
+                exit_signal: 17
+            }
+        );
+        assert_eq!(exited.kind.exited_with(), Some(2));
+        assert_eq!(exited.kind.killed_by(), None);
+
+        let killed = ProcEvent::parse(&event(PROC_EVENT_EXIT, &[8, 8, 9, 17])).unwrap();
+        assert_eq!(killed.kind.exited_with(), None);
+        assert_eq!(killed.kind.killed_by(), Some(9));
+    }
+
+    #[test]
+    fn parse_comm_test() {
+        let mut bytes = event(PROC_EVENT_COMM, &[42, 41]);
+        bytes.extend_from_slice(b"worker\0\0\0\0\0\0\0\0\0\0");
+        let event = ProcEvent::parse(&bytes).unwrap();
+        assert_eq!(
+            event.kind,
+            ProcEventKind::Comm {
+                pid: 42,
+                tgid: 41,
+                comm: "worker".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn parse_ids_test() {
+        let uid = ProcEvent::parse(&event(PROC_EVENT_UID, &[5, 5, 1000, 0])).unwrap();
+        assert_eq!(
+            uid.kind,
+            ProcEventKind::Uid {
+                pid: 5,
+                tgid: 5,
+                ruid: 1000,
+                euid: 0
+            }
+        );
+
+        let ptrace = ProcEvent::parse(&event(PROC_EVENT_PTRACE, &[5, 5, 9, 9])).unwrap();
+        assert_eq!(
+            ptrace.kind,
+            ProcEventKind::Ptrace {
+                pid: 5,
+                tgid: 5,
+                tracer_pid: 9,
+                tracer_tgid: 9
+            }
+        );
+    }
+
+    #[test]
+    fn parse_truncated_test() {
+        assert!(ProcEvent::parse(&event(PROC_EVENT_FORK, &[1, 1])).is_err());
+        assert!(ProcEvent::parse(&[0, 0]).is_err());
+    }
+
+    #[test]
+    fn parse_unknown_test() {
+        let event = ProcEvent::parse(&event(0x1000, &[])).unwrap();
+        assert_eq!(event.kind, ProcEventKind::Unknown { what: 0x1000 });
+    }
+
+    #[test]
+    fn payloads_test() {
+        let mut datagram = mcast_request(PROC_CN_MCAST_LISTEN);
+        datagram.extend(mcast_request(PROC_CN_MCAST_IGNORE));
+        let found = payloads(&datagram).unwrap();
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0], &PROC_CN_MCAST_LISTEN.to_ne_bytes());
+        assert_eq!(found[1], &PROC_CN_MCAST_IGNORE.to_ne_bytes());
+
+        // A last message whose length is not a multiple of 4 is not padded
+        let mut datagram = mcast_request(PROC_CN_MCAST_LISTEN);
+        datagram.push(7);
+        let len = datagram.len() as u32;
+        datagram[..4].copy_from_slice(&len.to_ne_bytes());
+        let found = payloads(&datagram).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].len(), 5);
+        assert_eq!(found[0][4], 7);
+
+        // A message longer than what was received is an error
+        let mut datagram = mcast_request(PROC_CN_MCAST_LISTEN);
+        datagram.truncate(datagram.len() - 1);
+        assert_eq!(
+            payloads(&datagram).unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+
+        // As is an error message without its error number
+        let mut datagram = vec![0u8; NLMSG_HDRLEN];
+        datagram[..4].copy_from_slice(&(NLMSG_HDRLEN as u32).to_ne_bytes());
+        datagram[4..6].copy_from_slice(&NLMSG_ERROR.to_ne_bytes());
+        assert_eq!(
+            payloads(&datagram).unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn subscribe_test() {
+        match ProcEventSubscriber::subscribe() {
+            Ok(mut subscriber) => {
+                // Starting a process must produce events, at least for the fork
+                subscriber
+                    .set_timeout(Some(Duration::from_secs(5)))
+                    .unwrap();
+                let mut child = std::process::Command::new("true").spawn().unwrap();
+                let child_pid = child.id() as i32;
+                child.wait().unwrap();
+
+                loop {
+                    match subscriber.next_event() {
+                        Ok(ProcEvent {
+                            kind: ProcEventKind::Fork { child_tgid, .. },
+                            ..
+                        }) if child_tgid == child_pid => break,
+                        Ok(_) => {}
+                        Err(err) => panic!("Error waiting for the fork event: {}", err),
+                    }
+                }
+            }
+            // Not being root, or running where the connector is not available, is not a failure
+            Err(err) => println!("Could not subscribe to process events: {}", err),
+        }
+    }
+}
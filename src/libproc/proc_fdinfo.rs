@@ -0,0 +1,514 @@
+// Details of file descriptors from /proc/<pid>/fdinfo/<fd>, which only Linux has
+// See "/proc/<pid>/fdinfo/<fd>" in https://www.kernel.org/doc/Documentation/filesystems/proc.txt
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+
+use crate::libproc::proc_pid::{PIDFDInfo, PidFDInfoFlavor};
+use crate::libproc::procfs;
+
+/// The "key: value" pairs of an fdinfo file. Most lines hold one pair, but the lines of the
+/// epoll targets and inotify watches hold several.
+pub(crate) struct FDInfoLines {
+    lines: Vec<Vec<(String, String)>>,
+}
+
+impl FDInfoLines {
+    pub(crate) fn parse(contents: &str) -> FDInfoLines {
+        FDInfoLines {
+            lines: contents.lines().map(FDInfoLines::parse_line).collect(),
+        }
+    }
+
+    // Pairs are either "key: value", with any amount of whitespace after the colon, or
+    // "key:value". Words without a colon, such as the "inotify" that starts a watch, are skipped.
+    fn parse_line(line: &str) -> Vec<(String, String)> {
+        // A few keys have a space in them
+        let line = line.replacen("settime flags:", "settime_flags:", 1);
+
+        let mut pairs = vec![];
+        let mut words = line.split_whitespace();
+        while let Some(word) = words.next() {
+            if let Some(key) = word.strip_suffix(':') {
+                pairs.push((key.to_string(), words.next().unwrap_or("").to_string()));
+            } else if let Some((key, value)) = word.split_once(':') {
+                pairs.push((key.to_string(), value.to_string()));
+            }
+        }
+        pairs
+    }
+
+    /// The value of the first line starting with `key`
+    pub(crate) fn get(&self, key: &str) -> Option<&str> {
+        self.lines
+            .iter()
+            .filter_map(|line| line.first())
+            .find(|(line_key, _)| line_key == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The lines that start with `key`, such as the "tfd" line of each epoll target
+    fn lines_starting(&self, key: &str) -> impl Iterator<Item = &Vec<(String, String)>> {
+        let key = key.to_string();
+        self.lines
+            .iter()
+            .filter(move |line| line.first().is_some_and(|(first, _)| *first == key))
+    }
+
+    // The whole of values such as "(0, 49406829)" are needed, not just their first word
+    fn get_raw<'a>(contents: &'a str, key: &str) -> Option<&'a str> {
+        contents
+            .lines()
+            .find_map(|line| line.strip_prefix(key)?.strip_prefix(':'))
+            .map(str::trim)
+    }
+}
+
+fn invalid_data(what: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("Could not parse fdinfo {}", what),
+    )
+}
+
+fn value<'a>(pairs: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    pairs
+        .iter()
+        .find(|(pair_key, _)| pair_key == key)
+        .map(|(_, value)| value.as_str())
+}
+
+fn number(value: Option<&str>, radix: u32, what: &str) -> Result<u64> {
+    value
+        .and_then(|value| u64::from_str_radix(value, radix).ok())
+        .ok_or_else(|| invalid_data(what))
+}
+
+fn optional_number(value: Option<&str>, radix: u32) -> Option<u64> {
+    value.and_then(|value| u64::from_str_radix(value, radix).ok())
+}
+
+// Read the fdinfo of a descriptor, checking it is of the expected kind from the target of its
+// link in /proc/<pid>/fd, such as "anon_inode:[eventfd]"
+fn read_fdinfo(pid: i32, fd: i32, kind: Option<(&str, &str)>) -> Result<String> {
+    if let Some((target, name)) = kind {
+        let link = fs::read_link(procfs::pid_path(pid, &format!("fd/{}", fd)))
+            .map_err(|err| procfs::pid_error(pid, err))?;
+        if link.to_string_lossy() != target {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("File descriptor {} is not {}", fd, name),
+            ));
+        }
+    }
+    procfs::read_pid_file(pid, &format!("fdinfo/{}", fd))
+}
+
+/// The details every file descriptor has, from /proc/<pid>/fdinfo/<fd>
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FDInfo {
+    /// The file offset
+    pub pos: u64,
+    /// The flags it was opened with, such as `libc::O_RDWR` and `libc::O_CLOEXEC`
+    pub flags: u32,
+    /// The id of the mount the file is on, as in /proc/<pid>/mountinfo
+    pub mnt_id: u32,
+    /// The inode of the file, only reported by Linux 5.7 and later
+    pub ino: Option<u64>,
+}
+
+impl FDInfo {
+    fn from_lines(lines: &FDInfoLines) -> Result<FDInfo> {
+        Ok(FDInfo {
+            pos: number(lines.get("pos"), 10, "pos")?,
+            flags: number(lines.get("flags"), 8, "flags")? as u32,
+            mnt_id: optional_number(lines.get("mnt_id"), 10).unwrap_or(0) as u32,
+            ino: optional_number(lines.get("ino"), 10),
+        })
+    }
+
+    pub fn parse(contents: &str) -> Result<FDInfo> {
+        FDInfo::from_lines(&FDInfoLines::parse(contents))
+    }
+}
+
+impl PIDFDInfo for FDInfo {
+    fn flavor() -> PidFDInfoFlavor {
+        PidFDInfoFlavor::LinuxFDInfo
+    }
+
+    fn from_procfs(pid: i32, fd: i32) -> Result<FDInfo> {
+        FDInfo::parse(&read_fdinfo(pid, fd, None)?)
+    }
+}
+
+/// An eventfd(2) file descriptor
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EventFDInfo {
+    pub fdinfo: FDInfo,
+    /// The value of the counter
+    pub count: u64,
+    /// A unique id of the eventfd, only reported by Linux 5.18 and later
+    pub id: Option<u64>,
+}
+
+impl EventFDInfo {
+    pub fn parse(contents: &str) -> Result<EventFDInfo> {
+        let lines = FDInfoLines::parse(contents);
+        Ok(EventFDInfo {
+            fdinfo: FDInfo::from_lines(&lines)?,
+            count: number(lines.get("eventfd-count"), 16, "eventfd-count")?,
+            id: optional_number(lines.get("eventfd-id"), 10),
+        })
+    }
+}
+
+impl PIDFDInfo for EventFDInfo {
+    fn flavor() -> PidFDInfoFlavor {
+        PidFDInfoFlavor::LinuxFDInfo
+    }
+
+    fn from_procfs(pid: i32, fd: i32) -> Result<EventFDInfo> {
+        let kind = ("anon_inode:[eventfd]", "an eventfd");
+        EventFDInfo::parse(&read_fdinfo(pid, fd, Some(kind))?)
+    }
+}
+
+/// A file descriptor being watched by an epoll instance
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EpollTarget {
+    /// The watched file descriptor, in the process that added it
+    pub tfd: i32,
+    /// The events being waited for, such as `libc::EPOLLIN`
+    pub events: u32,
+    /// The data registered with the file descriptor
+    pub data: u64,
+    /// The file offset of the watched file
+    pub pos: u64,
+    /// The inode and device of the watched file
+    pub ino: u64,
+    pub sdev: u64,
+}
+
+/// An epoll(7) file descriptor, with the file descriptors it watches
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EpollFDInfo {
+    pub fdinfo: FDInfo,
+    pub targets: Vec<EpollTarget>,
+}
+
+impl EpollFDInfo {
+    pub fn parse(contents: &str) -> Result<EpollFDInfo> {
+        let lines = FDInfoLines::parse(contents);
+        let targets = lines
+            .lines_starting("tfd")
+            .map(|pairs| {
+                Ok(EpollTarget {
+                    tfd: number(value(pairs, "tfd"), 10, "tfd")? as i32,
+                    events: number(value(pairs, "events"), 16, "events")? as u32,
+                    data: number(value(pairs, "data"), 16, "data")?,
+                    pos: optional_number(value(pairs, "pos"), 10).unwrap_or(0),
+                    ino: optional_number(value(pairs, "ino"), 16).unwrap_or(0),
+                    sdev: optional_number(value(pairs, "sdev"), 16).unwrap_or(0),
+                })
+            })
+            .collect::<Result<Vec<EpollTarget>>>()?;
+
+        Ok(EpollFDInfo {
+            fdinfo: FDInfo::from_lines(&lines)?,
+            targets,
+        })
+    }
+}
+
+impl PIDFDInfo for EpollFDInfo {
+    fn flavor() -> PidFDInfoFlavor {
+        PidFDInfoFlavor::LinuxFDInfo
+    }
+
+    fn from_procfs(pid: i32, fd: i32) -> Result<EpollFDInfo> {
+        let kind = ("anon_inode:[eventpoll]", "an epoll instance");
+        EpollFDInfo::parse(&read_fdinfo(pid, fd, Some(kind))?)
+    }
+}
+
+/// A file being watched by an inotify instance
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InotifyWatch {
+    /// The watch descriptor returned by inotify_add_watch(2)
+    pub wd: i32,
+    /// The inode and device of the watched file
+    pub ino: u64,
+    pub sdev: u64,
+    /// The events being watched for, such as `libc::IN_MODIFY`
+    pub mask: u32,
+    pub ignored_mask: u32,
+}
+
+/// An inotify(7) file descriptor, with the files it watches
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InotifyFDInfo {
+    pub fdinfo: FDInfo,
+    pub watches: Vec<InotifyWatch>,
+}
+
+impl InotifyFDInfo {
+    pub fn parse(contents: &str) -> Result<InotifyFDInfo> {
+        let lines = FDInfoLines::parse(contents);
+        let watches = lines
+            .lines_starting("wd")
+            .map(|pairs| {
+                Ok(InotifyWatch {
+                    wd: number(value(pairs, "wd"), 16, "wd")? as i32,
+                    ino: number(value(pairs, "ino"), 16, "ino")?,
+                    sdev: number(value(pairs, "sdev"), 16, "sdev")?,
+                    mask: number(value(pairs, "mask"), 16, "mask")? as u32,
+                    ignored_mask: optional_number(value(pairs, "ignored_mask"), 16).unwrap_or(0)
+                        as u32,
+                })
+            })
+            .collect::<Result<Vec<InotifyWatch>>>()?;
+
+        Ok(InotifyFDInfo {
+            fdinfo: FDInfo::from_lines(&lines)?,
+            watches,
+        })
+    }
+}
+
+impl PIDFDInfo for InotifyFDInfo {
+    fn flavor() -> PidFDInfoFlavor {
+        PidFDInfoFlavor::LinuxFDInfo
+    }
+
+    fn from_procfs(pid: i32, fd: i32) -> Result<InotifyFDInfo> {
+        let kind = ("anon_inode:inotify", "an inotify instance");
+        InotifyFDInfo::parse(&read_fdinfo(pid, fd, Some(kind))?)
+    }
+}
+
+/// A signalfd(2) file descriptor
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SignalFDInfo {
+    pub fdinfo: FDInfo,
+    /// The signals accepted, bit n - 1 is set for signal n
+    pub sigmask: u64,
+}
+
+impl SignalFDInfo {
+    pub fn parse(contents: &str) -> Result<SignalFDInfo> {
+        let lines = FDInfoLines::parse(contents);
+        Ok(SignalFDInfo {
+            fdinfo: FDInfo::from_lines(&lines)?,
+            sigmask: number(lines.get("sigmask"), 16, "sigmask")?,
+        })
+    }
+
+    /// Whether the signal is accepted
+    pub fn accepts(&self, signal: i32) -> bool {
+        signal > 0 && signal <= 64 && self.sigmask & (1 << (signal - 1)) != 0
+    }
+}
+
+impl PIDFDInfo for SignalFDInfo {
+    fn flavor() -> PidFDInfoFlavor {
+        PidFDInfoFlavor::LinuxFDInfo
+    }
+
+    fn from_procfs(pid: i32, fd: i32) -> Result<SignalFDInfo> {
+        let kind = ("anon_inode:[signalfd]", "a signalfd");
+        SignalFDInfo::parse(&read_fdinfo(pid, fd, Some(kind))?)
+    }
+}
+
+/// A timerfd_create(2) file descriptor and how it is set
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TimerFDInfo {
+    pub fdinfo: FDInfo,
+    /// The clock, such as `libc::CLOCK_MONOTONIC`
+    pub clockid: i32,
+    /// The number of expirations that have not been read yet
+    pub ticks: u64,
+    /// The flags it was set with, such as `libc::TFD_TIMER_ABSTIME`
+    pub settime_flags: u32,
+    /// The time until it next expires, as seconds and nanoseconds, zero if it is disarmed
+    pub it_value: (i64, i64),
+    /// The interval it repeats at, as seconds and nanoseconds, zero if it only expires once
+    pub it_interval: (i64, i64),
+}
+
+// Times are written as "(seconds, nanoseconds)"
+fn parse_timespec(value: Option<&str>, what: &str) -> Result<(i64, i64)> {
+    let value = value
+        .and_then(|value| value.strip_prefix('('))
+        .and_then(|value| value.strip_suffix(')'))
+        .ok_or_else(|| invalid_data(what))?;
+    let mut parts = value.splitn(2, ',').map(|part| part.trim().parse::<i64>());
+    match (parts.next(), parts.next()) {
+        (Some(Ok(seconds)), Some(Ok(nanoseconds))) => Ok((seconds, nanoseconds)),
+        _ => Err(invalid_data(what)),
+    }
+}
+
+impl TimerFDInfo {
+    pub fn parse(contents: &str) -> Result<TimerFDInfo> {
+        let lines = FDInfoLines::parse(contents);
+        Ok(TimerFDInfo {
+            fdinfo: FDInfo::from_lines(&lines)?,
+            clockid: number(lines.get("clockid"), 10, "clockid")? as i32,
+            ticks: number(lines.get("ticks"), 10, "ticks")?,
+            settime_flags: number(lines.get("settime_flags"), 8, "settime flags")? as u32,
+            it_value: parse_timespec(FDInfoLines::get_raw(contents, "it_value"), "it_value")?,
+            it_interval: parse_timespec(
+                FDInfoLines::get_raw(contents, "it_interval"),
+                "it_interval",
+            )?,
+        })
+    }
+}
+
+impl PIDFDInfo for TimerFDInfo {
+    fn flavor() -> PidFDInfoFlavor {
+        PidFDInfoFlavor::LinuxFDInfo
+    }
+
+    fn from_procfs(pid: i32, fd: i32) -> Result<TimerFDInfo> {
+        let kind = ("anon_inode:[timerfd]", "a timerfd");
+        TimerFDInfo::parse(&read_fdinfo(pid, fd, Some(kind))?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::libproc::proc_pid::pidfdinfo;
+    use std::process;
+
+    #[test]
+    fn parse_fdinfo_test() {
+        let info = FDInfo::parse("pos:\t42\nflags:\t02100002\nmnt_id:\t29\nino:\t1057\n").unwrap();
+        assert_eq!(
+            info,
+            FDInfo {
+                pos: 42,
+                flags: 0o2100002,
+                mnt_id: 29,
+                ino: Some(1057)
+            }
+        );
+
+        // Before Linux 5.7 there was no inode
+        assert_eq!(
+            FDInfo::parse("pos:\t0\nflags:\t02\nmnt_id:\t9\n")
+                .unwrap()
+                .ino,
+            None
+        );
+        assert!(FDInfo::parse("flags:\t02\n").is_err());
+    }
+
+    #[test]
+    fn parse_eventfd_test() {
+        let info = EventFDInfo::parse(
+            "pos:\t0\nflags:\t02\nmnt_id:\t15\nino:\t1057\neventfd-count:               1a\neventfd-id: 3\n",
+        )
+        .unwrap();
+        assert_eq!(info.count, 0x1a);
+        assert_eq!(info.id, Some(3));
+    }
+
+    #[test]
+    fn parse_epoll_test() {
+        let info = EpollFDInfo::parse(
+            "pos:\t0\nflags:\t02\nmnt_id:\t15\nino:\t1057\n\
+             tfd:        5 events:       19 data:                7  pos:0 ino:61af sdev:7\n\
+             tfd:        9 events:        1 data:     ffffffffffff  pos:12 ino:2 sdev:a\n",
+        )
+        .unwrap();
+        assert_eq!(
+            info.targets,
+            vec![
+                EpollTarget {
+                    tfd: 5,
+                    events: 0x19,
+                    data: 7,
+                    pos: 0,
+                    ino: 0x61af,
+                    sdev: 7
+                },
+                EpollTarget {
+                    tfd: 9,
+                    events: 1,
+                    data: 0xffff_ffff_ffff,
+                    pos: 12,
+                    ino: 2,
+                    sdev: 0xa
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_inotify_test() {
+        let info = InotifyFDInfo::parse(
+            "pos:\t0\nflags:\t00\nmnt_id:\t15\n\
+             inotify wd:3 ino:9e7e sdev:800013 mask:800afce ignored_mask:0 fhandle-bytes:8 fhandle-type:1 f_handle:7e9e0000640d1b6d\n",
+        )
+        .unwrap();
+        assert_eq!(
+            info.watches,
+            vec![InotifyWatch {
+                wd: 3,
+                ino: 0x9e7e,
+                sdev: 0x800013,
+                mask: 0x800afce,
+                ignored_mask: 0
+            }]
+        );
+    }
+
+    #[test]
+    fn parse_signalfd_test() {
+        let info =
+            SignalFDInfo::parse("pos:\t0\nflags:\t02\nmnt_id:\t9\nsigmask:\t0000000000004a02\n")
+                .unwrap();
+        assert_eq!(info.sigmask, 0x4a02);
+        assert!(info.accepts(libc::SIGINT));
+        assert!(!info.accepts(libc::SIGKILL));
+    }
+
+    #[test]
+    fn parse_timerfd_test() {
+        let info = TimerFDInfo::parse(
+            "pos:\t0\nflags:\t02\nmnt_id:\t9\nclockid: 1\nticks: 3\nsettime flags: 01\n\
+             it_value: (0, 49406829)\nit_interval: (1, 0)\n",
+        )
+        .unwrap();
+        assert_eq!(info.clockid, 1);
+        assert_eq!(info.ticks, 3);
+        assert_eq!(info.settime_flags, 1);
+        assert_eq!(info.it_value, (0, 49406829));
+        assert_eq!(info.it_interval, (1, 0));
+    }
+
+    #[test]
+    fn pidfdinfo_eventfd_test() {
+        let fd = unsafe { libc::eventfd(5, libc::EFD_CLOEXEC) };
+        assert!(fd >= 0);
+        let pid = process::id() as i32;
+
+        let eventfd = pidfdinfo::<EventFDInfo>(pid, fd);
+        let wrong_kind = pidfdinfo::<TimerFDInfo>(pid, fd);
+        let fdinfo = pidfdinfo::<FDInfo>(pid, fd);
+        unsafe { libc::close(fd) };
+
+        assert_eq!(eventfd.unwrap().count, 5);
+        assert_eq!(
+            fdinfo.unwrap().flags & libc::O_CLOEXEC as u32,
+            libc::O_CLOEXEC as u32
+        );
+        match wrong_kind {
+            Ok(_) => panic!("An eventfd is not a timerfd"),
+            Err(err) => assert_eq!(err.kind(), ErrorKind::InvalidInput),
+        }
+    }
+}
@@ -0,0 +1,279 @@
+// How many file descriptors processes, and the whole system, have open against their limits
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind, Result};
+
+use crate::libproc::proc_limits::{self, Resource};
+use crate::libproc::proc_pid::{self, BSDInfo, ListFDs, ProcFDType, ProcType};
+
+/// The file descriptors a process has open, by type, and its RLIMIT_NOFILE
+#[derive(Clone, Debug, PartialEq)]
+pub struct FDUsage {
+    pub pid: i32,
+    /// The number of open file descriptors
+    pub open: usize,
+    /// The number of open file descriptors of each type
+    pub by_type: HashMap<ProcFDType, usize>,
+    /// The soft limit on open file descriptors, `None` if it is unlimited or not known (on Mac OS
+    /// X only the limit of the current process is known)
+    pub limit: Option<u64>,
+}
+
+impl FDUsage {
+    /// The number of open file descriptors of a type
+    pub fn count(&self, fd_type: ProcFDType) -> usize {
+        self.by_type.get(&fd_type).copied().unwrap_or(0)
+    }
+
+    /// The percentage of the limit in use, `None` if there is no limit
+    pub fn percent_used(&self) -> Option<f64> {
+        self.limit
+            .filter(|&limit| limit > 0)
+            .map(|limit| self.open as f64 * 100.0 / limit as f64)
+    }
+}
+
+/// Returns the file descriptors the process with the specified pid has open, against its limit
+pub fn pidfdusage(pid: i32) -> Result<FDUsage> {
+    let bsd = proc_pid::pidinfo::<BSDInfo>(pid, 0)?;
+    let fds = proc_pid::listpidinfo::<ListFDs>(pid, bsd.pbi_nfiles as usize)?;
+
+    let mut by_type = HashMap::new();
+    for fd in &fds {
+        *by_type.entry(ProcFDType::from(fd.proc_fdtype)).or_insert(0) += 1;
+    }
+
+    let limit = match proc_limits::pidlimit(pid, Resource::OpenFiles) {
+        Ok(limit) => limit.soft,
+        Err(ref err) if err.kind() == ErrorKind::Unsupported => None,
+        Err(err) => return Err(err),
+    };
+
+    Ok(FDUsage {
+        pid,
+        open: fds.len(),
+        by_type,
+        limit,
+    })
+}
+
+/// The files open in the whole system, against the most the kernel will allow
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SystemFDUsage {
+    pub open: u64,
+    pub max: u64,
+}
+
+impl SystemFDUsage {
+    /// Parse /proc/sys/fs/file-nr, which holds the number of file handles allocated, the number
+    /// of those that are unused and the maximum
+    #[cfg(target_os = "linux")]
+    pub fn parse_file_nr(contents: &str) -> Result<SystemFDUsage> {
+        let numbers: Vec<u64> = contents
+            .split_whitespace()
+            .filter_map(|number| number.parse().ok())
+            .collect();
+        match numbers[..] {
+            [allocated, unused, max] => Ok(SystemFDUsage {
+                open: allocated.saturating_sub(unused),
+                max,
+            }),
+            _ => Err(Error::new(
+                ErrorKind::InvalidData,
+                "Could not parse /proc/sys/fs/file-nr",
+            )),
+        }
+    }
+
+    /// The percentage of the maximum in use
+    pub fn percent_used(&self) -> f64 {
+        if self.max == 0 {
+            0.0
+        } else {
+            self.open as f64 * 100.0 / self.max as f64
+        }
+    }
+}
+
+/// Returns the files open in the whole system from /proc/sys/fs/file-nr, with the maximum from
+/// /proc/sys/fs/file-max
+#[cfg(target_os = "linux")]
+pub fn systemfdusage() -> Result<SystemFDUsage> {
+    let mut usage =
+        SystemFDUsage::parse_file_nr(&std::fs::read_to_string("/proc/sys/fs/file-nr")?)?;
+    if let Ok(max) = std::fs::read_to_string("/proc/sys/fs/file-max") {
+        if let Ok(max) = max.trim().parse() {
+            usage.max = max;
+        }
+    }
+    Ok(usage)
+}
+
+#[cfg(target_os = "macos")]
+fn sysctl_int(name: &str) -> Result<u64> {
+    use libc::{c_int, c_void, size_t};
+    use std::ffi::CString;
+
+    let name = CString::new(name).map_err(|err| Error::new(ErrorKind::InvalidInput, err))?;
+    let mut value: c_int = 0;
+    let mut size = std::mem::size_of::<c_int>() as size_t;
+    let ret = unsafe {
+        libc::sysctlbyname(
+            name.as_ptr(),
+            &mut value as *mut c_int as *mut c_void,
+            &mut size,
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+    if ret != 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(value as u64)
+}
+
+/// Returns the files open in the whole system from the kern.num_files and kern.maxfiles sysctls
+#[cfg(target_os = "macos")]
+pub fn systemfdusage() -> Result<SystemFDUsage> {
+    Ok(SystemFDUsage {
+        open: sysctl_int("kern.num_files")?,
+        max: sysctl_int("kern.maxfiles")?,
+    })
+}
+
+/// The file descriptor usage of every process we may inspect and of the whole system, to find
+/// processes that are close to running out of file descriptors
+///
+/// On Mac OS X only the limit of the current process can be read, so the `limit` of every other
+/// process is `None` and `over_threshold` never reports them.
+///
+/// # Examples
+///
+/// ```no_run
+/// use libproc::libproc::proc_fdusage::FDUsageReport;
+///
+/// let report = FDUsageReport::new(80.0).unwrap();
+/// for usage in report.over_threshold() {
+///     println!("{} has {} of {:?} files open", usage.pid, usage.open, usage.limit);
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct FDUsageReport {
+    /// The percentage of its limit above which a process is reported by `over_threshold`
+    pub threshold_percent: f64,
+    pub processes: Vec<FDUsage>,
+    pub system: SystemFDUsage,
+}
+
+impl FDUsageReport {
+    /// Take the file descriptor usage of every process, leaving out those that exit or that we
+    /// may not inspect while we do
+    pub fn new(threshold_percent: f64) -> Result<FDUsageReport> {
+        let processes = proc_pid::listpids(ProcType::ProcAllPIDS, 0)?
+            .into_iter()
+            .filter_map(|pid| pidfdusage(pid as i32).ok())
+            .collect();
+
+        Ok(FDUsageReport {
+            threshold_percent,
+            processes,
+            system: systemfdusage()?,
+        })
+    }
+
+    /// The processes using more than the threshold percentage of their limit, fullest first.
+    /// Processes whose limit is not known are left out.
+    pub fn over_threshold(&self) -> Vec<&FDUsage> {
+        let mut over: Vec<&FDUsage> = self
+            .processes
+            .iter()
+            .filter(|usage| {
+                usage
+                    .percent_used()
+                    .is_some_and(|percent| percent > self.threshold_percent)
+            })
+            .collect();
+        over.sort_by(|one, other| {
+            other
+                .percent_used()
+                .partial_cmp(&one.percent_used())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        over
+    }
+
+    /// Whether the whole system is using more than the threshold percentage of its maximum
+    pub fn system_over_threshold(&self) -> bool {
+        self.system.percent_used() > self.threshold_percent
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::process;
+
+    fn usage(pid: i32, open: usize, limit: Option<u64>) -> FDUsage {
+        FDUsage {
+            pid,
+            open,
+            by_type: HashMap::new(),
+            limit,
+        }
+    }
+
+    #[test]
+    fn over_threshold_test() {
+        let report = FDUsageReport {
+            threshold_percent: 80.0,
+            processes: vec![
+                usage(1, 900, Some(1000)),
+                usage(2, 100, Some(1000)),
+                usage(3, 1000, Some(1024)),
+                usage(4, 5000, None),
+            ],
+            system: SystemFDUsage {
+                open: 900,
+                max: 1000,
+            },
+        };
+
+        let pids: Vec<i32> = report
+            .over_threshold()
+            .iter()
+            .map(|usage| usage.pid)
+            .collect();
+        assert_eq!(pids, vec![3, 1]);
+        assert!(report.system_over_threshold());
+        assert_eq!(usage(4, 5000, None).percent_used(), None);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn parse_file_nr_test() {
+        assert_eq!(
+            SystemFDUsage::parse_file_nr("2720\t120\t9223372036854775807\n").unwrap(),
+            SystemFDUsage {
+                open: 2600,
+                max: 9_223_372_036_854_775_807
+            }
+        );
+        assert!(SystemFDUsage::parse_file_nr("2720\n").is_err());
+    }
+
+    #[test]
+    fn pidfdusage_test() {
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        let usage = pidfdusage(process::id() as i32);
+        unsafe {
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+        }
+
+        let usage = usage.unwrap();
+        assert!(usage.count(ProcFDType::Pipe) >= 2);
+        assert_eq!(usage.by_type.values().sum::<usize>(), usage.open);
+        assert!(usage.limit.is_some());
+        assert!(systemfdusage().unwrap().open > 0);
+    }
+}
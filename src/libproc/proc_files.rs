@@ -0,0 +1,405 @@
+// Which processes are using a file, directory or filesystem, like fuser(1)
+use std::cmp::Reverse;
+use std::fs;
+use std::io::Result;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+use crate::libproc::proc_pid::{
+    self, BSDInfo, ListFDs, ProcFDType, ProcType, VInfoStat, VnodePathFDInfo, VnodePathInfo,
+};
+#[cfg(target_os = "linux")]
+use crate::libproc::procfs;
+
+/// How a process is using a file
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Access {
+    /// It is open as a file descriptor, which may be open for writing
+    File { fd: i32, writable: bool },
+    /// It is the current working directory of the process
+    Cwd,
+    /// It is the root directory of the process
+    Root,
+    /// It is the executable of the process
+    Exe,
+    /// It is mapped into the memory of the process, as shared libraries are
+    Mmap,
+}
+
+impl Access {
+    /// The letter fuser(1) uses for the access: 'f' (or 'F' when writable) for an open file, 'c'
+    /// for the working directory, 'r' for the root directory, 'e' for the executable and 'm' for
+    /// a memory mapping
+    pub fn code(&self) -> char {
+        match *self {
+            Access::File {
+                writable: false, ..
+            } => 'f',
+            Access::File { writable: true, .. } => 'F',
+            Access::Cwd => 'c',
+            Access::Root => 'r',
+            Access::Exe => 'e',
+            Access::Mmap => 'm',
+        }
+    }
+}
+
+/// A process using a file, and how
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileUse {
+    pub pid: i32,
+    pub access: Access,
+    /// The path the process is using, which is that of the file being looked for or, when looking
+    /// for a filesystem, any file on it
+    pub path: PathBuf,
+}
+
+// The device and inode that identify a file. On Mac OS X the device is truncated to 32 bits, as
+// it is in a `VInfoStat`, so the two compare equal.
+fn identity(metadata: &fs::Metadata) -> (u64, u64) {
+    #[cfg(target_os = "macos")]
+    let device = metadata.dev() as u32 as u64;
+    #[cfg(target_os = "linux")]
+    let device = metadata.dev();
+    (device, metadata.ino())
+}
+
+fn stat_identity(stat: &VInfoStat) -> Option<(u64, u64)> {
+    if stat.vst_ino == 0 {
+        None
+    } else {
+        Some(stat.identity())
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn exe_metadata(pid: i32) -> Result<fs::Metadata> {
+    fs::metadata(procfs::pid_path(pid, "exe"))
+}
+
+#[cfg(target_os = "macos")]
+fn exe_metadata(pid: i32) -> Result<fs::Metadata> {
+    fs::metadata(proc_pid::pidpath(pid)?)
+}
+
+// The file descriptors of a process that are files, directories or devices, leaving out any that
+// are closed while we are looking
+fn vnode_fds(pid: i32) -> Vec<(i32, VnodePathFDInfo)> {
+    let fds = match proc_pid::pidinfo::<BSDInfo>(pid, 0) {
+        Ok(bsd) => {
+            proc_pid::listpidinfo::<ListFDs>(pid, bsd.pbi_nfiles as usize).unwrap_or_default()
+        }
+        Err(_) => return vec![],
+    };
+
+    fds.into_iter()
+        .filter(|fd| matches!(ProcFDType::from(fd.proc_fdtype), ProcFDType::VNode))
+        .filter_map(|fd| {
+            proc_pid::pidfdinfo::<VnodePathFDInfo>(pid, fd.proc_fd)
+                .ok()
+                .map(|info| (fd.proc_fd, info))
+        })
+        .collect()
+}
+
+// The files a process is using that `matches` the identity of, leaving out those we may not look
+// at. A process has many mappings of each file it maps, only the first is reported.
+fn pid_uses<F>(pid: i32, matches: F) -> Vec<FileUse>
+where
+    F: Fn((u64, u64)) -> bool,
+{
+    let mut uses = vec![];
+    let mut found = |access: Access, identity: Option<(u64, u64)>, path: PathBuf| {
+        if identity.is_some_and(&matches) {
+            uses.push(FileUse { pid, access, path });
+        }
+    };
+
+    for (fd, info) in vnode_fds(pid) {
+        let access = Access::File {
+            fd,
+            writable: info.pfi.is_writable(),
+        };
+        found(
+            access,
+            stat_identity(&info.pvip.vip_vi.vi_stat),
+            info.pvip.path(),
+        );
+    }
+
+    if let Ok(info) = proc_pid::pidinfo::<VnodePathInfo>(pid, 0) {
+        let cdir = &info.pvi_cdir;
+        found(
+            Access::Cwd,
+            stat_identity(&cdir.vip_vi.vi_stat),
+            cdir.path(),
+        );
+        let rdir = &info.pvi_rdir;
+        found(
+            Access::Root,
+            stat_identity(&rdir.vip_vi.vi_stat),
+            rdir.path(),
+        );
+    }
+
+    if let Ok(metadata) = exe_metadata(pid) {
+        let path = proc_pid::pidpath(pid).unwrap_or_default();
+        found(Access::Exe, Some(identity(&metadata)), PathBuf::from(path));
+    }
+
+    if let Ok(regions) = proc_pid::pidregions(pid) {
+        if let Some((region, path)) = regions
+            .filter_map(Result::ok)
+            .filter(|region| region.inode != 0)
+            .filter_map(|region| region.path.clone().map(|path| (region, path)))
+            .find(|(region, _)| matches((region.dev, region.inode)))
+        {
+            found(Access::Mmap, Some((region.dev, region.inode)), path);
+        }
+    }
+
+    uses
+}
+
+fn all_uses<F>(matches: F) -> Result<Vec<FileUse>>
+where
+    F: Fn((u64, u64)) -> bool,
+{
+    let mut uses = vec![];
+    for pid in proc_pid::listpids(ProcType::ProcAllPIDS, 0)? {
+        uses.extend(pid_uses(pid as i32, &matches));
+    }
+    uses.sort_by_key(|file_use| (file_use.pid, file_use.access));
+    Ok(uses)
+}
+
+/// The processes using a file or directory: as an open file descriptor, working or root
+/// directory, executable or memory mapping. The file is identified by its device and inode, so
+/// any hard link to it, or path through a symbolic link, is found.
+///
+/// Processes we may not inspect are left out, so run as root to see every process.
+///
+/// # Examples
+///
+/// ```no_run
+/// use libproc::libproc::proc_files::processes_using;
+///
+/// for file_use in processes_using("/var/log/syslog").unwrap() {
+///     println!("{} {:?}", file_use.pid, file_use.access);
+/// }
+/// ```
+pub fn processes_using<P: AsRef<Path>>(path: P) -> Result<Vec<FileUse>> {
+    let target = identity(&fs::metadata(path)?);
+    all_uses(|identity| identity == target)
+}
+
+/// The processes using any file on the filesystem that `path` is on, such as a mount point that
+/// cannot be unmounted because it is busy
+pub fn processes_using_mount<P: AsRef<Path>>(path: P) -> Result<Vec<FileUse>> {
+    let (device, _) = identity(&fs::metadata(path)?);
+    all_uses(|(identity_device, _)| identity_device == device)
+}
+
+/// A file that has been deleted while a process still has it open, so the space it uses on disk
+/// is not freed until the process closes it
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeletedFile {
+    pub pid: i32,
+    pub fd: i32,
+    /// The path the file had before it was deleted
+    pub path: PathBuf,
+    /// The size of the file in bytes
+    pub size: u64,
+}
+
+impl DeletedFile {
+    /// The path through which the file can still be reached, to truncate it or copy it back
+    #[cfg(target_os = "linux")]
+    pub fn proc_path(&self) -> PathBuf {
+        procfs::pid_path(self.pid, &format!("fd/{}", self.fd))
+    }
+}
+
+// Linux adds " (deleted)" to the path of a file that has been deleted
+fn original_path(path: PathBuf) -> PathBuf {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let bytes = path.as_os_str().as_bytes();
+    match bytes.strip_suffix(b" (deleted)") {
+        Some(original) => PathBuf::from(OsStr::from_bytes(original)),
+        None => path,
+    }
+}
+
+// Memory file descriptors (see memfd_create(2)) and anonymous shared memory have no links
+// either, but use memory rather than space on disk
+fn in_memory(path: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+
+    let bytes = path.as_os_str().as_bytes();
+    [&b"/memfd:"[..], b"/SYSV", b"/dev/zero", b"/anon_hugepage"]
+        .iter()
+        .any(|prefix| bytes.starts_with(prefix))
+}
+
+/// The files a process has open that have been deleted, which have no links left. Memory file
+/// descriptors and anonymous shared memory, which do not use space on disk, are left out.
+pub fn pid_deleted_files(pid: i32) -> Vec<DeletedFile> {
+    vnode_fds(pid)
+        .into_iter()
+        .filter(|(_, info)| info.pvip.vip_vi.vi_stat.vst_nlink == 0)
+        .map(|(fd, info)| DeletedFile {
+            pid,
+            fd,
+            path: original_path(info.pvip.path()),
+            size: info.pvip.vip_vi.vi_stat.vst_size as u64,
+        })
+        .filter(|file| !in_memory(&file.path))
+        .collect()
+}
+
+/// The files every process we may inspect has open that have been deleted, largest first. A
+/// file held open by several processes, or file descriptors, is reported for each of them.
+///
+/// # Examples
+///
+/// ```no_run
+/// use libproc::libproc::proc_files::deleted_files;
+///
+/// for file in deleted_files().unwrap() {
+///     println!("{} bytes in {} held by {}", file.size, file.path.display(), file.pid);
+/// }
+/// ```
+pub fn deleted_files() -> Result<Vec<DeletedFile>> {
+    let mut files = vec![];
+    for pid in proc_pid::listpids(ProcType::ProcAllPIDS, 0)? {
+        files.extend(pid_deleted_files(pid as i32));
+    }
+    files.sort_by_key(|file| (Reverse(file.size), file.pid, file.fd));
+    Ok(files)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+    use std::fs::File;
+    use std::os::unix::io::AsRawFd;
+    use std::process;
+
+    fn own_uses(uses: Vec<FileUse>) -> Vec<Access> {
+        let pid = process::id() as i32;
+        uses.into_iter()
+            .filter(|file_use| file_use.pid == pid)
+            .map(|file_use| file_use.access)
+            .collect()
+    }
+
+    #[test]
+    fn access_code_test() {
+        assert_eq!(
+            Access::File {
+                fd: 3,
+                writable: true
+            }
+            .code(),
+            'F'
+        );
+        assert_eq!(Access::Mmap.code(), 'm');
+    }
+
+    #[test]
+    fn processes_using_file_test() {
+        let path = env::temp_dir().join(format!("processes_using_file_test.{}", process::id()));
+        let file = File::create(&path).unwrap();
+
+        let uses = processes_using(&path);
+        let mount_uses = processes_using_mount(&path);
+        fs::remove_file(&path).unwrap();
+
+        let access = Access::File {
+            fd: file.as_raw_fd(),
+            writable: true,
+        };
+        assert_eq!(own_uses(uses.unwrap()), vec![access]);
+        assert!(own_uses(mount_uses.unwrap()).contains(&access));
+    }
+
+    #[test]
+    fn processes_using_cwd_and_exe_test() {
+        let cwd = own_uses(processes_using(env::current_dir().unwrap()).unwrap());
+        assert!(cwd.contains(&Access::Cwd));
+
+        let exe = own_uses(processes_using(env::current_exe().unwrap()).unwrap());
+        assert!(exe.contains(&Access::Exe));
+        assert!(exe.contains(&Access::Mmap));
+    }
+
+    #[test]
+    fn deleted_files_test() {
+        use std::io::Write;
+
+        let path = env::temp_dir().join(format!("deleted_files_test.{}", process::id()));
+        let mut file = File::create(&path).unwrap();
+        file.write_all(&[0; 1000]).unwrap();
+        // The path of the file descriptor has any symbolic links resolved
+        let path = path.canonicalize().unwrap();
+        let fd = file.as_raw_fd();
+        let pid = process::id() as i32;
+        assert!(!pid_deleted_files(pid)
+            .iter()
+            .any(|deleted| deleted.fd == fd));
+
+        fs::remove_file(&path).unwrap();
+        let deleted = DeletedFile {
+            pid,
+            fd,
+            path: path.clone(),
+            size: 1000,
+        };
+        assert!(pid_deleted_files(pid).contains(&deleted));
+        assert!(deleted_files().unwrap().contains(&deleted));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn deleted_files_leaves_out_memfd_test() {
+        use std::ffi::CString;
+        use std::os::unix::io::{FromRawFd, RawFd};
+
+        let name = CString::new("deleted_files_test").unwrap();
+        let fd: RawFd = unsafe { libc::memfd_create(name.as_ptr(), 0) };
+        assert!(fd >= 0);
+        let file = unsafe { File::from_raw_fd(fd) };
+        file.set_len(1000).unwrap();
+
+        assert!(!pid_deleted_files(process::id() as i32)
+            .iter()
+            .any(|deleted| deleted.fd == fd));
+    }
+
+    #[test]
+    fn in_memory_test() {
+        assert!(in_memory(Path::new("/memfd:wayland-shm")));
+        assert!(in_memory(Path::new("/SYSV00000000")));
+        assert!(!in_memory(Path::new("/var/log/app.log")));
+    }
+
+    #[test]
+    fn original_path_test() {
+        assert_eq!(
+            original_path(PathBuf::from("/var/log/app.log (deleted)")),
+            PathBuf::from("/var/log/app.log")
+        );
+        assert_eq!(
+            original_path(PathBuf::from("/var/log/app.log")),
+            PathBuf::from("/var/log/app.log")
+        );
+    }
+
+    #[test]
+    fn processes_using_missing_test() {
+        assert!(processes_using("/no/such/file").is_err());
+    }
+}
@@ -0,0 +1,283 @@
+use libc::c_int;
+use std::io::{Error, Result};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::libproc::proc_pid::{self, BSDInfo, ProcStatus};
+
+// How often to check on a process that we cannot wait on with a pidfd, starting with the shortest
+const MIN_POLL_INTERVAL: Duration = Duration::from_millis(1);
+const MAX_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A handle on a running process, that will not send signals to another process that happens to
+/// reuse the pid after the original one has exited.
+///
+/// On Linux the handle holds a pidfd (see pidfd_open(2)), which always refers to the process it
+/// was opened for. Where pidfds are not available the start time of the process is recorded when
+/// the handle is opened and checked again before each signal is sent.
+///
+/// # Examples
+///
+/// ```
+/// use std::process::Command;
+/// use std::time::Duration;
+/// use libproc::libproc::proc_handle::Process;
+///
+/// let mut child = Command::new("sleep").arg("10").spawn().unwrap();
+/// let process = Process::open(child.id() as i32).unwrap();
+///
+/// process.send_signal(libc::SIGTERM).unwrap();
+/// assert!(process.wait_for_exit(Some(Duration::from_secs(5))).unwrap());
+/// child.wait().unwrap();
+/// ```
+pub struct Process {
+    pid: i32,
+    start_time: (u64, u64),
+    #[cfg(target_os = "linux")]
+    pidfd: Option<c_int>,
+}
+
+impl Process {
+    /// Open a handle on the process with the specified pid
+    pub fn open(pid: i32) -> Result<Process> {
+        Process::open_with(pid, true)
+    }
+
+    fn open_with(pid: i32, use_pidfd: bool) -> Result<Process> {
+        // Open the pidfd before looking up the start time, so both refer to the same process
+        #[cfg(target_os = "linux")]
+        let pidfd = if use_pidfd { pidfd_open(pid)? } else { None };
+        #[cfg(not(target_os = "linux"))]
+        let _ = use_pidfd;
+
+        let info = proc_pid::pidinfo::<BSDInfo>(pid, 0)?;
+
+        Ok(Process {
+            pid,
+            start_time: (info.pbi_start_tvsec, info.pbi_start_tvusec),
+            #[cfg(target_os = "linux")]
+            pidfd,
+        })
+    }
+
+    /// The pid of the process
+    pub fn pid(&self) -> i32 {
+        self.pid
+    }
+
+    /// The time the process started, as seconds and microseconds since the epoch
+    pub fn start_time(&self) -> (u64, u64) {
+        self.start_time
+    }
+
+    /// Send a signal to the process.
+    ///
+    /// Returns an error with the raw OS error ESRCH if the process has exited, even if its pid
+    /// has been reused by another process since.
+    pub fn send_signal(&self, signal: c_int) -> Result<()> {
+        #[cfg(target_os = "linux")]
+        {
+            if let Some(pidfd) = self.pidfd {
+                let ret = unsafe {
+                    libc::syscall(
+                        libc::SYS_pidfd_send_signal,
+                        pidfd,
+                        signal,
+                        std::ptr::null::<libc::siginfo_t>(),
+                        0,
+                    )
+                };
+                return if ret == 0 {
+                    Ok(())
+                } else {
+                    Err(Error::last_os_error())
+                };
+            }
+        }
+
+        self.verify_start_time()?;
+        if unsafe { libc::kill(self.pid, signal) } == 0 {
+            Ok(())
+        } else {
+            Err(Error::last_os_error())
+        }
+    }
+
+    /// Returns true if the process has not exited yet
+    pub fn is_alive(&self) -> Result<bool> {
+        #[cfg(target_os = "linux")]
+        {
+            if let Some(pidfd) = self.pidfd {
+                return Ok(!poll_pidfd(pidfd, Some(Duration::from_secs(0)))?);
+            }
+        }
+
+        match proc_pid::pidinfo::<BSDInfo>(self.pid, 0) {
+            Ok(info) => Ok(
+                (info.pbi_start_tvsec, info.pbi_start_tvusec) == self.start_time
+                    && ProcStatus::from(info.pbi_status) != ProcStatus::Zombie,
+            ),
+            Err(ref err) if err.raw_os_error() == Some(libc::ESRCH) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Wait for the process to exit, which need not be a child of this process.
+    ///
+    /// Waits for at most `timeout`, or for as long as it takes if that is `None`. Returns true if
+    /// the process exited and false if the timeout expired first.
+    pub fn wait_for_exit(&self, timeout: Option<Duration>) -> Result<bool> {
+        #[cfg(target_os = "linux")]
+        {
+            if let Some(pidfd) = self.pidfd {
+                return poll_pidfd(pidfd, timeout);
+            }
+        }
+
+        let start = Instant::now();
+        let mut interval = MIN_POLL_INTERVAL;
+        loop {
+            if !self.is_alive()? {
+                return Ok(true);
+            }
+
+            let sleep = match timeout {
+                Some(timeout) => match timeout.checked_sub(start.elapsed()) {
+                    Some(remaining) if remaining > Duration::from_secs(0) => {
+                        interval.min(remaining)
+                    }
+                    _ => return Ok(false),
+                },
+                None => interval,
+            };
+            thread::sleep(sleep);
+            interval = (interval * 2).min(MAX_POLL_INTERVAL);
+        }
+    }
+
+    fn verify_start_time(&self) -> Result<()> {
+        let info = proc_pid::pidinfo::<BSDInfo>(self.pid, 0)?;
+        if (info.pbi_start_tvsec, info.pbi_start_tvusec) == self.start_time {
+            Ok(())
+        } else {
+            Err(Error::from_raw_os_error(libc::ESRCH))
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for Process {
+    fn drop(&mut self) {
+        if let Some(pidfd) = self.pidfd {
+            unsafe {
+                libc::close(pidfd);
+            }
+        }
+    }
+}
+
+// Returns None if the kernel does not support pidfds (before Linux 5.3)
+#[cfg(target_os = "linux")]
+fn pidfd_open(pid: i32) -> Result<Option<c_int>> {
+    let ret = unsafe { libc::syscall(libc::SYS_pidfd_open, pid, 0) };
+    if ret >= 0 {
+        return Ok(Some(ret as c_int));
+    }
+
+    let err = Error::last_os_error();
+    match err.raw_os_error() {
+        Some(libc::ENOSYS) | Some(libc::EPERM) => Ok(None),
+        _ => Err(err),
+    }
+}
+
+// A pidfd becomes readable when the process exits, returns true if it has
+#[cfg(target_os = "linux")]
+fn poll_pidfd(pidfd: c_int, timeout: Option<Duration>) -> Result<bool> {
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    loop {
+        let timeout_ms = match deadline {
+            Some(deadline) => {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                // Round up so we never wake before the deadline
+                (remaining.as_micros().div_ceil(1000)).min(c_int::MAX as u128) as c_int
+            }
+            None => -1,
+        };
+
+        let mut pollfd = libc::pollfd {
+            fd: pidfd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        match unsafe { libc::poll(&mut pollfd, 1, timeout_ms) } {
+            -1 => {
+                let err = Error::last_os_error();
+                if err.raw_os_error() != Some(libc::EINTR) {
+                    return Err(err);
+                }
+            }
+            0 => return Ok(false),
+            _ => return Ok(true),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::process::{Child, Command};
+
+    fn sleeper() -> Child {
+        Command::new("sleep").arg("30").spawn().unwrap()
+    }
+
+    fn signal_and_wait(use_pidfd: bool) {
+        let mut child = sleeper();
+        let process = Process::open_with(child.id() as i32, use_pidfd).unwrap();
+
+        assert!(process.is_alive().unwrap());
+        assert!(!process
+            .wait_for_exit(Some(Duration::from_millis(50)))
+            .unwrap());
+
+        process.send_signal(libc::SIGKILL).unwrap();
+        assert!(process
+            .wait_for_exit(Some(Duration::from_secs(10)))
+            .unwrap());
+        assert!(!process.is_alive().unwrap());
+
+        child.wait().unwrap();
+    }
+
+    #[test]
+    fn signal_and_wait_test() {
+        signal_and_wait(true);
+    }
+
+    #[test]
+    fn signal_and_wait_start_time_test() {
+        signal_and_wait(false);
+    }
+
+    #[test]
+    fn signal_after_exit_test() {
+        let mut child = sleeper();
+        let process = Process::open_with(child.id() as i32, false).unwrap();
+        child.kill().unwrap();
+        child.wait().unwrap();
+
+        match process.send_signal(libc::SIGTERM) {
+            Ok(()) => panic!("Signalled a process that has exited"),
+            Err(err) => assert_eq!(err.raw_os_error(), Some(libc::ESRCH)),
+        }
+    }
+
+    #[test]
+    fn open_unknown_pid_test() {
+        match Process::open(-1) {
+            Ok(_) => panic!("Opened a handle on pid -1"),
+            Err(err) => assert!(err.raw_os_error().is_some()),
+        }
+    }
+}
@@ -0,0 +1,431 @@
+// Which processes are at the other end of the pipes and Unix domain sockets of a process
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::io::Result;
+
+use crate::libproc::proc_pid::{
+    self, BSDInfo, ListFDs, PipeFDInfo, ProcFDType, ProcFileInfo, ProcType, VnodeFDInfo,
+};
+#[cfg(target_os = "macos")]
+use crate::libproc::proc_pid::{SocketFDInfo, SocketInfoKind};
+#[cfg(target_os = "linux")]
+use crate::libproc::{procfs, sock_diag};
+
+/// The kind of channel between processes
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IpcKind {
+    Pipe,
+    UnixSocket,
+}
+
+/// Which end of a pipe a file descriptor is
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PipeEnd {
+    Read,
+    Write,
+}
+
+/// A file descriptor of a process that is one end of a pipe or Unix domain socket
+#[derive(Clone, Debug, PartialEq)]
+pub struct IpcEndpoint {
+    pub pid: i32,
+    pub fd: i32,
+    pub kind: IpcKind,
+    /// The kernel object the descriptor refers to: on Linux the inode of the pipe or socket, on
+    /// Mac OS X the address of the pipe or socket control block. For a named pipe it is the
+    /// inode on both.
+    pub id: u64,
+    /// The object at the other end, `None` for a socket that is not connected
+    pub peer_id: Option<u64>,
+    /// Which end of a pipe this is, when it is known
+    pub end: Option<PipeEnd>,
+}
+
+impl IpcEndpoint {
+    /// Whether `other` is at the other end of the channel of this endpoint. The ends of a pipe
+    /// that are known are only peers if one reads and the other writes.
+    pub fn is_peer(&self, other: &IpcEndpoint) -> bool {
+        let ends_differ = match (self.end, other.end) {
+            (Some(end), Some(other_end)) => end != other_end,
+            _ => true,
+        };
+        self.kind == other.kind
+            && (self.pid, self.fd) != (other.pid, other.fd)
+            && self.peer_id == Some(other.id)
+            && ends_differ
+    }
+}
+
+/// The pipes and Unix domain sockets of the running processes, and which of them are connected
+///
+/// Processes we may not inspect, and processes that exit while the graph is built, are left out.
+/// On Linux only the Unix domain sockets in the network namespace of this process are found.
+///
+/// # Examples
+///
+/// ```no_run
+/// use libproc::libproc::proc_ipc::IpcGraph;
+///
+/// let graph = IpcGraph::build().unwrap();
+/// for peer in graph.peers(1234, 1) {
+///     println!("fd 1 of 1234 is connected to fd {} of {}", peer.fd, peer.pid);
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct IpcGraph {
+    endpoints: Vec<IpcEndpoint>,
+    names: HashMap<i32, String>,
+}
+
+// Which end of a pipe a descriptor is, from the mode it was opened with
+fn pipe_end(pfi: &ProcFileInfo) -> Option<PipeEnd> {
+    match (pfi.is_readable(), pfi.is_writable()) {
+        (true, false) => Some(PipeEnd::Read),
+        (false, true) => Some(PipeEnd::Write),
+        _ => None,
+    }
+}
+
+fn pipe_endpoint(pid: i32, fd: i32) -> Option<IpcEndpoint> {
+    let pipe = proc_pid::pidfdinfo::<PipeFDInfo>(pid, fd).ok()?;
+    Some(IpcEndpoint {
+        pid,
+        fd,
+        kind: IpcKind::Pipe,
+        id: pipe.pipeinfo.pipe_handle,
+        peer_id: Some(pipe.pipeinfo.pipe_peerhandle).filter(|&peer| peer != 0),
+        end: pipe_end(&pipe.pfi),
+    })
+}
+
+// The vnode type of a FIFO, from enum vtype in
+// http://opensource.apple.com//source/xnu/xnu-1456.1.26/bsd/sys/vnode.h
+const VFIFO: i32 = 7;
+
+// Named pipes (FIFOs) are files, so their descriptors are listed as vnodes. Mac OS X has no pipe
+// information for them, so every descriptor of the same FIFO is identified by its inode.
+fn fifo_endpoint(pid: i32, fd: i32) -> Option<IpcEndpoint> {
+    let vnode = proc_pid::pidfdinfo::<VnodeFDInfo>(pid, fd).ok()?;
+    if vnode.pvi.vi_type != VFIFO {
+        return None;
+    }
+
+    let inode = vnode.pvi.vi_stat.vst_ino;
+    Some(IpcEndpoint {
+        pid,
+        fd,
+        kind: IpcKind::Pipe,
+        id: inode,
+        peer_id: Some(inode),
+        end: pipe_end(&vnode.pfi),
+    })
+}
+
+#[cfg(target_os = "linux")]
+type UnixSockets = HashMap<u64, Option<u64>>;
+
+#[cfg(target_os = "linux")]
+fn unix_sockets() -> Result<UnixSockets> {
+    sock_diag::unix_sockets()
+}
+
+// Sockets that are not Unix domain sockets are not in the sock_diag dump
+#[cfg(target_os = "linux")]
+fn socket_endpoint(sockets: &UnixSockets, pid: i32, fd: i32) -> Option<IpcEndpoint> {
+    use std::os::unix::fs::MetadataExt;
+
+    let inode = std::fs::metadata(procfs::pid_path(pid, &format!("fd/{}", fd)))
+        .ok()?
+        .ino();
+    Some(IpcEndpoint {
+        pid,
+        fd,
+        kind: IpcKind::UnixSocket,
+        id: inode,
+        peer_id: *sockets.get(&inode)?,
+        end: None,
+    })
+}
+
+#[cfg(target_os = "macos")]
+type UnixSockets = ();
+
+#[cfg(target_os = "macos")]
+fn unix_sockets() -> Result<UnixSockets> {
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn socket_endpoint(_sockets: &UnixSockets, pid: i32, fd: i32) -> Option<IpcEndpoint> {
+    let socket = proc_pid::pidfdinfo::<SocketFDInfo>(pid, fd).ok()?;
+    match SocketInfoKind::from(socket.psi.soi_kind) {
+        SocketInfoKind::Un => {}
+        _ => return None,
+    }
+
+    // access to the members of `soi_proto` is unsafe because it is a union type.
+    let conn_pcb = unsafe { socket.psi.soi_proto.pri_un.unsi_conn_pcb };
+    Some(IpcEndpoint {
+        pid,
+        fd,
+        kind: IpcKind::UnixSocket,
+        id: socket.psi.soi_pcb,
+        peer_id: Some(conn_pcb).filter(|&peer| peer != 0),
+        end: None,
+    })
+}
+
+impl IpcGraph {
+    /// Find the pipes and Unix domain sockets of every process we may inspect
+    pub fn build() -> Result<IpcGraph> {
+        let sockets = unix_sockets()?;
+        let mut graph = IpcGraph::default();
+
+        for pid in proc_pid::listpids(ProcType::ProcAllPIDS, 0)? {
+            let pid = pid as i32;
+            let bsd = match proc_pid::pidinfo::<BSDInfo>(pid, 0) {
+                Ok(bsd) => bsd,
+                Err(_) => continue,
+            };
+            let fds = match proc_pid::listpidinfo::<ListFDs>(pid, bsd.pbi_nfiles as usize) {
+                Ok(fds) => fds,
+                Err(_) => continue,
+            };
+
+            let before = graph.endpoints.len();
+            for fd in fds {
+                let endpoint = match ProcFDType::from(fd.proc_fdtype) {
+                    ProcFDType::Pipe => pipe_endpoint(pid, fd.proc_fd),
+                    ProcFDType::VNode => fifo_endpoint(pid, fd.proc_fd),
+                    ProcFDType::Socket => socket_endpoint(&sockets, pid, fd.proc_fd),
+                    _ => None,
+                };
+                graph.endpoints.extend(endpoint);
+            }
+
+            if graph.endpoints.len() > before {
+                if let Ok(name) = proc_pid::name(pid) {
+                    graph.names.insert(pid, name);
+                }
+            }
+        }
+
+        Ok(graph)
+    }
+
+    /// Every pipe and Unix domain socket file descriptor found
+    pub fn endpoints(&self) -> &[IpcEndpoint] {
+        &self.endpoints
+    }
+
+    /// The endpoint of a file descriptor, if it is a pipe or Unix domain socket
+    pub fn endpoint(&self, pid: i32, fd: i32) -> Option<&IpcEndpoint> {
+        self.endpoints
+            .iter()
+            .find(|endpoint| endpoint.pid == pid && endpoint.fd == fd)
+    }
+
+    /// The file descriptors, in any process, at the other end of a file descriptor. There can be
+    /// many, as descriptors are inherited and duplicated.
+    pub fn peers(&self, pid: i32, fd: i32) -> Vec<&IpcEndpoint> {
+        match self.endpoint(pid, fd) {
+            Some(endpoint) => self
+                .endpoints
+                .iter()
+                .filter(|other| endpoint.is_peer(other))
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// Each pair of connected endpoints once. Pipes are ordered from the writer to the reader.
+    pub fn connections(&self) -> Vec<(&IpcEndpoint, &IpcEndpoint)> {
+        let mut connections = vec![];
+        for one in &self.endpoints {
+            for other in self.endpoints.iter().filter(|other| one.is_peer(other)) {
+                let first = match (one.end, other.end) {
+                    (Some(PipeEnd::Write), _) | (_, Some(PipeEnd::Read)) => true,
+                    (Some(PipeEnd::Read), _) | (_, Some(PipeEnd::Write)) => false,
+                    _ => (one.pid, one.fd) < (other.pid, other.fd),
+                };
+                if first {
+                    connections.push((one, other));
+                }
+            }
+        }
+        connections
+    }
+
+    /// The name of a process in the graph
+    pub fn name(&self, pid: i32) -> Option<&str> {
+        self.names.get(&pid).map(String::as_str)
+    }
+
+    /// The processes and the connections between them in the Graphviz DOT language, with an edge
+    /// labelled with the file descriptors for each connection
+    pub fn to_dot(&self) -> String {
+        let connections = self.connections();
+        let mut pids: Vec<i32> = connections
+            .iter()
+            .flat_map(|(one, other)| vec![one.pid, other.pid])
+            .collect();
+        pids.sort_unstable();
+        pids.dedup();
+
+        let mut dot = String::from("digraph ipc {\n");
+        for pid in pids {
+            let label = match self.name(pid) {
+                Some(name) => format!("{} {}", pid, name),
+                None => pid.to_string(),
+            };
+            writeln!(dot, "    {} [label={:?}];", pid, label).unwrap();
+        }
+        for (one, other) in connections {
+            let (style, arrow) = match one.kind {
+                IpcKind::Pipe => ("solid", "->"),
+                IpcKind::UnixSocket => ("dashed", "-"),
+            };
+            writeln!(
+                dot,
+                "    {} -> {} [label=\"{} {} {}\", style={}{}];",
+                one.pid,
+                other.pid,
+                one.fd,
+                arrow,
+                other.fd,
+                style,
+                if one.kind == IpcKind::UnixSocket {
+                    ", dir=none"
+                } else {
+                    ""
+                }
+            )
+            .unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::process;
+
+    fn pipe(pid: i32, fd: i32, id: u64, end: PipeEnd) -> IpcEndpoint {
+        IpcEndpoint {
+            pid,
+            fd,
+            kind: IpcKind::Pipe,
+            id,
+            peer_id: Some(id),
+            end: Some(end),
+        }
+    }
+
+    #[test]
+    fn connections_test() {
+        let socket = |pid, fd, id, peer_id| IpcEndpoint {
+            pid,
+            fd,
+            kind: IpcKind::UnixSocket,
+            id,
+            peer_id: Some(peer_id),
+            end: None,
+        };
+        let graph = IpcGraph {
+            endpoints: vec![
+                pipe(20, 0, 7, PipeEnd::Read),
+                pipe(10, 1, 7, PipeEnd::Write),
+                pipe(30, 1, 7, PipeEnd::Write),
+                socket(10, 3, 100, 101),
+                socket(20, 4, 101, 100),
+            ],
+            names: vec![(10, "cat".to_string())].into_iter().collect(),
+        };
+
+        let readers: Vec<(i32, i32)> = graph
+            .peers(10, 1)
+            .iter()
+            .map(|peer| (peer.pid, peer.fd))
+            .collect();
+        assert_eq!(readers, vec![(20, 0)]);
+        assert_eq!(graph.peers(20, 0).len(), 2);
+        assert!(graph.peers(99, 0).is_empty());
+
+        let connections: Vec<((i32, i32), (i32, i32))> = graph
+            .connections()
+            .iter()
+            .map(|(one, other)| ((one.pid, one.fd), (other.pid, other.fd)))
+            .collect();
+        assert_eq!(
+            connections,
+            vec![((10, 1), (20, 0)), ((30, 1), (20, 0)), ((10, 3), (20, 4))]
+        );
+
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("digraph ipc {\n"));
+        assert!(dot.contains("    10 [label=\"10 cat\"];\n"));
+        assert!(dot.contains("    10 -> 20 [label=\"1 -> 0\", style=solid];\n"));
+        assert!(dot.contains("    10 -> 20 [label=\"3 - 4\", style=dashed, dir=none];\n"));
+    }
+
+    #[test]
+    fn build_test() {
+        use std::os::unix::io::AsRawFd;
+        use std::os::unix::net::UnixStream;
+
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        let (one, other) = UnixStream::pair().unwrap();
+        let pid = process::id() as i32;
+
+        let graph = IpcGraph::build();
+        unsafe {
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+        }
+        let graph = graph.unwrap();
+
+        let reader = graph.endpoint(pid, fds[0]).unwrap();
+        assert_eq!(reader.kind, IpcKind::Pipe);
+        assert_eq!(reader.end, Some(PipeEnd::Read));
+        let peers = graph.peers(pid, fds[0]);
+        assert_eq!(peers.len(), 1);
+        assert_eq!((peers[0].pid, peers[0].fd), (pid, fds[1]));
+
+        let peers = graph.peers(pid, one.as_raw_fd());
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers[0].kind, IpcKind::UnixSocket);
+        assert_eq!((peers[0].pid, peers[0].fd), (pid, other.as_raw_fd()));
+    }
+
+    #[test]
+    fn build_fifo_test() {
+        use std::ffi::CString;
+        use std::fs::OpenOptions;
+        use std::os::unix::ffi::OsStrExt;
+        use std::os::unix::fs::OpenOptionsExt;
+        use std::os::unix::io::AsRawFd;
+
+        let path = std::env::temp_dir().join(format!("build_fifo_test.{}", process::id()));
+        let c_path = CString::new(path.as_os_str().as_bytes()).unwrap();
+        assert_eq!(unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) }, 0);
+        let reader = OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(&path)
+            .unwrap();
+        let writer = OpenOptions::new().write(true).open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let pid = process::id() as i32;
+
+        let graph = IpcGraph::build().unwrap();
+        let endpoint = graph.endpoint(pid, reader.as_raw_fd()).unwrap();
+        assert_eq!(endpoint.kind, IpcKind::Pipe);
+        assert_eq!(endpoint.end, Some(PipeEnd::Read));
+        let peers = graph.peers(pid, reader.as_raw_fd());
+        assert_eq!(peers.len(), 1);
+        assert_eq!((peers[0].pid, peers[0].fd), (pid, writer.as_raw_fd()));
+    }
+}
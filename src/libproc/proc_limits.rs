@@ -0,0 +1,474 @@
+// The resource limits of processes, see getrlimit(2)
+use libc::c_int;
+use std::fmt;
+use std::io::{Error, ErrorKind, Result};
+
+#[cfg(target_os = "linux")]
+use crate::libproc::procfs;
+
+/// A resource whose use by a process can be limited
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Resource {
+    /// CPU time, in seconds
+    Cpu,
+    /// The size of files the process may create, in bytes
+    FileSize,
+    /// The size of the data segment, in bytes
+    Data,
+    /// The size of the stack, in bytes
+    Stack,
+    /// The size of core files, in bytes
+    Core,
+    /// The resident set size, in bytes
+    Rss,
+    /// The number of processes of the user
+    Processes,
+    /// The number of open file descriptors
+    OpenFiles,
+    /// The memory that may be locked, in bytes
+    LockedMemory,
+    /// The size of the address space, in bytes
+    AddressSpace,
+    /// The number of file locks (Linux only)
+    FileLocks,
+    /// The number of signals that may be queued (Linux only)
+    PendingSignals,
+    /// The bytes that may be queued on POSIX message queues (Linux only)
+    MessageQueue,
+    /// The ceiling of the nice value that may be set, as 20 - nice (Linux only)
+    NicePriority,
+    /// The ceiling of the real time priority that may be set (Linux only)
+    RealtimePriority,
+    /// CPU time a real time process may use without blocking, in microseconds (Linux only)
+    RealtimeTimeout,
+}
+
+impl Resource {
+    /// Every resource, in the order of /proc/<pid>/limits
+    pub fn all() -> &'static [Resource] {
+        &[
+            Resource::Cpu,
+            Resource::FileSize,
+            Resource::Data,
+            Resource::Stack,
+            Resource::Core,
+            Resource::Rss,
+            Resource::Processes,
+            Resource::OpenFiles,
+            Resource::LockedMemory,
+            Resource::AddressSpace,
+            Resource::FileLocks,
+            Resource::PendingSignals,
+            Resource::MessageQueue,
+            Resource::NicePriority,
+            Resource::RealtimePriority,
+            Resource::RealtimeTimeout,
+        ]
+    }
+
+    /// The unit the limit is in, as /proc/<pid>/limits shows it
+    pub fn unit(self) -> &'static str {
+        match self {
+            Resource::Cpu => "seconds",
+            Resource::FileSize
+            | Resource::Data
+            | Resource::Stack
+            | Resource::Core
+            | Resource::Rss
+            | Resource::LockedMemory
+            | Resource::AddressSpace
+            | Resource::MessageQueue => "bytes",
+            Resource::Processes => "processes",
+            Resource::OpenFiles => "files",
+            Resource::FileLocks => "locks",
+            Resource::PendingSignals => "signals",
+            Resource::NicePriority | Resource::RealtimePriority => "",
+            Resource::RealtimeTimeout => "us",
+        }
+    }
+
+    // The name of the line in /proc/<pid>/limits
+    #[cfg(target_os = "linux")]
+    fn procfs_name(self) -> &'static str {
+        match self {
+            Resource::Cpu => "Max cpu time",
+            Resource::FileSize => "Max file size",
+            Resource::Data => "Max data size",
+            Resource::Stack => "Max stack size",
+            Resource::Core => "Max core file size",
+            Resource::Rss => "Max resident set",
+            Resource::Processes => "Max processes",
+            Resource::OpenFiles => "Max open files",
+            Resource::LockedMemory => "Max locked memory",
+            Resource::AddressSpace => "Max address space",
+            Resource::FileLocks => "Max file locks",
+            Resource::PendingSignals => "Max pending signals",
+            Resource::MessageQueue => "Max msgqueue size",
+            Resource::NicePriority => "Max nice priority",
+            Resource::RealtimePriority => "Max realtime priority",
+            Resource::RealtimeTimeout => "Max realtime timeout",
+        }
+    }
+
+    // The RLIMIT_* constant of the resource, if the platform has it
+    #[cfg(target_os = "linux")]
+    fn raw(self) -> Option<c_int> {
+        Some(match self {
+            Resource::Cpu => libc::RLIMIT_CPU as c_int,
+            Resource::FileSize => libc::RLIMIT_FSIZE as c_int,
+            Resource::Data => libc::RLIMIT_DATA as c_int,
+            Resource::Stack => libc::RLIMIT_STACK as c_int,
+            Resource::Core => libc::RLIMIT_CORE as c_int,
+            Resource::Rss => libc::RLIMIT_RSS as c_int,
+            Resource::Processes => libc::RLIMIT_NPROC as c_int,
+            Resource::OpenFiles => libc::RLIMIT_NOFILE as c_int,
+            Resource::LockedMemory => libc::RLIMIT_MEMLOCK as c_int,
+            Resource::AddressSpace => libc::RLIMIT_AS as c_int,
+            Resource::FileLocks => libc::RLIMIT_LOCKS as c_int,
+            Resource::PendingSignals => libc::RLIMIT_SIGPENDING as c_int,
+            Resource::MessageQueue => libc::RLIMIT_MSGQUEUE as c_int,
+            Resource::NicePriority => libc::RLIMIT_NICE as c_int,
+            Resource::RealtimePriority => libc::RLIMIT_RTPRIO as c_int,
+            Resource::RealtimeTimeout => libc::RLIMIT_RTTIME as c_int,
+        })
+    }
+
+    #[cfg(target_os = "macos")]
+    fn raw(self) -> Option<c_int> {
+        match self {
+            Resource::Cpu => Some(libc::RLIMIT_CPU),
+            Resource::FileSize => Some(libc::RLIMIT_FSIZE),
+            Resource::Data => Some(libc::RLIMIT_DATA),
+            Resource::Stack => Some(libc::RLIMIT_STACK),
+            Resource::Core => Some(libc::RLIMIT_CORE),
+            Resource::Rss => Some(libc::RLIMIT_RSS),
+            Resource::Processes => Some(libc::RLIMIT_NPROC),
+            Resource::OpenFiles => Some(libc::RLIMIT_NOFILE),
+            Resource::LockedMemory => Some(libc::RLIMIT_MEMLOCK),
+            Resource::AddressSpace => Some(libc::RLIMIT_AS),
+            _ => None,
+        }
+    }
+
+    fn unsupported(self) -> Error {
+        Error::new(
+            ErrorKind::Unsupported,
+            format!("{:?} is not limited on this platform", self),
+        )
+    }
+}
+
+/// The soft and hard limits of a resource, `None` meaning unlimited. The soft limit is the one
+/// that is enforced, the hard limit is the ceiling an unprivileged process may raise it to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Limit {
+    pub soft: Option<u64>,
+    pub hard: Option<u64>,
+}
+
+impl Limit {
+    fn from_raw(soft: u64, hard: u64) -> Limit {
+        let limit = |value: u64| {
+            if value == libc::RLIM_INFINITY {
+                None
+            } else {
+                Some(value)
+            }
+        };
+        Limit {
+            soft: limit(soft),
+            hard: limit(hard),
+        }
+    }
+
+    fn raw(value: Option<u64>) -> u64 {
+        value.unwrap_or(libc::RLIM_INFINITY)
+    }
+}
+
+fn show_limit(f: &mut fmt::Formatter, value: Option<u64>) -> fmt::Result {
+    match value {
+        Some(value) => write!(f, "{}", value),
+        None => write!(f, "unlimited"),
+    }
+}
+
+/// Shows "soft/hard", such as "1024/unlimited"
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        show_limit(f, self.soft)?;
+        write!(f, "/")?;
+        show_limit(f, self.hard)
+    }
+}
+
+// struct rlimit64 from sys/resource.h
+#[cfg(target_os = "linux")]
+#[repr(C)]
+#[derive(Default)]
+struct Rlimit64 {
+    rlim_cur: u64,
+    rlim_max: u64,
+}
+
+// prlimit(2), which reads and optionally changes a limit of any process
+#[cfg(target_os = "linux")]
+fn prlimit(pid: i32, resource: Resource, new: Option<Limit>) -> Result<Limit> {
+    let raw = resource.raw().ok_or_else(|| resource.unsupported())?;
+    let new = new.map(|limit| Rlimit64 {
+        rlim_cur: Limit::raw(limit.soft),
+        rlim_max: Limit::raw(limit.hard),
+    });
+    let new_ptr = new
+        .as_ref()
+        .map_or(std::ptr::null(), |new| new as *const Rlimit64);
+    let mut old = Rlimit64::default();
+
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_prlimit64,
+            pid,
+            raw,
+            new_ptr,
+            &mut old as *mut Rlimit64,
+        )
+    };
+    if ret != 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(Limit::from_raw(old.rlim_cur, old.rlim_max))
+}
+
+/// Parse the contents of /proc/<pid>/limits, leaving out any lines that are not understood
+#[cfg(target_os = "linux")]
+pub fn parse_procfs_limits(contents: &str) -> Vec<(Resource, Limit)> {
+    let value = |word: &str| match word {
+        "unlimited" => Some(None),
+        word => word.parse::<u64>().ok().map(Some),
+    };
+
+    contents
+        .lines()
+        .filter_map(|line| {
+            let resource = *Resource::all()
+                .iter()
+                .find(|resource| line.starts_with(resource.procfs_name()))?;
+            let mut words = line[resource.procfs_name().len()..].split_whitespace();
+            Some((
+                resource,
+                Limit {
+                    soft: value(words.next()?)?,
+                    hard: value(words.next()?)?,
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Returns a resource limit of the process with the specified pid, using prlimit(2) or, if that
+/// is not permitted, /proc/<pid>/limits
+///
+/// # Examples
+///
+/// ```
+/// use libproc::libproc::proc_limits::{pidlimit, Resource};
+///
+/// let limit = pidlimit(std::process::id() as i32, Resource::OpenFiles).unwrap();
+/// println!("open files limited to {}", limit);
+/// ```
+#[cfg(target_os = "linux")]
+pub fn pidlimit(pid: i32, resource: Resource) -> Result<Limit> {
+    match prlimit(pid, resource, None) {
+        // Reading the limits of a process of another user needs CAP_SYS_RESOURCE
+        Err(ref err) if err.raw_os_error() == Some(libc::EPERM) => {
+            let contents = procfs::read_pid_file(pid, "limits")?;
+            parse_procfs_limits(&contents)
+                .into_iter()
+                .find(|(found, _)| *found == resource)
+                .map(|(_, limit)| limit)
+                .ok_or_else(|| resource.unsupported())
+        }
+        result => result,
+    }
+}
+
+/// Returns a resource limit of the process with the specified pid. Mac OS X only tells a process
+/// its own limits, the limits of any other process return an error of kind `Unsupported`.
+#[cfg(target_os = "macos")]
+pub fn pidlimit(pid: i32, resource: Resource) -> Result<Limit> {
+    let raw = own_process(pid, resource)?;
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    if unsafe { libc::getrlimit(raw, &mut limit) } != 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(Limit::from_raw(limit.rlim_cur, limit.rlim_max))
+}
+
+#[cfg(target_os = "macos")]
+fn own_process(pid: i32, resource: Resource) -> Result<c_int> {
+    if pid != std::process::id() as i32 {
+        return Err(Error::new(
+            ErrorKind::Unsupported,
+            "Only the limits of the current process are available",
+        ));
+    }
+    resource.raw().ok_or_else(|| resource.unsupported())
+}
+
+/// Returns every resource limit of the process with the specified pid that the platform has, in
+/// the order of `Resource::all()`
+#[cfg(target_os = "linux")]
+pub fn pidlimits(pid: i32) -> Result<Vec<(Resource, Limit)>> {
+    match procfs::read_pid_file(pid, "limits") {
+        Ok(contents) => Ok(parse_procfs_limits(&contents)),
+        // Kernels before 2.6.24 have no /proc/<pid>/limits
+        Err(ref err) if err.kind() == ErrorKind::NotFound => Resource::all()
+            .iter()
+            .map(|&resource| Ok((resource, prlimit(pid, resource, None)?)))
+            .collect(),
+        Err(err) => Err(err),
+    }
+}
+
+/// Returns every resource limit of the process with the specified pid that the platform has, in
+/// the order of `Resource::all()`
+#[cfg(target_os = "macos")]
+pub fn pidlimits(pid: i32) -> Result<Vec<(Resource, Limit)>> {
+    Resource::all()
+        .iter()
+        .filter(|resource| resource.raw().is_some())
+        .map(|&resource| Ok((resource, pidlimit(pid, resource)?)))
+        .collect()
+}
+
+/// Changes a resource limit of the process with the specified pid with prlimit(2), returning the
+/// limit it had. Raising the hard limit, or changing the limits of a process of another user,
+/// needs the CAP_SYS_RESOURCE capability.
+#[cfg(target_os = "linux")]
+pub fn setpidlimit(pid: i32, resource: Resource, limit: Limit) -> Result<Limit> {
+    prlimit(pid, resource, Some(limit))
+}
+
+/// Changes a resource limit of the current process, returning the limit it had. Mac OS X cannot
+/// change the limits of any other process.
+#[cfg(target_os = "macos")]
+pub fn setpidlimit(pid: i32, resource: Resource, limit: Limit) -> Result<Limit> {
+    let old = pidlimit(pid, resource)?;
+    let raw = own_process(pid, resource)?;
+    let new = libc::rlimit {
+        rlim_cur: Limit::raw(limit.soft),
+        rlim_max: Limit::raw(limit.hard),
+    };
+    if unsafe { libc::setrlimit(raw, &new) } != 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(old)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::process;
+
+    #[test]
+    fn limit_display_test() {
+        let limit = Limit {
+            soft: Some(1024),
+            hard: None,
+        };
+        assert_eq!(limit.to_string(), "1024/unlimited");
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn parse_procfs_limits_test() {
+        let limits = parse_procfs_limits(
+            "Limit                     Soft Limit           Hard Limit           Units     \n\
+             Max cpu time              unlimited            unlimited            seconds   \n\
+             Max open files            1024                 1048576              files     \n\
+             Max nice priority         0                    0                    \n\
+             Max realtime timeout      unlimited            unlimited            us        \n",
+        );
+        assert_eq!(
+            limits,
+            vec![
+                (
+                    Resource::Cpu,
+                    Limit {
+                        soft: None,
+                        hard: None
+                    }
+                ),
+                (
+                    Resource::OpenFiles,
+                    Limit {
+                        soft: Some(1024),
+                        hard: Some(1_048_576)
+                    }
+                ),
+                (
+                    Resource::NicePriority,
+                    Limit {
+                        soft: Some(0),
+                        hard: Some(0)
+                    }
+                ),
+                (
+                    Resource::RealtimeTimeout,
+                    Limit {
+                        soft: None,
+                        hard: None
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn pidlimit_test() {
+        let mut expected = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        assert_eq!(
+            unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut expected) },
+            0
+        );
+        let expected = Limit::from_raw(expected.rlim_cur, expected.rlim_max);
+
+        let pid = process::id() as i32;
+        assert_eq!(pidlimit(pid, Resource::OpenFiles).unwrap(), expected);
+        assert!(pidlimits(pid)
+            .unwrap()
+            .contains(&(Resource::OpenFiles, expected)));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn setpidlimit_test() {
+        use std::process::{Command, Stdio};
+
+        let mut child = Command::new("sleep")
+            .arg("30")
+            .stdout(Stdio::null())
+            .spawn()
+            .unwrap();
+        let pid = child.id() as i32;
+
+        let old = pidlimit(pid, Resource::Core).unwrap();
+        let new = Limit {
+            soft: Some(0),
+            hard: old.hard,
+        };
+        let result = setpidlimit(pid, Resource::Core, new);
+        let limits = pidlimits(pid);
+        child.kill().unwrap();
+        child.wait().unwrap();
+
+        assert_eq!(result.unwrap(), old);
+        assert!(limits.unwrap().contains(&(Resource::Core, new)));
+    }
+}
@@ -0,0 +1,333 @@
+use libc::uid_t;
+use regex::{Regex, RegexBuilder};
+use std::io::{Error, ErrorKind, Result};
+
+use crate::libproc::proc_pid::{self, BSDInfo, ProcType};
+
+/// What the pattern of a `ProcessMatcher` is matched against
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MatchTarget {
+    /// The process name, as returned by `proc_pid::name`
+    Name,
+    /// The path of the executable, as returned by `proc_pid::pidpath`
+    Path,
+    /// The full command line, arguments separated by spaces
+    CommandLine,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Selection {
+    All,
+    Newest,
+    Oldest,
+}
+
+/// Selects processes by name, path or command line and by their ids, in the manner of pgrep.
+///
+/// All the criteria that are set must match for a process to be selected.
+///
+/// # Examples
+///
+/// ```
+/// use libproc::libproc::proc_matcher::ProcessMatcher;
+///
+/// let uid = unsafe { libc::getuid() };
+/// match ProcessMatcher::new().pattern("^.*$").uid(uid).newest().find() {
+///     Ok(pids) => println!("Newest process of user {}: {:?}", uid, pids),
+///     Err(err) => panic!("Error matching processes: {}", err),
+/// }
+/// ```
+#[derive(Debug, PartialEq)]
+pub struct ProcessMatcher {
+    pattern: Option<String>,
+    target: MatchTarget,
+    exact: bool,
+    ignore_case: bool,
+    uids: Vec<uid_t>,
+    ruids: Vec<uid_t>,
+    ppids: Vec<u32>,
+    pgids: Vec<u32>,
+    ttys: Vec<u32>,
+    selection: Selection,
+    inverse: bool,
+    excluded_pids: Vec<i32>,
+}
+
+impl Default for ProcessMatcher {
+    fn default() -> ProcessMatcher {
+        ProcessMatcher::new()
+    }
+}
+
+impl ProcessMatcher {
+    /// Create a matcher that matches every process
+    pub fn new() -> ProcessMatcher {
+        ProcessMatcher {
+            pattern: None,
+            target: MatchTarget::Name,
+            exact: false,
+            ignore_case: false,
+            uids: vec![],
+            ruids: vec![],
+            ppids: vec![],
+            pgids: vec![],
+            ttys: vec![],
+            selection: Selection::All,
+            inverse: false,
+            excluded_pids: vec![],
+        }
+    }
+
+    /// Only match processes where the regular expression `pattern` matches the `MatchTarget`
+    pub fn pattern(mut self, pattern: &str) -> ProcessMatcher {
+        self.pattern = Some(pattern.to_string());
+        self
+    }
+
+    /// Select what the pattern is matched against, the process name by default
+    pub fn target(mut self, target: MatchTarget) -> ProcessMatcher {
+        self.target = target;
+        self
+    }
+
+    /// Require the pattern to match the whole of the target rather than any part of it
+    pub fn exact(mut self, exact: bool) -> ProcessMatcher {
+        self.exact = exact;
+        self
+    }
+
+    /// Match the pattern ignoring case
+    pub fn ignore_case(mut self, ignore_case: bool) -> ProcessMatcher {
+        self.ignore_case = ignore_case;
+        self
+    }
+
+    /// Only match processes with this effective user id, may be called multiple times
+    pub fn uid(mut self, uid: uid_t) -> ProcessMatcher {
+        self.uids.push(uid);
+        self
+    }
+
+    /// Only match processes with this real user id, may be called multiple times
+    pub fn ruid(mut self, ruid: uid_t) -> ProcessMatcher {
+        self.ruids.push(ruid);
+        self
+    }
+
+    /// Only match processes with this parent process id, may be called multiple times
+    pub fn ppid(mut self, ppid: u32) -> ProcessMatcher {
+        self.ppids.push(ppid);
+        self
+    }
+
+    /// Only match processes in this process group, may be called multiple times
+    pub fn pgid(mut self, pgid: u32) -> ProcessMatcher {
+        self.pgids.push(pgid);
+        self
+    }
+
+    /// Only match processes with this controlling terminal device, may be called multiple times
+    pub fn tty(mut self, tty: u32) -> ProcessMatcher {
+        self.ttys.push(tty);
+        self
+    }
+
+    /// Only return the most recently started of the matching processes
+    pub fn newest(mut self) -> ProcessMatcher {
+        self.selection = Selection::Newest;
+        self
+    }
+
+    /// Only return the least recently started of the matching processes
+    pub fn oldest(mut self) -> ProcessMatcher {
+        self.selection = Selection::Oldest;
+        self
+    }
+
+    /// Return the processes that do not match the criteria instead of those that do
+    pub fn inverse(mut self, inverse: bool) -> ProcessMatcher {
+        self.inverse = inverse;
+        self
+    }
+
+    /// Never match the process with this pid, such as our own, even when `inverse` is set, may be
+    /// called multiple times. Excluded processes are left out before `newest` or `oldest` select
+    /// one of the matches.
+    pub fn exclude_pid(mut self, pid: i32) -> ProcessMatcher {
+        self.excluded_pids.push(pid);
+        self
+    }
+
+    fn regex(&self) -> Result<Option<Regex>> {
+        let pattern = match self.pattern {
+            Some(ref pattern) if self.exact => format!("^(?:{})$", pattern),
+            Some(ref pattern) => pattern.clone(),
+            None => return Ok(None),
+        };
+
+        match RegexBuilder::new(&pattern)
+            .case_insensitive(self.ignore_case)
+            .build()
+        {
+            Ok(regex) => Ok(Some(regex)),
+            Err(e) => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Invalid pattern: {}", e),
+            )),
+        }
+    }
+
+    fn target_text(&self, pid: i32) -> Result<String> {
+        match self.target {
+            MatchTarget::Name => proc_pid::name(pid),
+            MatchTarget::Path => proc_pid::pidpath(pid),
+            MatchTarget::CommandLine => proc_pid::cmdline(pid),
+        }
+    }
+
+    fn criteria_match(&self, regex: &Option<Regex>, pid: i32, info: &BSDInfo) -> Result<bool> {
+        if !self.uids.is_empty() && !self.uids.contains(&info.pbi_uid) {
+            return Ok(false);
+        }
+        if !self.ruids.is_empty() && !self.ruids.contains(&info.pbi_ruid) {
+            return Ok(false);
+        }
+        if !self.ppids.is_empty() && !self.ppids.contains(&info.pbi_ppid) {
+            return Ok(false);
+        }
+        if !self.pgids.is_empty() && !self.pgids.contains(&info.pbi_pgid) {
+            return Ok(false);
+        }
+        if !self.ttys.is_empty() && !self.ttys.contains(&info.e_tdev) {
+            return Ok(false);
+        }
+
+        match *regex {
+            Some(ref regex) => Ok(regex.is_match(&self.target_text(pid)?)),
+            None => Ok(true),
+        }
+    }
+
+    /// Returns the pids of the running processes that match
+    ///
+    /// Processes that exit, or that we are not permitted to inspect, while matching are left out,
+    /// any other error reading a process is returned.
+    pub fn find(&self) -> Result<Vec<i32>> {
+        let regex = self.regex()?;
+
+        let mut matches: Vec<(i32, (u64, u64))> = vec![];
+        for pid in proc_pid::listpids(ProcType::ProcAllPIDS, 0)? {
+            let pid = pid as i32;
+            if self.excluded_pids.contains(&pid) {
+                continue;
+            }
+            let info = match proc_pid::pidinfo::<BSDInfo>(pid, 0) {
+                Ok(info) => info,
+                Err(_) => continue,
+            };
+
+            match self.criteria_match(&regex, pid, &info) {
+                Ok(matched) if matched != self.inverse => {
+                    matches.push((pid, (info.pbi_start_tvsec, info.pbi_start_tvusec)))
+                }
+                Ok(_) => {}
+                Err(ref err) if gone_or_denied(err) => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        let selected = match self.selection {
+            Selection::All => return Ok(matches.into_iter().map(|(pid, _)| pid).collect()),
+            Selection::Newest => matches.iter().max_by_key(|&&(_, started)| started),
+            Selection::Oldest => matches.iter().min_by_key(|&&(_, started)| started),
+        };
+
+        Ok(selected.map(|&(pid, _)| pid).into_iter().collect())
+    }
+}
+
+// Whether an error reading a process is because it has exited or we may not inspect it
+fn gone_or_denied(err: &Error) -> bool {
+    match err.raw_os_error() {
+        Some(libc::ESRCH) | Some(libc::EPERM) | Some(libc::EACCES) => true,
+        _ => matches!(
+            err.kind(),
+            ErrorKind::NotFound | ErrorKind::PermissionDenied
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::process::{self, Command};
+
+    #[test]
+    fn find_self_by_pid_and_name_test() {
+        let pid = process::id() as i32;
+        let name = proc_pid::name(pid).unwrap();
+        let matcher = ProcessMatcher::new()
+            .pattern(&regex::escape(&name))
+            .exact(true)
+            .uid(unsafe { libc::geteuid() });
+        match matcher.find() {
+            Ok(pids) => assert!(pids.contains(&pid)),
+            Err(err) => panic!("Error matching processes: {}", err),
+        }
+    }
+
+    #[test]
+    fn inverse_excludes_self_test() {
+        let pid = process::id() as i32;
+        let name = proc_pid::name(pid).unwrap();
+        let matcher = ProcessMatcher::new()
+            .pattern(&regex::escape(&name))
+            .exact(true)
+            .inverse(true);
+        match matcher.find() {
+            Ok(pids) => assert!(!pids.contains(&pid)),
+            Err(err) => panic!("Error matching processes: {}", err),
+        }
+    }
+
+    #[test]
+    fn newest_returns_one_test() {
+        match ProcessMatcher::new().newest().find() {
+            Ok(pids) => assert_eq!(pids.len(), 1),
+            Err(err) => panic!("Error matching processes: {}", err),
+        }
+    }
+
+    // As pgrep -n does with its own pid, the newest process is picked after leaving out those
+    // excluded
+    #[test]
+    fn newest_excluding_pid_test() {
+        let mut older = Command::new("sleep").arg("5").spawn().unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        let mut newer = Command::new("sleep").arg("5").spawn().unwrap();
+        let matcher = || {
+            ProcessMatcher::new()
+                .pattern("^sleep$")
+                .ppid(process::id())
+                .newest()
+        };
+
+        let newest = matcher().find();
+        let newest_excluding = matcher().exclude_pid(newer.id() as i32).find();
+        for child in [&mut older, &mut newer].iter_mut() {
+            child.kill().unwrap();
+            child.wait().unwrap();
+        }
+
+        assert_eq!(newest.unwrap(), vec![newer.id() as i32]);
+        assert_eq!(newest_excluding.unwrap(), vec![older.id() as i32]);
+    }
+
+    #[test]
+    fn invalid_pattern_test() {
+        match ProcessMatcher::new().pattern("(").find() {
+            Ok(_) => panic!("An invalid pattern should not match"),
+            Err(err) => assert_eq!(err.kind(), ErrorKind::InvalidInput),
+        }
+    }
+}
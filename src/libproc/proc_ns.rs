@@ -0,0 +1,270 @@
+// The namespaces processes are in on Linux, from the links in /proc/<pid>/ns
+// See http://man7.org/linux/man-pages/man7/namespaces.7.html
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+
+use crate::libproc::procfs;
+
+/// A type of namespace
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum NamespaceType {
+    /// Mount points
+    Mnt,
+    /// Process ids
+    Pid,
+    /// Network devices, addresses, ports etc.
+    Net,
+    /// System V IPC objects and POSIX message queues
+    Ipc,
+    /// Hostname and NIS domain name
+    Uts,
+    /// User and group ids
+    User,
+    /// The cgroup root directory
+    Cgroup,
+    /// The boot and monotonic clocks (since Linux 5.6)
+    Time,
+}
+
+impl NamespaceType {
+    /// Every type of namespace
+    pub fn all() -> &'static [NamespaceType] {
+        &[
+            NamespaceType::Mnt,
+            NamespaceType::Pid,
+            NamespaceType::Net,
+            NamespaceType::Ipc,
+            NamespaceType::Uts,
+            NamespaceType::User,
+            NamespaceType::Cgroup,
+            NamespaceType::Time,
+        ]
+    }
+
+    /// The name of the link in /proc/<pid>/ns, such as "net"
+    pub fn name(self) -> &'static str {
+        match self {
+            NamespaceType::Mnt => "mnt",
+            NamespaceType::Pid => "pid",
+            NamespaceType::Net => "net",
+            NamespaceType::Ipc => "ipc",
+            NamespaceType::Uts => "uts",
+            NamespaceType::User => "user",
+            NamespaceType::Cgroup => "cgroup",
+            NamespaceType::Time => "time",
+        }
+    }
+
+    /// Parse the target of a link in /proc/<pid>/ns, such as "net:[4026531833]", to the inode
+    /// number that identifies the namespace
+    pub fn parse_link(self, link: &str) -> Result<u64> {
+        link.strip_prefix(self.name())
+            .and_then(|rest| rest.strip_prefix(":["))
+            .and_then(|rest| rest.strip_suffix(']'))
+            .and_then(|inode| inode.parse().ok())
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Could not parse {} namespace link '{}'", self.name(), link),
+                )
+            })
+    }
+}
+
+impl fmt::Display for NamespaceType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The namespaces a process is in, each identified by its inode number. A namespace is `None`
+/// when the kernel does not have that type of namespace.
+///
+/// Processes with equal `Namespaces` see the same system, such as the processes of a container.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Namespaces {
+    pub mnt: Option<u64>,
+    pub pid: Option<u64>,
+    pub net: Option<u64>,
+    pub ipc: Option<u64>,
+    pub uts: Option<u64>,
+    pub user: Option<u64>,
+    pub cgroup: Option<u64>,
+    pub time: Option<u64>,
+}
+
+impl Namespaces {
+    /// The namespace of a type
+    pub fn get(&self, ns_type: NamespaceType) -> Option<u64> {
+        match ns_type {
+            NamespaceType::Mnt => self.mnt,
+            NamespaceType::Pid => self.pid,
+            NamespaceType::Net => self.net,
+            NamespaceType::Ipc => self.ipc,
+            NamespaceType::Uts => self.uts,
+            NamespaceType::User => self.user,
+            NamespaceType::Cgroup => self.cgroup,
+            NamespaceType::Time => self.time,
+        }
+    }
+
+    fn set(&mut self, ns_type: NamespaceType, inode: Option<u64>) {
+        let field = match ns_type {
+            NamespaceType::Mnt => &mut self.mnt,
+            NamespaceType::Pid => &mut self.pid,
+            NamespaceType::Net => &mut self.net,
+            NamespaceType::Ipc => &mut self.ipc,
+            NamespaceType::Uts => &mut self.uts,
+            NamespaceType::User => &mut self.user,
+            NamespaceType::Cgroup => &mut self.cgroup,
+            NamespaceType::Time => &mut self.time,
+        };
+        *field = inode;
+    }
+
+    /// The types of namespace in which these namespaces and others differ
+    pub fn differences(&self, other: &Namespaces) -> Vec<NamespaceType> {
+        NamespaceType::all()
+            .iter()
+            .copied()
+            .filter(|&ns_type| self.get(ns_type) != other.get(ns_type))
+            .collect()
+    }
+}
+
+/// Returns the namespace of a type that the process with the specified pid is in
+///
+/// Reading the namespaces of a process of another user needs the same permission as ptrace(2).
+pub fn pidnamespace(pid: i32, ns_type: NamespaceType) -> Result<u64> {
+    let link = fs::read_link(procfs::pid_path(pid, &format!("ns/{}", ns_type.name())))
+        .map_err(|err| procfs::pid_error(pid, err))?;
+    ns_type.parse_link(&link.to_string_lossy())
+}
+
+/// Returns all the namespaces the process with the specified pid is in
+///
+/// # Examples
+///
+/// ```
+/// use std::process;
+/// use libproc::libproc::proc_ns::pidnamespaces;
+///
+/// let namespaces = pidnamespaces(process::id() as i32).unwrap();
+/// println!("In network namespace {:?}", namespaces.net);
+/// ```
+pub fn pidnamespaces(pid: i32) -> Result<Namespaces> {
+    let mut namespaces = Namespaces::default();
+    for &ns_type in NamespaceType::all() {
+        match pidnamespace(pid, ns_type) {
+            Ok(inode) => namespaces.set(ns_type, Some(inode)),
+            // The link is missing when the kernel does not have that type of namespace
+            Err(ref err) if err.kind() == ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(namespaces)
+}
+
+/// The processes we may inspect grouped by the namespaces they are in, such as the processes of
+/// each container. The pids of each group are in order.
+pub fn group_by_namespaces() -> Result<BTreeMap<Namespaces, Vec<i32>>> {
+    let mut groups: BTreeMap<Namespaces, Vec<i32>> = BTreeMap::new();
+    for pid in procfs::pids()? {
+        if let Ok(namespaces) = pidnamespaces(pid) {
+            groups.entry(namespaces).or_default().push(pid);
+        }
+    }
+    Ok(groups)
+}
+
+/// The processes we may inspect grouped by the namespace of a type they are in
+pub fn group_by_namespace(ns_type: NamespaceType) -> Result<BTreeMap<u64, Vec<i32>>> {
+    let mut groups: BTreeMap<u64, Vec<i32>> = BTreeMap::new();
+    for pid in procfs::pids()? {
+        if let Ok(inode) = pidnamespace(pid, ns_type) {
+            groups.entry(inode).or_default().push(pid);
+        }
+    }
+    Ok(groups)
+}
+
+/// Returns the PIDs of the processes in the same namespace of a type as the process with the
+/// specified pid, including that process, in the manner of `proc_pid::listpids`
+///
+/// # Examples
+///
+/// ```
+/// use std::process;
+/// use libproc::libproc::proc_ns::{listpids_sharing, NamespaceType};
+///
+/// let pids = listpids_sharing(NamespaceType::Net, process::id() as i32).unwrap();
+/// assert!(pids.contains(&process::id()));
+/// ```
+pub fn listpids_sharing(ns_type: NamespaceType, pid: i32) -> Result<Vec<u32>> {
+    let inode = pidnamespace(pid, ns_type)?;
+    Ok(procfs::pids()?
+        .into_iter()
+        .filter(|&other| pidnamespace(other, ns_type).is_ok_and(|other| other == inode))
+        .map(|other| other as u32)
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::process::{self, Command};
+
+    #[test]
+    fn parse_link_test() {
+        assert_eq!(
+            NamespaceType::Net.parse_link("net:[4026531833]").unwrap(),
+            4_026_531_833
+        );
+        assert!(NamespaceType::Pid.parse_link("net:[4026531833]").is_err());
+        assert!(NamespaceType::Net.parse_link("net:4026531833").is_err());
+    }
+
+    #[test]
+    fn differences_test() {
+        let host = Namespaces {
+            mnt: Some(1),
+            net: Some(2),
+            ..Namespaces::default()
+        };
+        let container = Namespaces {
+            net: Some(3),
+            ..host
+        };
+        assert_eq!(host.differences(&container), vec![NamespaceType::Net]);
+        assert!(host.differences(&host).is_empty());
+    }
+
+    #[test]
+    fn namespaces_of_child_test() {
+        let mut child = Command::new("sleep").arg("5").spawn().unwrap();
+        let pid = process::id() as i32;
+        let child_pid = child.id() as i32;
+
+        let namespaces = pidnamespaces(pid);
+        let child_namespaces = pidnamespaces(child_pid);
+        let sharing = listpids_sharing(NamespaceType::Mnt, pid);
+        let groups = group_by_namespaces();
+        child.kill().unwrap();
+        child.wait().unwrap();
+
+        let namespaces = namespaces.unwrap();
+        assert!(namespaces.mnt.is_some());
+        assert_eq!(namespaces, child_namespaces.unwrap());
+        assert!(sharing.unwrap().contains(&(child_pid as u32)));
+        let group = &groups.unwrap()[&namespaces];
+        assert!(group.contains(&pid) && group.contains(&child_pid));
+    }
+
+    #[test]
+    fn pidnamespace_missing_test() {
+        let err = pidnamespace(i32::MAX, NamespaceType::Net).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ESRCH));
+    }
+}
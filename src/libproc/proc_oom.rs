@@ -0,0 +1,157 @@
+// How likely processes are to be killed by the Linux out of memory killer, and protecting them
+// See http://man7.org/linux/man-pages/man5/proc.5.html
+use std::cmp::Reverse;
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+
+use crate::libproc::proc_pid::{self, ProcType, TaskInfo};
+use crate::libproc::procfs;
+
+/// The `oom_score_adj` of a process that the OOM killer never kills
+pub const OOM_SCORE_ADJ_MIN: i32 = -1000;
+/// The `oom_score_adj` of a process that the OOM killer kills first
+pub const OOM_SCORE_ADJ_MAX: i32 = 1000;
+
+fn parse_number<T: std::str::FromStr>(contents: &str, file: &str) -> Result<T> {
+    contents.trim().parse().map_err(|_| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("Could not parse {} '{}'", file, contents.trim()),
+        )
+    })
+}
+
+/// Returns the score the OOM killer gives the process with the specified pid, from its memory
+/// use and `oom_score_adj`. The process with the highest score is killed first.
+pub fn pidoomscore(pid: i32) -> Result<u32> {
+    parse_number(&procfs::read_pid_file(pid, "oom_score")?, "oom_score")
+}
+
+/// Returns the adjustment, from `OOM_SCORE_ADJ_MIN` to `OOM_SCORE_ADJ_MAX`, made to the OOM
+/// score of the process with the specified pid
+pub fn pidoomscoreadj(pid: i32) -> Result<i32> {
+    parse_number(
+        &procfs::read_pid_file(pid, "oom_score_adj")?,
+        "oom_score_adj",
+    )
+}
+
+/// Sets the adjustment made to the OOM score of the process with the specified pid, from
+/// `OOM_SCORE_ADJ_MIN`, which protects it from the OOM killer, to `OOM_SCORE_ADJ_MAX`.
+///
+/// Lowering the adjustment below the lowest it has been set to needs CAP_SYS_RESOURCE.
+///
+/// # Examples
+///
+/// ```no_run
+/// use libproc::libproc::proc_oom::{setpidoomscoreadj, OOM_SCORE_ADJ_MIN};
+///
+/// // Never let the OOM killer kill the database
+/// setpidoomscoreadj(1234, OOM_SCORE_ADJ_MIN).unwrap();
+/// ```
+pub fn setpidoomscoreadj(pid: i32, adj: i32) -> Result<()> {
+    if !(OOM_SCORE_ADJ_MIN..=OOM_SCORE_ADJ_MAX).contains(&adj) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "oom_score_adj must be from {} to {}",
+                OOM_SCORE_ADJ_MIN, OOM_SCORE_ADJ_MAX
+            ),
+        ));
+    }
+    fs::write(procfs::pid_path(pid, "oom_score_adj"), adj.to_string())
+        .map_err(|err| procfs::pid_error(pid, err))
+}
+
+/// A process the OOM killer may kill
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OomCandidate {
+    pub pid: i32,
+    pub name: String,
+    pub oom_score: u32,
+    pub oom_score_adj: i32,
+    /// The resident set size in bytes, from `TaskInfo::pti_resident_size`
+    pub rss: u64,
+}
+
+impl OomCandidate {
+    /// Whether the OOM killer never kills the process
+    pub fn is_protected(&self) -> bool {
+        self.oom_score_adj == OOM_SCORE_ADJ_MIN
+    }
+}
+
+fn oom_candidate(pid: i32) -> Result<OomCandidate> {
+    Ok(OomCandidate {
+        pid,
+        name: proc_pid::name(pid)?,
+        oom_score: pidoomscore(pid)?,
+        oom_score_adj: pidoomscoreadj(pid)?,
+        rss: proc_pid::pidinfo::<TaskInfo>(pid, 0)?.pti_resident_size,
+    })
+}
+
+/// Every process we may inspect, in the order the OOM killer would kill them: highest OOM score
+/// first, and of those with the same score the largest first
+///
+/// # Examples
+///
+/// ```
+/// use libproc::libproc::proc_oom::oom_ranking;
+///
+/// if let Some(first) = oom_ranking().unwrap().first() {
+///     println!("{} ({}) would be killed first", first.name, first.pid);
+/// }
+/// ```
+pub fn oom_ranking() -> Result<Vec<OomCandidate>> {
+    let mut candidates: Vec<OomCandidate> = proc_pid::listpids(ProcType::ProcAllPIDS, 0)?
+        .into_iter()
+        .filter_map(|pid| oom_candidate(pid as i32).ok())
+        .collect();
+    candidates.sort_by_key(|candidate| {
+        (
+            Reverse(candidate.oom_score),
+            Reverse(candidate.rss),
+            candidate.pid,
+        )
+    });
+    Ok(candidates)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::process::{self, Command};
+
+    #[test]
+    fn pidoomscore_test() {
+        let pid = process::id() as i32;
+        assert!(pidoomscore(pid).unwrap() <= 2 * OOM_SCORE_ADJ_MAX as u32);
+        let adj = pidoomscoreadj(pid).unwrap();
+        assert!((OOM_SCORE_ADJ_MIN..=OOM_SCORE_ADJ_MAX).contains(&adj));
+
+        let err = pidoomscore(i32::MAX).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ESRCH));
+    }
+
+    // Raising the adjustment of a process needs no privileges
+    #[test]
+    fn setpidoomscoreadj_test() {
+        let mut child = Command::new("sleep").arg("5").spawn().unwrap();
+        let pid = child.id() as i32;
+
+        let set = setpidoomscoreadj(pid, OOM_SCORE_ADJ_MAX);
+        let adj = pidoomscoreadj(pid);
+        let ranking = oom_ranking();
+        let out_of_range = setpidoomscoreadj(pid, OOM_SCORE_ADJ_MAX + 1);
+        child.kill().unwrap();
+        child.wait().unwrap();
+
+        set.unwrap();
+        assert_eq!(adj.unwrap(), OOM_SCORE_ADJ_MAX);
+        let ranking = ranking.unwrap();
+        let position = |pid: i32| ranking.iter().position(|candidate| candidate.pid == pid);
+        assert!(position(pid).unwrap() < position(process::id() as i32).unwrap());
+        assert_eq!(out_of_range.unwrap_err().kind(), ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,403 @@
+// Which container, Kubernetes pod or systemd unit a process belongs to on Linux, from the paths
+// of its cgroups
+use std::io::{ErrorKind, Result};
+
+use crate::libproc::proc_cgroup::{self, CgroupEntry};
+use crate::libproc::proc_ns::{self, NamespaceType};
+
+/// The container runtime that started a container
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ContainerRuntime {
+    Docker,
+    Containerd,
+    CriO,
+    Podman,
+    /// A container of a Kubernetes pod whose cgroup does not say which runtime started it, as
+    /// when the kubelet uses the cgroupfs driver with containerd
+    Unknown,
+}
+
+/// The kind of a systemd unit that has a cgroup
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum UnitKind {
+    /// A service started by systemd, such as "sshd.service"
+    Service,
+    /// Processes started elsewhere and put in a unit, such as a login session "session-2.scope"
+    Scope,
+    /// A group of units, such as "user-1000.slice"
+    Slice,
+}
+
+/// Where a process comes from: which container, pod or service it is part of
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ProcessOrigin {
+    /// A process of a container
+    Container {
+        runtime: ContainerRuntime,
+        /// The full id of the container, 64 hexadecimal digits
+        id: String,
+        /// The UID of the Kubernetes pod the container is part of, if it is
+        pod_uid: Option<String>,
+    },
+    /// A process of a Kubernetes pod that is not in any of its containers
+    KubernetesPod { uid: String },
+    /// A process of a systemd unit, such as "nginx.service"
+    Systemd { kind: UnitKind, unit: String },
+    /// A process in other namespaces than the init process whose cgroup does not say what
+    /// started it, most likely of a container of a runtime not known here
+    UnknownContainer { cgroup: String },
+    /// Any other process, such as one in the root cgroup, or one whose namespaces could not be
+    /// compared with those of init
+    Other { cgroup: String },
+}
+
+fn is_container_id(id: &str) -> bool {
+    id.len() == 64 && id.bytes().all(|byte| byte.is_ascii_hexdigit())
+}
+
+fn is_pod_uid(uid: &str) -> bool {
+    uid.len() == 36
+        && uid
+            .bytes()
+            .all(|byte| byte.is_ascii_hexdigit() || byte == b'-')
+}
+
+// A container from a component of a cgroup path, such as "docker-<id>.scope" or "crio-<id>",
+// which may have the runtime as a prefix. The monitors podman and CRI-O run for each container,
+// "libpod-conmon-<id>.scope" and "crio-conmon-<id>.scope", are not part of the container.
+fn container(component: &str, parents: &[&str]) -> Option<(ContainerRuntime, String)> {
+    let name = component.strip_suffix(".scope").unwrap_or(component);
+    let prefixes = [
+        ("docker-", ContainerRuntime::Docker),
+        ("cri-containerd-", ContainerRuntime::Containerd),
+        ("crio-", ContainerRuntime::CriO),
+        ("libpod-", ContainerRuntime::Podman),
+    ];
+    for (prefix, runtime) in prefixes {
+        if let Some(id) = name.strip_prefix(prefix) {
+            return Some((runtime, id.to_string())).filter(|(_, id)| is_container_id(id));
+        }
+    }
+
+    // With the cgroupfs driver the container is a plain id under a directory of the runtime
+    if !is_container_id(name) {
+        return None;
+    }
+    let runtime = if parents.contains(&"docker") {
+        ContainerRuntime::Docker
+    } else if parents.contains(&"libpod_parent") {
+        ContainerRuntime::Podman
+    } else if parents.iter().any(|parent| parent.starts_with("kubepods")) {
+        ContainerRuntime::Unknown
+    } else {
+        return None;
+    };
+    Some((runtime, name.to_string()))
+}
+
+// The UID of a pod from a component of a cgroup path, "pod<uid>" with the cgroupfs driver or
+// "kubepods-burstable-pod<uid>.slice" with the systemd driver, which has '_' in place of '-'
+fn pod_uid(component: &str) -> Option<String> {
+    let name = component.strip_suffix(".slice").unwrap_or(component);
+    let uid = match name.rfind("-pod") {
+        Some(start) if name.starts_with("kubepods") => &name[start + 4..],
+        _ => name.strip_prefix("pod")?,
+    };
+    let uid = uid.replace('_', "-");
+    Some(uid).filter(|uid| is_pod_uid(uid))
+}
+
+fn systemd_unit(component: &str) -> Option<ProcessOrigin> {
+    let kinds = [
+        (".service", UnitKind::Service),
+        (".scope", UnitKind::Scope),
+        (".slice", UnitKind::Slice),
+    ];
+    kinds
+        .iter()
+        .find(|(suffix, _)| component.len() > suffix.len() && component.ends_with(suffix))
+        .map(|&(_, kind)| ProcessOrigin::Systemd {
+            kind,
+            unit: component.to_string(),
+        })
+}
+
+impl ProcessOrigin {
+    /// Classify a process by the path of its cgroup, as in /proc/<pid>/cgroup, such as
+    /// "/system.slice/docker-<id>.scope". Returns `None` if the path does not say what started
+    /// the process.
+    ///
+    /// Containers are looked for first, then Kubernetes pods and then systemd units, as the
+    /// cgroup of a container is often a systemd scope in the slice of a pod.
+    pub fn from_cgroup_path(path: &str) -> Option<ProcessOrigin> {
+        let components: Vec<&str> = path.split('/').filter(|c| !c.is_empty()).collect();
+        let pod = components.iter().find_map(|component| pod_uid(component));
+
+        for (index, component) in components.iter().enumerate().rev() {
+            if let Some((runtime, id)) = container(component, &components[..index]) {
+                return Some(ProcessOrigin::Container {
+                    runtime,
+                    id,
+                    pod_uid: pod,
+                });
+            }
+        }
+        if let Some(uid) = pod {
+            return Some(ProcessOrigin::KubernetesPod { uid });
+        }
+        components
+            .iter()
+            .rev()
+            .find_map(|component| systemd_unit(component))
+    }
+
+    /// The id of the container the process is in, if it is
+    pub fn container_id(&self) -> Option<&str> {
+        match self {
+            ProcessOrigin::Container { id, .. } => Some(id),
+            _ => None,
+        }
+    }
+}
+
+// The cgroup path that says most about a process: that of the unified hierarchy, or of the
+// name=systemd hierarchy when only that is managed by systemd, or else of any hierarchy
+fn origin_path(entries: &[CgroupEntry]) -> String {
+    let unified = entries
+        .iter()
+        .find(|entry| entry.is_v2() && entry.path != "/");
+    let systemd = entries
+        .iter()
+        .find(|entry| entry.controllers.iter().any(|c| c == "name=systemd") && entry.path != "/");
+    unified
+        .or(systemd)
+        .or_else(|| entries.iter().find(|entry| entry.path != "/"))
+        .or_else(|| entries.first())
+        .map(|entry| entry.path.clone())
+        .unwrap_or_else(|| "/".to_string())
+}
+
+/// Returns which container, Kubernetes pod or systemd unit the process with the specified pid
+/// is part of
+///
+/// The cgroup paths of a process in another cgroup namespace are shown relative to the
+/// namespace of the caller, so run this outside of any container to identify them all.
+///
+/// When the cgroups of a process do not show where it came from, its namespaces are compared
+/// with those of init to tell whether it is in a container. Reading the namespaces of init needs
+/// the privilege to ptrace it (usually that means running as root), and without it such a
+/// process is reported as `Other`.
+///
+/// # Examples
+///
+/// ```
+/// use std::process;
+/// use libproc::libproc::proc_origin::{pidorigin, ProcessOrigin};
+///
+/// match pidorigin(process::id() as i32) {
+///     Ok(ProcessOrigin::Container { runtime, id, .. }) => println!("In {:?} container {}", runtime, id),
+///     Ok(origin) => println!("{:?}", origin),
+///     Err(err) => println!("Could not tell where we came from: {}", err),
+/// }
+/// ```
+pub fn pidorigin(pid: i32) -> Result<ProcessOrigin> {
+    let cgroup = origin_path(&proc_cgroup::pidcgroups(pid)?);
+    if let Some(origin) = ProcessOrigin::from_cgroup_path(&cgroup) {
+        return Ok(origin);
+    }
+
+    let own = isolating_namespaces(pid)?;
+    let init = match isolating_namespaces(1) {
+        Ok(init) => Some(init),
+        Err(ref err) if err.kind() == ErrorKind::PermissionDenied => None,
+        Err(err) => return Err(err),
+    };
+    Ok(classify(cgroup, own, init))
+}
+
+// The mount and pid namespaces, in which the processes of a container differ from init
+fn isolating_namespaces(pid: i32) -> Result<[u64; 2]> {
+    Ok([
+        proc_ns::pidnamespace(pid, NamespaceType::Mnt)?,
+        proc_ns::pidnamespace(pid, NamespaceType::Pid)?,
+    ])
+}
+
+// A process in other namespaces than init is in a container, even if its cgroup does not say
+// which. Without the namespaces of init there is no telling.
+fn classify(cgroup: String, own: [u64; 2], init: Option<[u64; 2]>) -> ProcessOrigin {
+    match init {
+        Some(init) if init != own => ProcessOrigin::UnknownContainer { cgroup },
+        _ => ProcessOrigin::Other { cgroup },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::process;
+
+    const ID: &str = "8f2c1b0d6c4e4a3b9f1e2d3c4b5a69788f2c1b0d6c4e4a3b9f1e2d3c4b5a6978";
+    const POD: &str = "6a8b1c2d-3e4f-5a6b-7c8d-9e0f1a2b3c4d";
+
+    fn container(runtime: ContainerRuntime, pod_uid: Option<&str>) -> Option<ProcessOrigin> {
+        Some(ProcessOrigin::Container {
+            runtime,
+            id: ID.to_string(),
+            pod_uid: pod_uid.map(String::from),
+        })
+    }
+
+    fn systemd(kind: UnitKind, unit: &str) -> Option<ProcessOrigin> {
+        Some(ProcessOrigin::Systemd {
+            kind,
+            unit: unit.to_string(),
+        })
+    }
+
+    #[test]
+    fn container_paths_test() {
+        let cases = [
+            // Docker with the systemd and cgroupfs drivers
+            (
+                format!("/system.slice/docker-{}.scope", ID),
+                container(ContainerRuntime::Docker, None),
+            ),
+            (
+                format!("/docker/{}", ID),
+                container(ContainerRuntime::Docker, None),
+            ),
+            // podman, rootful and rootless, and with the cgroupfs driver
+            (
+                format!("/machine.slice/libpod-{}.scope", ID),
+                container(ContainerRuntime::Podman, None),
+            ),
+            (
+                format!(
+                    "/user.slice/user-1000.slice/user@1000.service/user.slice/libpod-{}.scope/container",
+                    ID
+                ),
+                container(ContainerRuntime::Podman, None),
+            ),
+            (
+                format!("/libpod_parent/libpod-{}", ID),
+                container(ContainerRuntime::Podman, None),
+            ),
+        ];
+        for (path, origin) in cases.iter() {
+            assert_eq!(&ProcessOrigin::from_cgroup_path(path), origin, "{}", path);
+        }
+    }
+
+    #[test]
+    fn kubernetes_paths_test() {
+        let systemd_pod = POD.replace('-', "_");
+        let cases = [
+            (
+                format!(
+                    "/kubepods.slice/kubepods-burstable.slice/kubepods-burstable-pod{}.slice/cri-containerd-{}.scope",
+                    systemd_pod, ID
+                ),
+                container(ContainerRuntime::Containerd, Some(POD)),
+            ),
+            (
+                format!(
+                    "/kubepods.slice/kubepods-pod{}.slice/crio-{}.scope",
+                    systemd_pod, ID
+                ),
+                container(ContainerRuntime::CriO, Some(POD)),
+            ),
+            (
+                format!("/kubepods/besteffort/pod{}/{}", POD, ID),
+                container(ContainerRuntime::Unknown, Some(POD)),
+            ),
+            (
+                format!("/kubepods/burstable/pod{}/crio-{}", POD, ID),
+                container(ContainerRuntime::CriO, Some(POD)),
+            ),
+            (
+                format!(
+                    "/kubepods.slice/kubepods-pod{}.slice/crio-conmon-{}.scope",
+                    systemd_pod, ID
+                ),
+                Some(ProcessOrigin::KubernetesPod {
+                    uid: POD.to_string(),
+                }),
+            ),
+        ];
+        for (path, origin) in cases.iter() {
+            assert_eq!(&ProcessOrigin::from_cgroup_path(path), origin, "{}", path);
+        }
+    }
+
+    #[test]
+    fn systemd_paths_test() {
+        assert_eq!(
+            ProcessOrigin::from_cgroup_path("/system.slice/sshd.service"),
+            systemd(UnitKind::Service, "sshd.service")
+        );
+        assert_eq!(
+            ProcessOrigin::from_cgroup_path("/user.slice/user-1000.slice/session-2.scope"),
+            systemd(UnitKind::Scope, "session-2.scope")
+        );
+        assert_eq!(
+            ProcessOrigin::from_cgroup_path(
+                "/user.slice/user-1000.slice/user@1000.service/app.slice/app-gnome-firefox-2121.scope"
+            ),
+            systemd(UnitKind::Scope, "app-gnome-firefox-2121.scope")
+        );
+        assert_eq!(
+            ProcessOrigin::from_cgroup_path(&format!("/machine.slice/libpod-conmon-{}.scope", ID)),
+            systemd(UnitKind::Scope, &format!("libpod-conmon-{}.scope", ID))
+        );
+        assert_eq!(
+            ProcessOrigin::from_cgroup_path("/init.scope"),
+            systemd(UnitKind::Scope, "init.scope")
+        );
+        assert_eq!(ProcessOrigin::from_cgroup_path("/"), None);
+        assert_eq!(ProcessOrigin::from_cgroup_path("/docker/not-an-id"), None);
+    }
+
+    #[test]
+    fn origin_path_test() {
+        let entries = proc_cgroup::parse_procfs_cgroups(
+            "4:memory:/docker/abc\n1:name=systemd:/system.slice/docker.service\n0::/\n",
+        )
+        .unwrap();
+        assert_eq!(origin_path(&entries), "/system.slice/docker.service");
+        assert_eq!(origin_path(&entries[..1]), "/docker/abc");
+        assert_eq!(origin_path(&[]), "/");
+    }
+
+    #[test]
+    fn classify_test() {
+        let cgroup = || "/".to_string();
+        let other = ProcessOrigin::Other { cgroup: cgroup() };
+        assert_eq!(classify(cgroup(), [1, 2], Some([1, 2])), other);
+        assert_eq!(classify(cgroup(), [1, 2], None), other);
+        for init in [[3, 2], [1, 3], [3, 4]] {
+            assert_eq!(
+                classify(cgroup(), [1, 2], Some(init)),
+                ProcessOrigin::UnknownContainer { cgroup: cgroup() }
+            );
+        }
+    }
+
+    #[test]
+    fn pidorigin_test() {
+        // Whether or not we may read the namespaces of init, our own origin is found
+        let pid = process::id() as i32;
+        let cgroup = origin_path(&proc_cgroup::pidcgroups(pid).unwrap());
+        match pidorigin(pid).unwrap() {
+            ProcessOrigin::UnknownContainer { cgroup: found }
+            | ProcessOrigin::Other { cgroup: found } => {
+                assert_eq!(ProcessOrigin::from_cgroup_path(&cgroup), None);
+                assert_eq!(found, cgroup);
+            }
+            origin => assert_eq!(Some(origin), ProcessOrigin::from_cgroup_path(&cgroup)),
+        }
+
+        let err = pidorigin(i32::MAX).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ESRCH));
+    }
+}
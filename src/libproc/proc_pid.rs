@@ -1,10 +1,25 @@
 use libc::{
-    c_char, c_int, c_short, c_uchar, c_ushort, c_void, gid_t, in6_addr, in_addr, off_t,
-    sockaddr_un, uid_t, IF_NAMESIZE, SOCK_MAXADDRLEN,
+    c_char, c_int, c_short, c_uchar, c_ushort, gid_t, in6_addr, in_addr, off_t, sockaddr_un, uid_t,
+    IF_NAMESIZE,
 };
+#[cfg(target_os = "macos")]
+use libc::{c_void, size_t};
+use std::ffi::OsString;
+use std::fmt;
+#[cfg(target_os = "linux")]
+use std::fs;
 use std::io::{Error, ErrorKind, Result};
 use std::mem;
+use std::os::unix::ffi::OsStringExt;
+use std::path::PathBuf;
+#[cfg(target_os = "macos")]
 use std::ptr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::libproc::bytes::u64_at;
+use crate::libproc::proc_sched::SchedPolicy;
+#[cfg(target_os = "linux")]
+use crate::libproc::procfs;
 
 // Since we cannot access C macros for constants from Rust - I have had to redefine this, based on Apple's source code
 // See http://opensource.apple.com/source/Libc/Libc-594.9.4/darwin/libproc.c
@@ -24,6 +39,17 @@ pub const PROC_PIDPATHINFO_MAXSIZE: usize = 4 * MAXPATHLEN;
 // from http://opensource.apple.com//source/xnu/xnu-1456.1.26/bsd/sys/proc_info.h
 const MAXTHREADNAMESIZE: usize = 64;
 
+// from http://opensource.apple.com//source/xnu/xnu-1456.1.26/bsd/sys/socket.h
+// (libc only defines it for Mac OS X)
+const SOCK_MAXADDRLEN: usize = 255;
+
+// from http://opensource.apple.com//source/xnu/xnu-1456.1.26/bsd/sys/fcntl.h, the open flags of
+// a proc_fileinfo
+#[cfg(target_os = "macos")]
+const FREAD: u32 = 0x0001;
+#[cfg(target_os = "macos")]
+const FWRITE: u32 = 0x0002;
+
 // From http://opensource.apple.com//source/xnu/xnu-1456.1.26/bsd/sys/proc_info.h and
 // http://fxr.watson.org/fxr/source/bsd/sys/proc_info.h?v=xnu-2050.18.24
 #[derive(Copy, Clone)]
@@ -43,6 +69,12 @@ const MAXCOMLEN: usize = 16;
 // type-guaranteed flavor correctness
 pub trait PIDInfo: Default {
     fn flavor() -> PidInfoFlavor;
+
+    // There is no libproc on Linux, so the types that can be built from /proc do so themselves
+    #[cfg(target_os = "linux")]
+    fn from_procfs(_pid: i32, _arg: u64) -> Result<Self> {
+        Err(procfs::unsupported("This PIDInfo flavor"))
+    }
 }
 
 // structures from http://opensource.apple.com//source/xnu/xnu-1456.1.26/bsd/sys/proc_info.h
@@ -84,10 +116,59 @@ pub struct TaskInfo {
     pub pti_priority: i32, // task priority
 }
 
+impl TaskInfo {
+    /// The scheduling policy of `pti_policy`, `None` if it is not one that is known
+    pub fn policy(&self) -> Option<SchedPolicy> {
+        SchedPolicy::from_raw(self.pti_policy)
+    }
+}
+
 impl PIDInfo for TaskInfo {
     fn flavor() -> PidInfoFlavor {
         PidInfoFlavor::TaskInfo
     }
+
+    // Times are reported in nanoseconds, as on Mac OS X. Mach messages and system call counts
+    // have no equivalent and are left as zero.
+    #[cfg(target_os = "linux")]
+    fn from_procfs(pid: i32, _arg: u64) -> Result<TaskInfo> {
+        let stat = procfs::stat(pid)?;
+        let status = procfs::status(pid)?;
+
+        let mut threads_user = 0;
+        let mut threads_system = 0;
+        let mut numrunning = 0;
+        for tid in procfs::tids(pid)? {
+            // Threads may exit while we are looking at them
+            if let Ok(thread) = procfs::task_stat(pid, tid) {
+                threads_user += thread.utime;
+                threads_system += thread.stime;
+                if thread.state == 'R' {
+                    numrunning += 1;
+                }
+            }
+        }
+
+        let context_switches = status.number("voluntary_ctxt_switches").unwrap_or(0)
+            + status.number("nonvoluntary_ctxt_switches").unwrap_or(0);
+
+        Ok(TaskInfo {
+            pti_virtual_size: stat.vsize,
+            pti_resident_size: stat.rss.max(0) as u64 * procfs::page_size(),
+            pti_total_user: procfs::ticks_to_nanos(stat.utime),
+            pti_total_system: procfs::ticks_to_nanos(stat.stime),
+            pti_threads_user: procfs::ticks_to_nanos(threads_user),
+            pti_threads_system: procfs::ticks_to_nanos(threads_system),
+            pti_policy: stat.policy as i32,
+            pti_faults: (stat.minflt + stat.majflt) as i32,
+            pti_pageins: stat.majflt as i32,
+            pti_csw: context_switches as i32,
+            pti_threadnum: stat.num_threads as i32,
+            pti_numrunning: numrunning,
+            pti_priority: stat.priority as i32,
+            ..Default::default()
+        })
+    }
 }
 
 #[repr(C)]
@@ -126,6 +207,105 @@ impl PIDInfo for BSDInfo {
     fn flavor() -> PidInfoFlavor {
         PidInfoFlavor::TBSDInfo
     }
+
+    #[cfg(target_os = "linux")]
+    fn from_procfs(pid: i32, _arg: u64) -> Result<BSDInfo> {
+        let stat = procfs::stat(pid)?;
+        let status = procfs::status(pid)?;
+        let (start_tvsec, start_tvusec) = procfs::start_time(&stat)?;
+
+        // The ids are listed as real, effective, saved and filesystem
+        let uids = status.numbers("Uid");
+        let gids = status.numbers("Gid");
+        let id = |ids: &Vec<u64>, index: usize| ids.get(index).copied().unwrap_or(0) as u32;
+
+        // We may not be allowed to look at the file descriptors of another user's process
+        let nfiles = fs::read_dir(procfs::pid_path(pid, "fd"))
+            .map(|entries| entries.count() as u32)
+            .unwrap_or(0);
+
+        let pbi_status = match stat.state {
+            'R' => ProcStatus::Run,
+            'T' | 't' => ProcStatus::Stop,
+            'Z' | 'X' | 'x' => ProcStatus::Zombie,
+            _ => ProcStatus::Sleep,
+        };
+
+        let mut info = BSDInfo {
+            pbi_status: pbi_status as u32,
+            pbi_pid: stat.pid as u32,
+            pbi_ppid: stat.ppid as u32,
+            pbi_uid: id(&uids, 1),
+            pbi_gid: id(&gids, 1),
+            pbi_ruid: id(&uids, 0),
+            pbi_rgid: id(&gids, 0),
+            pbi_svuid: id(&uids, 2),
+            pbi_svgid: id(&gids, 2),
+            pbi_nfiles: nfiles,
+            pbi_pgid: stat.pgrp as u32,
+            e_tdev: stat.tty_nr as u32,
+            e_tpgid: stat.tpgid as u32,
+            pbi_nice: stat.nice as i32,
+            pbi_start_tvsec: start_tvsec,
+            pbi_start_tvusec: start_tvusec,
+            ..Default::default()
+        };
+        copy_name(&stat.comm, &mut info.pbi_comm);
+        copy_name(&stat.comm, &mut info.pbi_name);
+
+        Ok(info)
+    }
+}
+
+// Copy a name into a fixed size, NUL terminated, C string buffer, truncating it if necessary
+#[cfg(target_os = "linux")]
+fn copy_name<T: AsRef<[u8]>>(name: T, buffer: &mut [c_char]) {
+    let len = buffer.len() - 1;
+    for (dst, &src) in buffer.iter_mut().zip(name.as_ref().iter().take(len)) {
+        *dst = src as c_char;
+    }
+}
+
+/// The text in a fixed size, NUL terminated, C string buffer of a structure, such as
+/// `BSDInfo::pbi_comm`, with any bytes that are not UTF-8 replaced
+pub fn c_chars_to_string(chars: &[c_char]) -> String {
+    let bytes: Vec<u8> = chars
+        .iter()
+        .take_while(|&&c| c != 0)
+        .map(|&c| c as u8)
+        .collect();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// The status of a process, as in `BSDInfo::pbi_status`
+// From http://opensource.apple.com//source/xnu/xnu-1456.1.26/bsd/sys/proc.h
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ProcStatus {
+    /// Process being created by fork
+    Idle = 1,
+    /// Currently runnable
+    Run = 2,
+    /// Sleeping on an address
+    Sleep = 3,
+    /// Process debugging or suspension
+    Stop = 4,
+    /// Awaiting collection by parent
+    Zombie = 5,
+    /// Unknown
+    Unknown,
+}
+
+impl From<u32> for ProcStatus {
+    fn from(value: u32) -> ProcStatus {
+        match value {
+            1 => ProcStatus::Idle,
+            2 => ProcStatus::Run,
+            3 => ProcStatus::Sleep,
+            4 => ProcStatus::Stop,
+            5 => ProcStatus::Zombie,
+            _ => ProcStatus::Unknown,
+        }
+    }
 }
 
 #[repr(C)]
@@ -139,6 +319,14 @@ impl PIDInfo for TaskAllInfo {
     fn flavor() -> PidInfoFlavor {
         PidInfoFlavor::TaskAllInfo
     }
+
+    #[cfg(target_os = "linux")]
+    fn from_procfs(pid: i32, arg: u64) -> Result<TaskAllInfo> {
+        Ok(TaskAllInfo {
+            pbsd: BSDInfo::from_procfs(pid, arg)?,
+            ptinfo: TaskInfo::from_procfs(pid, arg)?,
+        })
+    }
 }
 
 #[repr(C)]
@@ -166,10 +354,46 @@ pub struct ThreadInfo {
     pub pth_name: [c_char; MAXTHREADNAMESIZE], // thread name, if any
 }
 
+impl ThreadInfo {
+    /// The scheduling policy of `pth_policy`, `None` if it is not one that is known
+    pub fn policy(&self) -> Option<SchedPolicy> {
+        SchedPolicy::from_raw(self.pth_policy)
+    }
+}
+
 impl PIDInfo for ThreadInfo {
     fn flavor() -> PidInfoFlavor {
         PidInfoFlavor::ThreadInfo
     }
+
+    // `arg` is the id of the thread, as returned by `listpidinfo::<ListThreads>`
+    #[cfg(target_os = "linux")]
+    fn from_procfs(pid: i32, arg: u64) -> Result<ThreadInfo> {
+        let stat = procfs::task_stat(pid, arg as i32)?;
+
+        // Run states from http://opensource.apple.com//source/xnu/xnu-1456.1.26/osfmk/mach/thread_info.h
+        let run_state = match stat.state {
+            'R' => 1,             // TH_STATE_RUNNING
+            'T' | 't' => 2,       // TH_STATE_STOPPED
+            'D' => 4,             // TH_STATE_UNINTERRUPTIBLE
+            'Z' | 'X' | 'x' => 5, // TH_STATE_HALTED
+            _ => 3,               // TH_STATE_WAITING
+        };
+
+        let mut info = ThreadInfo {
+            pth_user_time: procfs::ticks_to_nanos(stat.utime),
+            pth_system_time: procfs::ticks_to_nanos(stat.stime),
+            pth_policy: stat.policy as i32,
+            pth_run_state: run_state,
+            pth_curpri: stat.priority as i32,
+            pth_priority: stat.priority as i32,
+            pth_maxpriority: stat.priority as i32,
+            ..Default::default()
+        };
+        copy_name(&stat.comm, &mut info.pth_name);
+
+        Ok(info)
+    }
 }
 
 impl Default for ThreadInfo {
@@ -190,6 +414,94 @@ impl Default for ThreadInfo {
     }
 }
 
+/// Scheduler statistics of a thread, from /proc/<pid>/task/<tid>/schedstat, status and stat,
+/// which have no Mac OS X equivalent. A thread that spends much of its time waiting to run is
+/// being starved of CPU.
+#[cfg(target_os = "linux")]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ThreadSchedInfo {
+    /// Time spent running on a CPU, in nanoseconds
+    pub run_time: u64,
+    /// Time spent waiting on a runqueue to run, in nanoseconds
+    pub wait_time: u64,
+    /// The number of timeslices run on a CPU
+    pub timeslices: u64,
+    /// The number of times the thread gave up the CPU, such as to wait for I/O or a lock
+    pub voluntary_switches: u64,
+    /// The number of times the thread was made to give up the CPU, such as when its timeslice
+    /// ran out
+    pub involuntary_switches: u64,
+    /// The CPU the thread last ran on
+    pub last_cpu: i32,
+}
+
+#[cfg(target_os = "linux")]
+impl ThreadSchedInfo {
+    /// Parse /proc/<pid>/task/<tid>/schedstat, which has the run time, wait time and timeslices,
+    /// into a `ThreadSchedInfo` without the switches and last CPU
+    pub fn parse_schedstat(contents: &str) -> Result<ThreadSchedInfo> {
+        let numbers: Vec<u64> = contents
+            .split_whitespace()
+            .map(|number| number.parse())
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|_| Error::new(ErrorKind::InvalidData, "Could not parse schedstat"))?;
+        match numbers[..] {
+            [run_time, wait_time, timeslices] => Ok(ThreadSchedInfo {
+                run_time,
+                wait_time,
+                timeslices,
+                ..Default::default()
+            }),
+            _ => Err(Error::new(
+                ErrorKind::InvalidData,
+                "Could not parse schedstat",
+            )),
+        }
+    }
+
+    /// The fraction of the time the thread wanted to run that it spent waiting to, from 0 to 1
+    pub fn wait_fraction(&self) -> f64 {
+        let total = self.run_time + self.wait_time;
+        if total == 0 {
+            0.0
+        } else {
+            self.wait_time as f64 / total as f64
+        }
+    }
+
+    /// The average time the thread waited on a runqueue before each timeslice
+    pub fn average_wait(&self) -> Option<Duration> {
+        self.wait_time
+            .checked_div(self.timeslices)
+            .map(Duration::from_nanos)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl PIDInfo for ThreadSchedInfo {
+    fn flavor() -> PidInfoFlavor {
+        PidInfoFlavor::LinuxSchedStat
+    }
+
+    // `arg` is the id of the thread, as returned by `listpidinfo::<ListThreads>`
+    fn from_procfs(pid: i32, arg: u64) -> Result<ThreadSchedInfo> {
+        let tid = arg as i32;
+        let schedstat = procfs::read_pid_file(pid, &format!("task/{}/schedstat", tid))?;
+        let status = procfs::Status::parse(&procfs::read_pid_file(
+            pid,
+            &format!("task/{}/status", tid),
+        )?);
+        let stat = procfs::task_stat(pid, tid)?;
+
+        Ok(ThreadSchedInfo {
+            voluntary_switches: status.number("voluntary_ctxt_switches").unwrap_or(0),
+            involuntary_switches: status.number("nonvoluntary_ctxt_switches").unwrap_or(0),
+            last_cpu: stat.processor,
+            ..ThreadSchedInfo::parse_schedstat(&schedstat)?
+        })
+    }
+}
+
 #[derive(Default)]
 pub struct WorkQueueInfo {
     pub pwq_nthreads: u32,
@@ -207,6 +519,458 @@ impl PIDInfo for WorkQueueInfo {
     }
 }
 
+/// The current and root directories of a process
+#[repr(C)]
+#[derive(Default)]
+pub struct VnodePathInfo {
+    pub pvi_cdir: VnodeInfoPath,
+    pub pvi_rdir: VnodeInfoPath,
+}
+
+impl PIDInfo for VnodePathInfo {
+    fn flavor() -> PidInfoFlavor {
+        PidInfoFlavor::VNodePathInfo
+    }
+
+    #[cfg(target_os = "linux")]
+    fn from_procfs(pid: i32, _arg: u64) -> Result<VnodePathInfo> {
+        Ok(VnodePathInfo {
+            pvi_cdir: VnodeInfoPath::from_procfs(pid, "cwd")?,
+            pvi_rdir: VnodeInfoPath::from_procfs(pid, "root")?,
+        })
+    }
+}
+
+#[repr(C)]
+#[derive(Default)]
+pub struct VnodeInfo {
+    pub vi_stat: VInfoStat,
+    pub vi_type: c_int,
+    pub vi_pad: c_int,
+    pub vi_fsid: [i32; 2],
+}
+
+#[repr(C)]
+pub struct VnodeInfoPath {
+    pub vip_vi: VnodeInfo,
+    #[cfg(target_os = "macos")]
+    pub vip_path: [c_char; MAXPATHLEN], // tail end of it
+    // Paths on Linux may be longer than MAXPATHLEN
+    #[cfg(target_os = "linux")]
+    pub vip_path: PathBuf,
+}
+
+impl Default for VnodeInfoPath {
+    fn default() -> VnodeInfoPath {
+        VnodeInfoPath {
+            vip_vi: Default::default(),
+            #[cfg(target_os = "macos")]
+            vip_path: [0; MAXPATHLEN],
+            #[cfg(target_os = "linux")]
+            vip_path: PathBuf::new(),
+        }
+    }
+}
+
+impl VnodeInfoPath {
+    /// The path of the vnode, which is empty if there is none
+    #[cfg(target_os = "macos")]
+    pub fn path(&self) -> PathBuf {
+        let bytes: Vec<u8> = self
+            .vip_path
+            .iter()
+            .take_while(|&&c| c != 0)
+            .map(|&c| c as u8)
+            .collect();
+        PathBuf::from(OsString::from_vec(bytes))
+    }
+
+    /// The path of the vnode, which is empty if there is none
+    #[cfg(target_os = "linux")]
+    pub fn path(&self) -> PathBuf {
+        self.vip_path.clone()
+    }
+
+    // Build from a symbolic link under /proc/<pid> such as "cwd", with the stat of its target
+    #[cfg(target_os = "linux")]
+    fn from_procfs(pid: i32, link: &str) -> Result<VnodeInfoPath> {
+        let link = procfs::pid_path(pid, link);
+        let path = fs::read_link(&link).map_err(|err| procfs::pid_error(pid, err))?;
+        let metadata = fs::metadata(&link).map_err(|err| procfs::pid_error(pid, err))?;
+
+        Ok(VnodeInfoPath {
+            vip_vi: VnodeInfo {
+                vi_stat: VInfoStat::from_metadata(&metadata),
+                vi_type: vnode_type(&metadata),
+                ..Default::default()
+            },
+            vip_path: path,
+        })
+    }
+}
+
+// From http://opensource.apple.com//source/xnu/xnu-1456.1.26/bsd/sys/proc_info.h
+#[repr(C)]
+#[derive(Default)]
+pub struct RegionInfo {
+    pub pri_protection: u32,
+    pub pri_max_protection: u32,
+    pub pri_inheritance: u32,
+    pub pri_flags: u32,
+    // shared, external pager, is submap
+    pub pri_offset: u64,
+    pub pri_behavior: u32,
+    pub pri_user_wired_count: u32,
+    pub pri_user_tag: u32,
+    pub pri_pages_resident: u32,
+    pub pri_pages_shared_now_private: u32,
+    pub pri_pages_swapped_out: u32,
+    pub pri_pages_dirtied: u32,
+    pub pri_ref_count: u32,
+    pub pri_shadow_depth: u32,
+    pub pri_share_mode: u32,
+    pub pri_private_pages_resident: u32,
+    pub pri_shared_pages_resident: u32,
+    pub pri_obj_id: u32,
+    pub pri_depth: u32,
+    pub pri_address: u64,
+    pub pri_size: u64,
+}
+
+impl PIDInfo for RegionInfo {
+    fn flavor() -> PidInfoFlavor {
+        PidInfoFlavor::RegionInfo
+    }
+}
+
+/// The region of memory at or after the address passed as `arg`, with the file backing it
+#[repr(C)]
+#[derive(Default)]
+pub struct RegionWithPathInfo {
+    pub prp_prinfo: RegionInfo,
+    pub prp_vip: VnodeInfoPath,
+}
+
+impl PIDInfo for RegionWithPathInfo {
+    fn flavor() -> PidInfoFlavor {
+        PidInfoFlavor::RegionPathInfo
+    }
+}
+
+/// The access permitted to a region of memory
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Protection {
+    pub read: bool,
+    pub write: bool,
+    pub execute: bool,
+}
+
+// VM_PROT_* from http://opensource.apple.com//source/xnu/xnu-1456.1.26/osfmk/mach/vm_prot.h
+impl From<u32> for Protection {
+    fn from(value: u32) -> Protection {
+        Protection {
+            read: value & 0x1 != 0,
+            write: value & 0x2 != 0,
+            execute: value & 0x4 != 0,
+        }
+    }
+}
+
+impl fmt::Display for Protection {
+    /// In the "rwx" form used by ls and pmap
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}{}{}",
+            if self.read { 'r' } else { '-' },
+            if self.write { 'w' } else { '-' },
+            if self.execute { 'x' } else { '-' }
+        )
+    }
+}
+
+/// How the pages of a region of memory are shared with other processes
+// SM_* from http://opensource.apple.com//source/xnu/xnu-1456.1.26/osfmk/mach/vm_region.h
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ShareMode {
+    /// Copied on write, shared until written to
+    CopyOnWrite = 1,
+    Private = 2,
+    /// No pages have been touched yet
+    Empty = 3,
+    Shared = 4,
+    TrueShared = 5,
+    PrivateAliased = 6,
+    SharedAliased = 7,
+    LargePage = 8,
+    Unknown,
+}
+
+impl From<u32> for ShareMode {
+    fn from(value: u32) -> ShareMode {
+        match value {
+            1 => ShareMode::CopyOnWrite,
+            2 => ShareMode::Private,
+            3 => ShareMode::Empty,
+            4 => ShareMode::Shared,
+            5 => ShareMode::TrueShared,
+            6 => ShareMode::PrivateAliased,
+            7 => ShareMode::SharedAliased,
+            8 => ShareMode::LargePage,
+            _ => ShareMode::Unknown,
+        }
+    }
+}
+
+/// A region of the address space of a process, as returned by `pidregions`
+#[derive(Clone, Debug, PartialEq)]
+pub struct MemoryRegion {
+    pub start: u64,
+    /// The address just after the region
+    pub end: u64,
+    pub protection: Protection,
+    /// The most access the region can be given with mprotect(2), which is not known on Linux
+    pub max_protection: Option<Protection>,
+    pub share_mode: ShareMode,
+    /// The offset in the backing file
+    pub offset: u64,
+    /// The device and inode of the backing file, zero for anonymous memory
+    pub dev: u64,
+    pub inode: u64,
+    /// The path of the backing file, or on Linux a name such as "[heap]" or "[stack]"
+    pub path: Option<PathBuf>,
+}
+
+impl MemoryRegion {
+    pub fn size(&self) -> u64 {
+        self.end - self.start
+    }
+
+    #[cfg(target_os = "macos")]
+    fn from_region_info(info: &RegionWithPathInfo) -> MemoryRegion {
+        let region = &info.prp_prinfo;
+        let path = info.prp_vip.path();
+        MemoryRegion {
+            start: region.pri_address,
+            end: region.pri_address + region.pri_size,
+            protection: Protection::from(region.pri_protection),
+            max_protection: Some(Protection::from(region.pri_max_protection)),
+            share_mode: ShareMode::from(region.pri_share_mode),
+            offset: region.pri_offset,
+            dev: u64::from(info.prp_vip.vip_vi.vi_stat.vst_dev),
+            inode: info.prp_vip.vip_vi.vi_stat.vst_ino,
+            path: if path.as_os_str().is_empty() {
+                None
+            } else {
+                Some(path)
+            },
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn from_mapping(mapping: procfs::Mapping) -> MemoryRegion {
+        let perms = mapping.perms.as_bytes();
+        let flag = |index: usize, flag: u8| perms.get(index) == Some(&flag);
+        MemoryRegion {
+            start: mapping.start,
+            end: mapping.end,
+            protection: Protection {
+                read: flag(0, b'r'),
+                write: flag(1, b'w'),
+                execute: flag(2, b'x'),
+            },
+            max_protection: None,
+            share_mode: if flag(3, b's') {
+                ShareMode::Shared
+            } else {
+                ShareMode::Private
+            },
+            offset: mapping.offset,
+            dev: procfs::makedev(mapping.dev_major, mapping.dev_minor),
+            inode: mapping.inode,
+            path: if mapping.path.is_empty() {
+                None
+            } else {
+                Some(PathBuf::from(mapping.path))
+            },
+        }
+    }
+}
+
+/// How much of a region of memory is in use, in bytes, as returned by `pidregionusage`, or of all
+/// the memory of a process, as returned by `pidmemoryusage`
+///
+/// The sizes that are only known on Linux are `None` on Mac OS X.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RegionUsage {
+    /// Resident in memory (RSS)
+    pub resident: u64,
+    /// The resident size with each page divided among the processes sharing it (PSS)
+    pub proportional: Option<u64>,
+    /// Resident and used by this process alone, which summed over all the regions is the unique
+    /// set size (USS) of the process: what exiting would free
+    pub private: u64,
+    /// Resident and shared with other processes
+    pub shared: u64,
+    pub private_clean: Option<u64>,
+    pub private_dirty: Option<u64>,
+    pub shared_clean: Option<u64>,
+    pub shared_dirty: Option<u64>,
+    /// Modified since it was read from the backing file
+    pub dirty: u64,
+    /// Not backed by a file
+    pub anonymous: Option<u64>,
+    /// Swapped out
+    pub swapped: u64,
+    /// The swapped size with each page divided among the processes sharing it
+    pub swapped_proportional: Option<u64>,
+}
+
+impl RegionUsage {
+    /// Add the usage of another region, as when totalling the usage of a process
+    pub fn add(&mut self, other: &RegionUsage) {
+        fn add_optional(total: &mut Option<u64>, value: Option<u64>) {
+            *total = match (*total, value) {
+                (Some(total), Some(value)) => Some(total + value),
+                (total, value) => total.or(value),
+            };
+        }
+
+        self.resident += other.resident;
+        add_optional(&mut self.proportional, other.proportional);
+        self.private += other.private;
+        self.shared += other.shared;
+        add_optional(&mut self.private_clean, other.private_clean);
+        add_optional(&mut self.private_dirty, other.private_dirty);
+        add_optional(&mut self.shared_clean, other.shared_clean);
+        add_optional(&mut self.shared_dirty, other.shared_dirty);
+        self.dirty += other.dirty;
+        add_optional(&mut self.anonymous, other.anonymous);
+        self.swapped += other.swapped;
+        add_optional(&mut self.swapped_proportional, other.swapped_proportional);
+    }
+
+    #[cfg(target_os = "macos")]
+    fn from_region_info(info: &RegionInfo, page_size: u64) -> RegionUsage {
+        RegionUsage {
+            resident: info.pri_pages_resident as u64 * page_size,
+            private: info.pri_private_pages_resident as u64 * page_size,
+            shared: info.pri_shared_pages_resident as u64 * page_size,
+            dirty: info.pri_pages_dirtied as u64 * page_size,
+            swapped: info.pri_pages_swapped_out as u64 * page_size,
+            ..Default::default()
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn from_smaps(entry: &procfs::SmapsEntry) -> RegionUsage {
+        let private_clean = entry.bytes("Private_Clean");
+        let private_dirty = entry.bytes("Private_Dirty");
+        let shared_clean = entry.bytes("Shared_Clean");
+        let shared_dirty = entry.bytes("Shared_Dirty");
+        RegionUsage {
+            resident: entry.bytes("Rss"),
+            proportional: Some(entry.bytes("Pss")),
+            private: private_clean + private_dirty,
+            shared: shared_clean + shared_dirty,
+            private_clean: Some(private_clean),
+            private_dirty: Some(private_dirty),
+            shared_clean: Some(shared_clean),
+            shared_dirty: Some(shared_dirty),
+            dirty: private_dirty + shared_dirty,
+            anonymous: Some(entry.bytes("Anonymous")),
+            swapped: entry.bytes("Swap"),
+            swapped_proportional: Some(entry.bytes("SwapPss")),
+        }
+    }
+}
+
+// The regions of a process as libproc returns them, in address order. Each region is found by
+// asking for the one at or after the end of the previous one, until libproc reports there are no
+// more with EINVAL. Any other error ends the iteration after it.
+#[cfg(target_os = "macos")]
+struct RegionInfos {
+    pid: i32,
+    next: Option<Result<RegionWithPathInfo>>,
+}
+
+#[cfg(target_os = "macos")]
+impl RegionInfos {
+    fn new(pid: i32) -> Result<RegionInfos> {
+        Ok(RegionInfos {
+            pid,
+            next: Some(Ok(pidinfo::<RegionWithPathInfo>(pid, 0)?)),
+        })
+    }
+}
+
+#[cfg(target_os = "macos")]
+impl Iterator for RegionInfos {
+    type Item = Result<RegionWithPathInfo>;
+
+    fn next(&mut self) -> Option<Result<RegionWithPathInfo>> {
+        let info = self.next.take()?;
+        if let Ok(ref info) = info {
+            let end = info.prp_prinfo.pri_address + info.prp_prinfo.pri_size;
+            self.next = match pidinfo::<RegionWithPathInfo>(self.pid, end) {
+                Ok(next) => Some(Ok(next)),
+                Err(ref err) if err.raw_os_error() == Some(libc::EINVAL) => None,
+                Err(err) => Some(Err(err)),
+            };
+        }
+        Some(info)
+    }
+}
+
+/// An iterator over the memory regions of a process, in address order, see `pidregions`
+pub struct Regions {
+    #[cfg(target_os = "macos")]
+    infos: RegionInfos,
+    #[cfg(target_os = "linux")]
+    regions: std::vec::IntoIter<MemoryRegion>,
+}
+
+impl Iterator for Regions {
+    type Item = Result<MemoryRegion>;
+
+    #[cfg(target_os = "macos")]
+    fn next(&mut self) -> Option<Result<MemoryRegion>> {
+        let info = self.infos.next()?;
+        Some(info.map(|info| MemoryRegion::from_region_info(&info)))
+    }
+
+    #[cfg(target_os = "linux")]
+    fn next(&mut self) -> Option<Result<MemoryRegion>> {
+        self.regions.next().map(Ok)
+    }
+}
+
+// The vnode type for the type of a file, from enum vtype in
+// http://opensource.apple.com//source/xnu/xnu-1456.1.26/bsd/sys/vnode.h
+#[cfg(target_os = "linux")]
+fn vnode_type(metadata: &fs::Metadata) -> c_int {
+    use std::os::unix::fs::FileTypeExt;
+
+    let file_type = metadata.file_type();
+    if file_type.is_file() {
+        1 // VREG
+    } else if file_type.is_dir() {
+        2 // VDIR
+    } else if file_type.is_block_device() {
+        3 // VBLK
+    } else if file_type.is_char_device() {
+        4 // VCHR
+    } else if file_type.is_symlink() {
+        5 // VLNK
+    } else if file_type.is_socket() {
+        6 // VSOCK
+    } else if file_type.is_fifo() {
+        7 // VFIFO
+    } else {
+        0 // VNON
+    }
+}
+
 // From http://opensource.apple.com/source/xnu/xnu-1504.7.4/bsd/kern/proc_info.c
 pub enum PidInfoFlavor {
     ListFDs = 1,
@@ -231,6 +995,9 @@ pub enum PidInfoFlavor {
     PathInfo = 11,
     // String
     WorkQueueInfo = 12, // struct proc_workqueueinfo
+    /// The scheduler statistics of a thread from /proc, which have no Mac OS X equivalent
+    #[cfg(target_os = "linux")]
+    LinuxSchedStat = 100,
 }
 
 pub enum PidInfo {
@@ -260,10 +1027,14 @@ pub enum PidFDInfoFlavor {
     PipeInfo = 6,
     KQueueInfo = 7,
     ATalkInfo = 8,
+    /// The details in /proc/<pid>/fdinfo/<fd>, which has no Mac OS X equivalent
+    #[cfg(target_os = "linux")]
+    LinuxFDInfo = 100,
 }
 
 // this extern block links to the libproc library
 // Original signatures of functions can be found at http://opensource.apple.com/source/Libc/Libc-594.9.4/darwin/libproc.c
+#[cfg(target_os = "macos")]
 #[link(name = "proc", kind = "dylib")]
 extern "C" {
     fn proc_listpids(proc_type: u32, typeinfo: u32, buffer: *mut c_void, buffersize: u32) -> c_int;
@@ -292,6 +1063,8 @@ extern "C" {
     fn proc_pidpath(pid: c_int, buffer: *mut c_void, buffersize: u32) -> c_int;
 
     fn proc_libversion(major: *mut c_int, minor: *mut c_int) -> c_int;
+
+    fn proc_pid_rusage(pid: c_int, flavor: c_int, buffer: *mut c_void) -> c_int;
 }
 
 /// Returns the PIDs of the processes active that match the ProcType passed in
@@ -310,6 +1083,7 @@ extern "C" {
 ///     Err(err) => assert!(false, "Error listing pids")
 /// }
 /// ```
+#[cfg(target_os = "macos")]
 pub fn listpids(proc_types: ProcType, info: u32) -> Result<Vec<u32>> {
     let buffer_size = unsafe { proc_listpids(proc_types as u32, info, ptr::null_mut(), 0) };
     if buffer_size <= 0 {
@@ -336,6 +1110,31 @@ pub fn listpids(proc_types: ProcType, info: u32) -> Result<Vec<u32>> {
     }
 }
 
+/// Returns the PIDs of the processes active that match the ProcType passed in, read from /proc
+#[cfg(target_os = "linux")]
+pub fn listpids(proc_types: ProcType, info: u32) -> Result<Vec<u32>> {
+    let mut pids = vec![];
+    for pid in procfs::pids()? {
+        // Processes may exit while we are looking at them, those are left out
+        let matched = match proc_types {
+            ProcType::ProcAllPIDS => true,
+            ProcType::ProcPGRPOnly => procfs::stat(pid).is_ok_and(|stat| stat.pgrp as u32 == info),
+            ProcType::ProcTTYOnly => procfs::stat(pid).is_ok_and(|stat| stat.tty_nr as u32 == info),
+            ProcType::ProcPPIDOnly => procfs::stat(pid).is_ok_and(|stat| stat.ppid as u32 == info),
+            ProcType::ProcUIDOnly => procfs::status(pid)
+                .is_ok_and(|status| status.numbers("Uid").get(1) == Some(&(info as u64))),
+            ProcType::ProcRUIDOnly => procfs::status(pid)
+                .is_ok_and(|status| status.numbers("Uid").first() == Some(&(info as u64))),
+        };
+
+        if matched {
+            pids.push(pid as u32);
+        }
+    }
+
+    Ok(pids)
+}
+
 /// Returns the PIDs of the process that match pid passed in.
 ///
 /// arg - is "geavily not documented" and need to look at code for each flavour here
@@ -359,6 +1158,7 @@ pub fn listpids(proc_types: ProcType, info: u32) -> Result<Vec<u32>> {
 /// }
 /// ```
 ///
+#[cfg(target_os = "macos")]
 pub fn pidinfo<T: PIDInfo>(pid: i32, arg: u64) -> Result<T> {
     let flavor = T::flavor() as i32;
     let buffer_size = mem::size_of::<T>() as i32;
@@ -377,6 +1177,14 @@ pub fn pidinfo<T: PIDInfo>(pid: i32, arg: u64) -> Result<T> {
     }
 }
 
+/// Returns the information of the process that match pid passed in, built from /proc for
+/// the types that support it
+#[cfg(target_os = "linux")]
+pub fn pidinfo<T: PIDInfo>(pid: i32, arg: u64) -> Result<T> {
+    T::from_procfs(pid, arg)
+}
+
+#[cfg(target_os = "macos")]
 pub fn regionfilename(pid: i32, address: u64) -> Result<String> {
     let mut regionfilenamebuf: Vec<u8> = Vec::with_capacity(PROC_PIDPATHINFO_MAXSIZE - 1);
     let buffer_ptr = regionfilenamebuf.as_mut_ptr() as *mut c_void;
@@ -404,6 +1212,335 @@ pub fn regionfilename(pid: i32, address: u64) -> Result<String> {
     }
 }
 
+/// Returns the path of the file mapped at `address` in the process, from /proc/<pid>/maps
+#[cfg(target_os = "linux")]
+pub fn regionfilename(pid: i32, address: u64) -> Result<String> {
+    pidregions(pid)?
+        .filter_map(Result::ok)
+        .find(|region| region.start <= address && address < region.end)
+        .and_then(|region| region.path)
+        .map(|path| path.to_string_lossy().into_owned())
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotFound,
+                format!("No file is mapped at address 0x{:x}", address),
+            )
+        })
+}
+
+/// Returns an iterator over the regions of memory mapped by the process with the specified pid,
+/// in address order
+///
+/// On Mac OS X the regions are looked up as the iterator advances, so a process that exits
+/// part way through ends the iteration with an error.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use libproc::libproc::proc_pid;
+///
+/// match proc_pid::pidregions(std::process::id() as i32) {
+///     Ok(regions) => for region in regions.filter_map(Result::ok) {
+///         println!("{:x}-{:x} {} {:?}", region.start, region.end, region.protection, region.path);
+///     },
+///     Err(err) => writeln!(&mut std::io::stderr(), "Error: {}", err).unwrap()
+/// }
+/// ```
+#[cfg(target_os = "macos")]
+pub fn pidregions(pid: i32) -> Result<Regions> {
+    Ok(Regions {
+        infos: RegionInfos::new(pid)?,
+    })
+}
+
+/// Returns the regions of memory mapped by the process with the specified pid in address order,
+/// each with how much of it is in use
+#[cfg(target_os = "macos")]
+pub fn pidregionusage(pid: i32) -> Result<Vec<(MemoryRegion, RegionUsage)>> {
+    let page_size = match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+        size if size > 0 => size as u64,
+        _ => 4096,
+    };
+
+    RegionInfos::new(pid)?
+        .map(|info| {
+            info.map(|info| {
+                (
+                    MemoryRegion::from_region_info(&info),
+                    RegionUsage::from_region_info(&info.prp_prinfo, page_size),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Returns the regions of memory mapped by the process with the specified pid in address order,
+/// each with how much of it is in use, from /proc/<pid>/smaps
+#[cfg(target_os = "linux")]
+pub fn pidregionusage(pid: i32) -> Result<Vec<(MemoryRegion, RegionUsage)>> {
+    Ok(procfs::smaps(pid)?
+        .into_iter()
+        .map(|entry| {
+            let usage = RegionUsage::from_smaps(&entry);
+            (MemoryRegion::from_mapping(entry.mapping), usage)
+        })
+        .collect())
+}
+
+/// Returns how much memory the process with the specified pid uses, the total of the usage of all
+/// its regions
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use libproc::libproc::proc_pid;
+///
+/// match proc_pid::pidmemoryusage(std::process::id() as i32) {
+///     Ok(usage) => println!("RSS {} USS {} PSS {:?}", usage.resident, usage.private, usage.proportional),
+///     Err(err) => writeln!(&mut std::io::stderr(), "Error: {}", err).unwrap()
+/// }
+/// ```
+#[cfg(target_os = "macos")]
+pub fn pidmemoryusage(pid: i32) -> Result<RegionUsage> {
+    let mut total = RegionUsage::default();
+    for (_, usage) in pidregionusage(pid)? {
+        total.add(&usage);
+    }
+    Ok(total)
+}
+
+/// Returns how much memory the process with the specified pid uses, from
+/// /proc/<pid>/smaps_rollup, or by totalling /proc/<pid>/smaps on kernels before 4.14 that do not
+/// have it
+#[cfg(target_os = "linux")]
+pub fn pidmemoryusage(pid: i32) -> Result<RegionUsage> {
+    match procfs::read_pid_file(pid, "smaps_rollup") {
+        Ok(contents) => match procfs::parse_smaps(&contents)?.first() {
+            Some(rollup) => Ok(RegionUsage::from_smaps(rollup)),
+            // Processes without an address space, such as kernel threads, have an empty rollup
+            None => Ok(RegionUsage::default()),
+        },
+        Err(ref err) if err.kind() == ErrorKind::NotFound => {
+            let mut total = RegionUsage::default();
+            for entry in procfs::smaps(pid)? {
+                total.add(&RegionUsage::from_smaps(&entry));
+            }
+            Ok(total)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+// The sizes of struct rusage_info_v2, v3 and v4 and the offsets of their disk I/O counters, from
+// http://opensource.apple.com//source/xnu/xnu-4570.1.46/bsd/sys/resource.h
+const RUSAGE_INFO_V2_SIZE: usize = 160;
+#[cfg(target_os = "macos")]
+const RUSAGE_INFO_V3_SIZE: usize = 232;
+const RUSAGE_INFO_V4_SIZE: usize = 296;
+const RI_DISKIO_BYTESREAD: usize = 144;
+const RI_DISKIO_BYTESWRITTEN: usize = 152;
+const RI_LOGICAL_WRITES: usize = 232;
+
+/// How much a process has read and written, as counted since it started
+///
+/// The counters that only Linux (from /proc/<pid>/io) or only Mac OS X (from rusage_info) keeps
+/// are `None` on the other.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DiskIO {
+    /// Bytes fetched from storage
+    pub read_bytes: u64,
+    /// Bytes sent, or to be sent, to storage
+    pub write_bytes: u64,
+    /// Bytes passed to read(2) and similar calls, whether or not they came from storage (Linux)
+    pub read_chars: Option<u64>,
+    /// Bytes passed to write(2) and similar calls (Linux)
+    pub write_chars: Option<u64>,
+    /// The number of read(2) and similar calls (Linux)
+    pub read_syscalls: Option<u64>,
+    /// The number of write(2) and similar calls (Linux)
+    pub write_syscalls: Option<u64>,
+    /// Bytes that were to be written but were not, as the file was truncated before they were
+    /// (Linux)
+    pub cancelled_write_bytes: Option<u64>,
+    /// Bytes written, including those to compressed and cached files that are not yet in storage
+    /// (Mac OS X, with rusage_info_v4)
+    pub logical_write_bytes: Option<u64>,
+}
+
+/// Disk I/O per second over an interval, see `DiskIO::rate`. Each is the rate of the `DiskIO`
+/// counter of the same name, and `None` where that counter is.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DiskIORate {
+    /// Bytes fetched from storage per second
+    pub read_bytes: f64,
+    /// Bytes sent, or to be sent, to storage per second
+    pub write_bytes: f64,
+    /// Bytes passed to read(2) and similar calls per second (Linux)
+    pub read_chars: Option<f64>,
+    /// Bytes passed to write(2) and similar calls per second (Linux)
+    pub write_chars: Option<f64>,
+    /// read(2) and similar calls per second (Linux)
+    pub read_syscalls: Option<f64>,
+    /// write(2) and similar calls per second (Linux)
+    pub write_syscalls: Option<f64>,
+    /// Bytes per second that were to be written but were not, as the file was truncated (Linux)
+    pub cancelled_write_bytes: Option<f64>,
+    /// Bytes written per second, including those not yet in storage (Mac OS X)
+    pub logical_write_bytes: Option<f64>,
+}
+
+impl DiskIO {
+    /// Parse the contents of /proc/<pid>/io
+    #[cfg(target_os = "linux")]
+    pub fn parse_procfs(contents: &str) -> Result<DiskIO> {
+        let io = procfs::Status::parse(contents);
+        let counter = |key: &str| {
+            io.number(key).ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("No {} in /proc/<pid>/io", key),
+                )
+            })
+        };
+
+        Ok(DiskIO {
+            read_bytes: counter("read_bytes")?,
+            write_bytes: counter("write_bytes")?,
+            read_chars: Some(counter("rchar")?),
+            write_chars: Some(counter("wchar")?),
+            read_syscalls: Some(counter("syscr")?),
+            write_syscalls: Some(counter("syscw")?),
+            cancelled_write_bytes: Some(counter("cancelled_write_bytes")?),
+            logical_write_bytes: None,
+        })
+    }
+
+    /// Decode a `struct rusage_info_v2` or later, as filled in by proc_pid_rusage() on Mac OS X.
+    /// The version is told from the length of the buffer.
+    pub fn from_rusage_info(buffer: &[u8]) -> Result<DiskIO> {
+        if buffer.len() < RUSAGE_INFO_V2_SIZE {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "rusage_info before v2 has no disk I/O counters",
+            ));
+        }
+
+        Ok(DiskIO {
+            read_bytes: u64_at(buffer, RI_DISKIO_BYTESREAD),
+            write_bytes: u64_at(buffer, RI_DISKIO_BYTESWRITTEN),
+            logical_write_bytes: if buffer.len() >= RUSAGE_INFO_V4_SIZE {
+                Some(u64_at(buffer, RI_LOGICAL_WRITES))
+            } else {
+                None
+            },
+            ..Default::default()
+        })
+    }
+
+    /// The I/O done since an earlier reading of the same process
+    pub fn since(&self, earlier: &DiskIO) -> DiskIO {
+        let since = |now: Option<u64>, then: Option<u64>| match (now, then) {
+            (Some(now), Some(then)) => Some(now.saturating_sub(then)),
+            _ => None,
+        };
+
+        DiskIO {
+            read_bytes: self.read_bytes.saturating_sub(earlier.read_bytes),
+            write_bytes: self.write_bytes.saturating_sub(earlier.write_bytes),
+            read_chars: since(self.read_chars, earlier.read_chars),
+            write_chars: since(self.write_chars, earlier.write_chars),
+            read_syscalls: since(self.read_syscalls, earlier.read_syscalls),
+            write_syscalls: since(self.write_syscalls, earlier.write_syscalls),
+            cancelled_write_bytes: since(self.cancelled_write_bytes, earlier.cancelled_write_bytes),
+            logical_write_bytes: since(self.logical_write_bytes, earlier.logical_write_bytes),
+        }
+    }
+
+    /// The I/O per second since an earlier reading of the same process, taken `interval` before
+    /// this one. The rates are zero for an empty interval.
+    pub fn rate(&self, earlier: &DiskIO, interval: Duration) -> DiskIORate {
+        let seconds = interval.as_secs_f64();
+        let per_second = |count: u64| {
+            if seconds > 0.0 {
+                count as f64 / seconds
+            } else {
+                0.0
+            }
+        };
+
+        let since = self.since(earlier);
+        DiskIORate {
+            read_bytes: per_second(since.read_bytes),
+            write_bytes: per_second(since.write_bytes),
+            read_chars: since.read_chars.map(per_second),
+            write_chars: since.write_chars.map(per_second),
+            read_syscalls: since.read_syscalls.map(per_second),
+            write_syscalls: since.write_syscalls.map(per_second),
+            cancelled_write_bytes: since.cancelled_write_bytes.map(per_second),
+            logical_write_bytes: since.logical_write_bytes.map(per_second),
+        }
+    }
+}
+
+/// Returns how much the process with the specified pid has read and written, using the newest
+/// rusage_info the system has
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use libproc::libproc::proc_pid;
+///
+/// match proc_pid::piddiskio(std::process::id() as i32) {
+///     Ok(io) => println!("read {} written {}", io.read_bytes, io.write_bytes),
+///     Err(err) => writeln!(&mut std::io::stderr(), "Error: {}", err).unwrap()
+/// }
+/// ```
+#[cfg(target_os = "macos")]
+pub fn piddiskio(pid: i32) -> Result<DiskIO> {
+    let mut buffer = [0u8; RUSAGE_INFO_V4_SIZE];
+    let mut err = Error::from_raw_os_error(libc::EINVAL);
+    for &(flavor, size) in &[
+        (4, RUSAGE_INFO_V4_SIZE),
+        (3, RUSAGE_INFO_V3_SIZE),
+        (2, RUSAGE_INFO_V2_SIZE),
+    ] {
+        let ret = unsafe { proc_pid_rusage(pid, flavor, buffer.as_mut_ptr() as *mut c_void) };
+        if ret == 0 {
+            return DiskIO::from_rusage_info(&buffer[..size]);
+        }
+        // Older systems do not know the newer flavors
+        err = Error::last_os_error();
+        if err.raw_os_error() != Some(libc::EINVAL) {
+            break;
+        }
+    }
+    Err(err)
+}
+
+/// Returns how much the process with the specified pid has read and written, from /proc/<pid>/io.
+/// Only the owner of a process (or root) may read it.
+#[cfg(target_os = "linux")]
+pub fn piddiskio(pid: i32) -> Result<DiskIO> {
+    DiskIO::parse_procfs(&procfs::read_pid_file(pid, "io")?)
+}
+
+/// Returns an iterator over the regions of memory mapped by the process with the specified pid,
+/// in address order, from /proc/<pid>/maps
+#[cfg(target_os = "linux")]
+pub fn pidregions(pid: i32) -> Result<Regions> {
+    let regions: Vec<MemoryRegion> = procfs::maps(pid)?
+        .into_iter()
+        .map(MemoryRegion::from_mapping)
+        .collect();
+    Ok(Regions {
+        regions: regions.into_iter(),
+    })
+}
+
+#[cfg(target_os = "macos")]
 pub fn pidpath(pid: i32) -> Result<String> {
     let mut pathbuf: Vec<u8> = Vec::with_capacity(PROC_PIDPATHINFO_MAXSIZE - 1);
     let buffer_ptr = pathbuf.as_mut_ptr() as *mut c_void;
@@ -431,6 +1568,55 @@ pub fn pidpath(pid: i32) -> Result<String> {
     }
 }
 
+/// Returns the path of the executable of the process, the target of /proc/<pid>/exe
+#[cfg(target_os = "linux")]
+pub fn pidpath(pid: i32) -> Result<String> {
+    match fs::read_link(procfs::pid_path(pid, "exe")) {
+        Ok(path) => match path.into_os_string().into_string() {
+            Ok(path) => Ok(path),
+            Err(path) => Err(Error::other(format!("Invalid UTF-8 sequence: {:?}", path))),
+        },
+        Err(err) => Err(procfs::pid_error(pid, err)),
+    }
+}
+
+/// Returns the current working directory of the process with the specified pid
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use libproc::libproc::proc_pid;
+///
+/// match proc_pid::pidcwd(std::process::id() as i32) {
+///     Ok(cwd) => println!("Working directory: {}", cwd.display()),
+///     Err(err) => writeln!(&mut std::io::stderr(), "Error: {}", err).unwrap()
+/// }
+/// ```
+#[cfg(target_os = "macos")]
+pub fn pidcwd(pid: i32) -> Result<PathBuf> {
+    pidinfo::<VnodePathInfo>(pid, 0).map(|info| info.pvi_cdir.path())
+}
+
+/// Returns the current working directory of the process with the specified pid
+// Only the cwd link is read, as the root link may not be readable when the cwd one is
+#[cfg(target_os = "linux")]
+pub fn pidcwd(pid: i32) -> Result<PathBuf> {
+    fs::read_link(procfs::pid_path(pid, "cwd")).map_err(|err| procfs::pid_error(pid, err))
+}
+
+/// Returns the root directory of the process with the specified pid, which is only something
+/// other than "/" for processes that have been chroot(2)ed
+pub fn pidroot(pid: i32) -> Result<PathBuf> {
+    let root = pidinfo::<VnodePathInfo>(pid, 0)?.pvi_rdir.path();
+    // Mac OS X has no root directory vnode for processes that have not been chrooted
+    if root.as_os_str().is_empty() {
+        Ok(PathBuf::from("/"))
+    } else {
+        Ok(root)
+    }
+}
+
 /// Returns the major and minor version numbers of the native librproc library being used
 ///
 /// # Examples
@@ -444,6 +1630,7 @@ pub fn pidpath(pid: i32) -> Result<String> {
 ///     Err(err) => writeln!(&mut std::io::stderr(), "Error: {}", err).unwrap()
 /// }
 /// ```
+#[cfg(target_os = "macos")]
 pub fn libversion() -> Result<(i32, i32)> {
     let mut major = 0;
     let mut minor = 0;
@@ -453,15 +1640,267 @@ pub fn libversion() -> Result<(i32, i32)> {
         ret = proc_libversion(&mut major, &mut minor);
     };
 
-    // return value of 0 indicates success (inconsistent with other functions... :-( )
-    if ret == 0 {
-        Ok((major, minor))
-    } else {
-        Err(Error::last_os_error())
-    }
+    // return value of 0 indicates success (inconsistent with other functions... :-( )
+    if ret == 0 {
+        Ok((major, minor))
+    } else {
+        Err(Error::last_os_error())
+    }
+}
+
+/// There is no libproc library on Linux, so there is no version to return
+#[cfg(target_os = "linux")]
+pub fn libversion() -> Result<(i32, i32)> {
+    Err(procfs::unsupported("libversion"))
+}
+
+/// Returns the name of the process with the specified pid
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use libproc::libproc::proc_pid;
+///
+/// match proc_pid::name(1) {
+///     Ok(name) => println!("Name: {}", name),
+///     Err(err) => writeln!(&mut std::io::stderr(), "Error: {}", err).unwrap()
+/// }
+/// ```
+#[cfg(target_os = "macos")]
+pub fn name(pid: i32) -> Result<String> {
+    let mut namebuf: Vec<u8> = Vec::with_capacity(PROC_PIDPATHINFO_MAXSIZE - 1);
+    let buffer_ptr = namebuf.as_ptr() as *mut c_void;
+    let buffer_size = namebuf.capacity() as u32;
+    let ret: i32;
+
+    unsafe {
+        ret = proc_name(pid, buffer_ptr, buffer_size);
+    };
+
+    if ret <= 0 {
+        Err(Error::last_os_error())
+    } else {
+        unsafe {
+            namebuf.set_len(ret as usize);
+        }
+
+        match String::from_utf8(namebuf) {
+            Ok(name) => Ok(name),
+            Err(e) => Err(Error::new(
+                ErrorKind::Other,
+                format!("Invalid UTF-8 sequence: {}", e),
+            )),
+        }
+    }
+}
+
+/// Returns the name of the process with the specified pid, from /proc/<pid>/comm
+#[cfg(target_os = "linux")]
+pub fn name(pid: i32) -> Result<String> {
+    Ok(procfs::read_pid_file(pid, "comm")?
+        .trim_end_matches('\n')
+        .to_string())
+}
+
+/// The arguments a process was started with, as returned by `pidargs`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProcArgs {
+    /// The path of the executable, which is `None` where it cannot be read (on Linux the
+    /// executable of another user's process cannot be read by anyone but root)
+    pub exec_path: Option<PathBuf>,
+    /// The arguments, starting with the name the program was invoked as (`argv[0]`)
+    pub args: Vec<OsString>,
+}
+
+/// The environment variables of a process, as returned by `pidenv`, in the order they appear
+pub type ProcEnv = Vec<(OsString, OsString)>;
+
+// Split NUL terminated strings, leaving out the empty string after the last NUL
+fn split_nul_terminated(buffer: &[u8]) -> Vec<&[u8]> {
+    let buffer = buffer.strip_suffix(&[0]).unwrap_or(buffer);
+    if buffer.is_empty() {
+        vec![]
+    } else {
+        buffer.split(|&b| b == 0).collect()
+    }
+}
+
+// Environment strings without an '=' are not variables and are left out
+fn parse_env_var(var: &[u8]) -> Option<(OsString, OsString)> {
+    let equals = var.iter().position(|&b| b == b'=')?;
+    Some((
+        OsString::from_vec(var[..equals].to_vec()),
+        OsString::from_vec(var[equals + 1..].to_vec()),
+    ))
+}
+
+/// Parse the buffer returned by the `KERN_PROCARGS2` sysctl on Mac OS X into the arguments and
+/// environment of the process.
+///
+/// The buffer holds argc, the NUL terminated path of the executable followed by NULs to align
+/// what comes next, then argc NUL terminated arguments, then the NUL terminated environment
+/// strings ending with an empty string. Anything after that (such as the "apple" strings passed
+/// to the program) is ignored.
+/// See http://opensource.apple.com//source/adv_cmds/adv_cmds-163/ps/print.c
+pub fn parse_procargs2(buffer: &[u8]) -> Result<(ProcArgs, ProcEnv)> {
+    let argc_size = mem::size_of::<c_int>();
+    if buffer.len() < argc_size {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "Truncated process arguments",
+        ));
+    }
+    let mut argc_bytes = [0; 4];
+    argc_bytes.copy_from_slice(&buffer[..argc_size]);
+    let argc = c_int::from_ne_bytes(argc_bytes).max(0) as usize;
+
+    // The executable path, then skip the NULs padding it
+    let rest = &buffer[argc_size..];
+    let path_end = rest.iter().position(|&b| b == 0).unwrap_or(rest.len());
+    let exec_path = match &rest[..path_end] {
+        [] => None,
+        path => Some(PathBuf::from(OsString::from_vec(path.to_vec()))),
+    };
+    let strings_start = rest[path_end..]
+        .iter()
+        .position(|&b| b != 0)
+        .map_or(rest.len(), |offset| path_end + offset);
+
+    let mut strings = rest[strings_start..].split(|&b| b == 0);
+    let args: Vec<OsString> = strings
+        .by_ref()
+        .take(argc)
+        .map(|arg| OsString::from_vec(arg.to_vec()))
+        .collect();
+    if args.len() < argc {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "Truncated process arguments",
+        ));
+    }
+
+    let env = strings
+        .take_while(|var| !var.is_empty())
+        .filter_map(parse_env_var)
+        .collect();
+
+    Ok((ProcArgs { exec_path, args }, env))
+}
+
+// The arguments and environment of a process are read with the KERN_PROCARGS2 sysctl
+#[cfg(target_os = "macos")]
+fn procargs2(pid: i32) -> Result<Vec<u8>> {
+    let mut argmax: c_int = 0;
+    let mut size = mem::size_of::<c_int>() as size_t;
+    let mut mib = [libc::CTL_KERN, libc::KERN_ARGMAX];
+    let ret = unsafe {
+        libc::sysctl(
+            mib.as_mut_ptr(),
+            2,
+            &mut argmax as *mut _ as *mut c_void,
+            &mut size,
+            ptr::null_mut(),
+            0,
+        )
+    };
+    if ret != 0 {
+        return Err(Error::last_os_error());
+    }
+
+    let mut procargs: Vec<u8> = vec![0; argmax as usize];
+    let mut size = procargs.len() as size_t;
+    let mut mib = [libc::CTL_KERN, libc::KERN_PROCARGS2, pid];
+    let ret = unsafe {
+        libc::sysctl(
+            mib.as_mut_ptr(),
+            3,
+            procargs.as_mut_ptr() as *mut c_void,
+            &mut size,
+            ptr::null_mut(),
+            0,
+        )
+    };
+    if ret != 0 {
+        return Err(Error::last_os_error());
+    }
+    procargs.truncate(size as usize);
+    Ok(procargs)
+}
+
+/// Returns the path of the executable and the arguments of the process with the specified pid.
+/// Arguments that are not valid UTF-8 are returned as they are.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use libproc::libproc::proc_pid;
+///
+/// match proc_pid::pidargs(std::process::id() as i32) {
+///     Ok(args) => println!("{:?} started with {:?}", args.exec_path, args.args),
+///     Err(err) => writeln!(&mut std::io::stderr(), "Error: {}", err).unwrap()
+/// }
+/// ```
+#[cfg(target_os = "macos")]
+pub fn pidargs(pid: i32) -> Result<ProcArgs> {
+    parse_procargs2(&procargs2(pid)?).map(|(args, _)| args)
+}
+
+/// Returns the path of the executable and the arguments of the process with the specified pid,
+/// from /proc/<pid>/exe and the NUL separated arguments in /proc/<pid>/cmdline
+#[cfg(target_os = "linux")]
+pub fn pidargs(pid: i32) -> Result<ProcArgs> {
+    let contents =
+        fs::read(procfs::pid_path(pid, "cmdline")).map_err(|err| procfs::pid_error(pid, err))?;
+    let args = split_nul_terminated(&contents)
+        .into_iter()
+        .map(|arg| OsString::from_vec(arg.to_vec()))
+        .collect();
+
+    Ok(ProcArgs {
+        exec_path: fs::read_link(procfs::pid_path(pid, "exe")).ok(),
+        args,
+    })
+}
+
+/// Returns the environment variables of the process with the specified pid, as (name, value)
+/// pairs. Names and values that are not valid UTF-8 are returned as they are.
+///
+/// Only processes of the same user can be inspected, unless running as root. This is the
+/// environment the process was started with, changes it has made since are not seen.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use libproc::libproc::proc_pid;
+///
+/// match proc_pid::pidenv(std::process::id() as i32) {
+///     Ok(env) => for (name, value) in env {
+///         println!("{:?}={:?}", name, value);
+///     },
+///     Err(err) => writeln!(&mut std::io::stderr(), "Error: {}", err).unwrap()
+/// }
+/// ```
+#[cfg(target_os = "macos")]
+pub fn pidenv(pid: i32) -> Result<ProcEnv> {
+    parse_procargs2(&procargs2(pid)?).map(|(_, env)| env)
+}
+
+/// Returns the environment variables of the process with the specified pid, from the NUL
+/// separated strings in /proc/<pid>/environ
+#[cfg(target_os = "linux")]
+pub fn pidenv(pid: i32) -> Result<ProcEnv> {
+    let contents =
+        fs::read(procfs::pid_path(pid, "environ")).map_err(|err| procfs::pid_error(pid, err))?;
+    Ok(split_nul_terminated(&contents)
+        .into_iter()
+        .filter_map(parse_env_var)
+        .collect())
 }
 
-/// Returns the name of the process with the specified pid
+/// Returns the command line of the process with the specified pid, arguments separated by spaces
 ///
 /// # Examples
 ///
@@ -469,36 +1908,19 @@ pub fn libversion() -> Result<(i32, i32)> {
 /// use std::io::Write;
 /// use libproc::libproc::proc_pid;
 ///
-/// match proc_pid::name(1) {
-///     Ok(name) => println!("Name: {}", name),
+/// match proc_pid::cmdline(std::process::id() as i32) {
+///     Ok(cmdline) => println!("Command line: {}", cmdline),
 ///     Err(err) => writeln!(&mut std::io::stderr(), "Error: {}", err).unwrap()
 /// }
 /// ```
-pub fn name(pid: i32) -> Result<String> {
-    let mut namebuf: Vec<u8> = Vec::with_capacity(PROC_PIDPATHINFO_MAXSIZE - 1);
-    let buffer_ptr = namebuf.as_ptr() as *mut c_void;
-    let buffer_size = namebuf.capacity() as u32;
-    let ret: i32;
-
-    unsafe {
-        ret = proc_name(pid, buffer_ptr, buffer_size);
-    };
-
-    if ret <= 0 {
-        Err(Error::last_os_error())
-    } else {
-        unsafe {
-            namebuf.set_len(ret as usize);
-        }
-
-        match String::from_utf8(namebuf) {
-            Ok(name) => Ok(name),
-            Err(e) => Err(Error::new(
-                ErrorKind::Other,
-                format!("Invalid UTF-8 sequence: {}", e),
-            )),
-        }
-    }
+pub fn cmdline(pid: i32) -> Result<String> {
+    let args: Vec<String> = pidargs(pid)?
+        .args
+        .iter()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+
+    Ok(args.join(" "))
 }
 
 // This trait is needed for polymorphism on listpidinfo types, also abstracting flavor in order to provide
@@ -506,11 +1928,19 @@ pub fn name(pid: i32) -> Result<String> {
 pub trait ListPIDInfo {
     type Item;
     fn flavor() -> PidInfoFlavor;
+
+    // There is no libproc on Linux, so the types that can be listed from /proc do so themselves
+    #[cfg(target_os = "linux")]
+    fn list_procfs(_pid: i32) -> Result<Vec<Self::Item>> {
+        Err(procfs::unsupported("This ListPIDInfo flavor"))
+    }
 }
 
 /// Returns the information of the process that match pid passed in.
 /// `max_len` is the maximum number of array to return.
 /// The length of return value: `Vec<T::Item>` may be less than `max_len`.
+/// An error of kind `InvalidInput` is returned if `max_len` items do not fit in a buffer that
+/// libproc can fill.
 ///
 /// # Examples
 ///
@@ -532,10 +1962,15 @@ pub trait ListPIDInfo {
 ///     }
 /// }
 /// ```
+#[cfg(target_os = "macos")]
 pub fn listpidinfo<T: ListPIDInfo>(pid: i32, max_len: usize) -> Result<Vec<T::Item>> {
-    assert!(max_len <= PROC_PIDPATHINFO_MAXSIZE);
     let flavor = T::flavor() as i32;
-    let buffer_size = mem::size_of::<T::Item>() as i32 * max_len as i32;
+    // The buffer is sized for max_len items, which may be many more than fit in a path
+    let buffer_size = mem::size_of::<T::Item>()
+        .checked_mul(max_len)
+        .filter(|&size| size <= i32::MAX as usize)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "max_len is too large"))?
+        as i32;
     let mut buffer = Vec::<T::Item>::with_capacity(max_len);
     let buffer_ptr = unsafe {
         buffer.set_len(max_len);
@@ -559,6 +1994,15 @@ pub fn listpidinfo<T: ListPIDInfo>(pid: i32, max_len: usize) -> Result<Vec<T::It
     }
 }
 
+/// Returns the information of the process that match pid passed in, listed from /proc for
+/// the types that support it
+#[cfg(target_os = "linux")]
+pub fn listpidinfo<T: ListPIDInfo>(pid: i32, max_len: usize) -> Result<Vec<T::Item>> {
+    let mut items = T::list_procfs(pid)?;
+    items.truncate(max_len);
+    Ok(items)
+}
+
 pub struct ListThreads;
 
 impl ListPIDInfo for ListThreads {
@@ -566,6 +2010,14 @@ impl ListPIDInfo for ListThreads {
     fn flavor() -> PidInfoFlavor {
         PidInfoFlavor::ListThreads
     }
+
+    #[cfg(target_os = "linux")]
+    fn list_procfs(pid: i32) -> Result<Vec<u64>> {
+        Ok(procfs::tids(pid)?
+            .into_iter()
+            .map(|tid| tid as u64)
+            .collect())
+    }
 }
 
 pub struct ListFDs;
@@ -575,6 +2027,36 @@ impl ListPIDInfo for ListFDs {
     fn flavor() -> PidInfoFlavor {
         PidInfoFlavor::ListFDs
     }
+
+    // The type of each descriptor is worked out from the target of its link in /proc/<pid>/fd
+    #[cfg(target_os = "linux")]
+    fn list_procfs(pid: i32) -> Result<Vec<ProcFDInfo>> {
+        let fd_dir = procfs::pid_path(pid, "fd");
+        let fds = procfs::numeric_entries(&fd_dir).map_err(|err| procfs::pid_error(pid, err))?;
+
+        Ok(fds
+            .into_iter()
+            .filter_map(|fd| {
+                // Descriptors may be closed while we are looking at them
+                let target = fs::read_link(fd_dir.join(fd.to_string())).ok()?;
+                let target = target.to_string_lossy();
+                let fd_type = if target.starts_with("socket:") {
+                    ProcFDType::Socket
+                } else if target.starts_with("pipe:") {
+                    ProcFDType::Pipe
+                } else if target.starts_with('/') {
+                    ProcFDType::VNode
+                } else {
+                    ProcFDType::Unknown
+                };
+
+                Some(ProcFDInfo {
+                    proc_fd: fd,
+                    proc_fdtype: fd_type as u32,
+                })
+            })
+            .collect())
+    }
 }
 
 #[repr(C)]
@@ -583,7 +2065,7 @@ pub struct ProcFDInfo {
     pub proc_fdtype: u32,
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum ProcFDType {
     /// AppleTalk
     ATalk = 0,
@@ -625,6 +2107,12 @@ impl From<u32> for ProcFDType {
 // type-guaranteed flavor correctness
 pub trait PIDFDInfo: Default {
     fn flavor() -> PidFDInfoFlavor;
+
+    // There is no libproc on Linux, so the types that can be built from /proc do so themselves
+    #[cfg(target_os = "linux")]
+    fn from_procfs(_pid: i32, _fd: i32) -> Result<Self> {
+        Err(procfs::unsupported("This PIDFDInfo flavor"))
+    }
 }
 
 /// Returns the information about file descriptors of the process that match pid passed in.
@@ -683,6 +2171,7 @@ pub trait PIDFDInfo: Default {
 /// }
 /// ```
 ///
+#[cfg(target_os = "macos")]
 pub fn pidfdinfo<T: PIDFDInfo>(pid: i32, fd: i32) -> Result<T> {
     let flavor = T::flavor() as i32;
     let buffer_size = mem::size_of::<T>() as i32;
@@ -701,6 +2190,13 @@ pub fn pidfdinfo<T: PIDFDInfo>(pid: i32, fd: i32) -> Result<T> {
     }
 }
 
+/// Returns the information about a file descriptor of the process that match pid passed in,
+/// built from /proc for the types that support it
+#[cfg(target_os = "linux")]
+pub fn pidfdinfo<T: PIDFDInfo>(pid: i32, fd: i32) -> Result<T> {
+    T::from_procfs(pid, fd)
+}
+
 #[repr(C)]
 #[derive(Default)]
 pub struct SocketFDInfo {
@@ -724,6 +2220,147 @@ pub struct ProcFileInfo {
     pub rfu_1: i32,
 }
 
+impl ProcFileInfo {
+    /// Whether the file descriptor was opened for reading
+    pub fn is_readable(&self) -> bool {
+        #[cfg(target_os = "linux")]
+        return self.fi_openflags & libc::O_ACCMODE as u32 != libc::O_WRONLY as u32;
+        #[cfg(target_os = "macos")]
+        return self.fi_openflags & FREAD != 0;
+    }
+
+    /// Whether the file descriptor was opened for writing
+    pub fn is_writable(&self) -> bool {
+        #[cfg(target_os = "linux")]
+        return self.fi_openflags & libc::O_ACCMODE as u32 != libc::O_RDONLY as u32;
+        #[cfg(target_os = "macos")]
+        return self.fi_openflags & FWRITE != 0;
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl ProcFileInfo {
+    // From /proc/<pid>/fdinfo/<fd>, the open flags are those of open(2) and not the FREAD,
+    // FWRITE etc. flags of Mac OS X
+    fn from_procfs(pid: i32, fd: i32, fd_type: ProcFDType) -> Result<ProcFileInfo> {
+        let fdinfo = <crate::libproc::proc_fdinfo::FDInfo as PIDFDInfo>::from_procfs(pid, fd)?;
+        Ok(ProcFileInfo {
+            fi_openflags: fdinfo.flags,
+            fi_offset: fdinfo.pos as off_t,
+            fi_type: fd_type as i32,
+            ..Default::default()
+        })
+    }
+}
+
+/// A file descriptor of a file, directory or device, from PROC_PIDFDVNODEINFO
+#[repr(C)]
+#[derive(Default)]
+pub struct VnodeFDInfo {
+    pub pfi: ProcFileInfo,
+    pub pvi: VnodeInfo,
+}
+
+impl PIDFDInfo for VnodeFDInfo {
+    fn flavor() -> PidFDInfoFlavor {
+        PidFDInfoFlavor::VNodeInfo
+    }
+
+    #[cfg(target_os = "linux")]
+    fn from_procfs(pid: i32, fd: i32) -> Result<VnodeFDInfo> {
+        let info = VnodePathFDInfo::from_procfs(pid, fd)?;
+        Ok(VnodeFDInfo {
+            pfi: info.pfi,
+            pvi: info.pvip.vip_vi,
+        })
+    }
+}
+
+// From http://opensource.apple.com//source/xnu/xnu-1456.1.26/bsd/sys/proc_info.h
+#[repr(C)]
+#[derive(Default)]
+pub struct PipeInfo {
+    pub pipe_stat: VInfoStat,
+    /// The pipe, and the pipe at the other end of it. On Linux both ends share the same pipe,
+    /// which is identified by its inode.
+    pub pipe_handle: u64,
+    pub pipe_peerhandle: u64,
+    pub pipe_status: c_int,
+    pub rfu_1: c_int,
+}
+
+/// A file descriptor of one end of a pipe, from PROC_PIDFDPIPEINFO
+#[repr(C)]
+#[derive(Default)]
+pub struct PipeFDInfo {
+    pub pfi: ProcFileInfo,
+    pub pipeinfo: PipeInfo,
+}
+
+impl PIDFDInfo for PipeFDInfo {
+    fn flavor() -> PidFDInfoFlavor {
+        PidFDInfoFlavor::PipeInfo
+    }
+
+    #[cfg(target_os = "linux")]
+    fn from_procfs(pid: i32, fd: i32) -> Result<PipeFDInfo> {
+        let link = procfs::pid_path(pid, &format!("fd/{}", fd));
+        let target = fs::read_link(&link).map_err(|err| procfs::pid_error(pid, err))?;
+        // Anonymous pipes link to "pipe:[<inode>]", named pipes to their path
+        let metadata = fs::metadata(&link).map_err(|err| procfs::pid_error(pid, err))?;
+        if !target.to_string_lossy().starts_with("pipe:") && vnode_type(&metadata) != 7 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("File descriptor {} is not a pipe", fd),
+            ));
+        }
+
+        let pipe_stat = VInfoStat::from_metadata(&metadata);
+        Ok(PipeFDInfo {
+            pfi: ProcFileInfo::from_procfs(pid, fd, ProcFDType::Pipe)?,
+            pipeinfo: PipeInfo {
+                pipe_handle: pipe_stat.vst_ino,
+                pipe_peerhandle: pipe_stat.vst_ino,
+                pipe_stat,
+                ..Default::default()
+            },
+        })
+    }
+}
+
+/// A file descriptor of a file, directory or device with its path, from PROC_PIDFDVNODEPATHINFO
+#[repr(C)]
+#[derive(Default)]
+pub struct VnodePathFDInfo {
+    pub pfi: ProcFileInfo,
+    pub pvip: VnodeInfoPath,
+}
+
+impl PIDFDInfo for VnodePathFDInfo {
+    fn flavor() -> PidFDInfoFlavor {
+        PidFDInfoFlavor::VNodePathInfo
+    }
+
+    #[cfg(target_os = "linux")]
+    fn from_procfs(pid: i32, fd: i32) -> Result<VnodePathFDInfo> {
+        let link = format!("fd/{}", fd);
+        let target = fs::read_link(procfs::pid_path(pid, &link))
+            .map_err(|err| procfs::pid_error(pid, err))?;
+        // Sockets, pipes and anonymous inodes link to a description such as "pipe:[1234]"
+        if !target.starts_with("/") {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("File descriptor {} is not a vnode", fd),
+            ));
+        }
+
+        Ok(VnodePathFDInfo {
+            pvip: VnodeInfoPath::from_procfs(pid, &link)?,
+            pfi: ProcFileInfo::from_procfs(pid, fd, ProcFDType::VNode)?,
+        })
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 pub enum SocketInfoKind {
     Generic = 0,
@@ -786,7 +2423,11 @@ pub struct SocketInfo {
 #[repr(C)]
 #[derive(Default)]
 pub struct VInfoStat {
+    #[cfg(target_os = "macos")]
     pub vst_dev: u32,
+    // Linux device numbers may not fit in 32 bits
+    #[cfg(target_os = "linux")]
+    pub vst_dev: u64,
     pub vst_mode: u16,
     pub vst_nlink: u16,
     pub vst_ino: u64,
@@ -805,10 +2446,84 @@ pub struct VInfoStat {
     pub vst_blksize: i32,
     pub vst_flags: u32,
     pub vst_gen: u32,
+    #[cfg(target_os = "macos")]
     pub vst_rdev: u32,
+    #[cfg(target_os = "linux")]
+    pub vst_rdev: u64,
     pub vst_qspare: [i64; 2],
 }
 
+// A time as seconds and nanoseconds either side of the epoch
+fn system_time(seconds: i64, nanoseconds: i64) -> SystemTime {
+    let since_epoch = Duration::new(seconds.unsigned_abs(), 0);
+    let time = if seconds < 0 {
+        UNIX_EPOCH - since_epoch
+    } else {
+        UNIX_EPOCH + since_epoch
+    };
+    time + Duration::from_nanos(nanoseconds as u64)
+}
+
+impl VInfoStat {
+    /// The device and inode, which together identify the file
+    // vst_dev is only narrower than u64 on Mac OS X
+    #[cfg_attr(target_os = "linux", allow(clippy::useless_conversion))]
+    pub fn identity(&self) -> (u64, u64) {
+        (u64::from(self.vst_dev), self.vst_ino)
+    }
+
+    /// The time the file was last accessed
+    pub fn accessed(&self) -> SystemTime {
+        system_time(self.vst_atime, self.vst_atimensec)
+    }
+
+    /// The time the contents of the file were last modified
+    pub fn modified(&self) -> SystemTime {
+        system_time(self.vst_mtime, self.vst_mtimensec)
+    }
+
+    /// The time the status of the file (owner, mode, links etc.) last changed
+    pub fn changed(&self) -> SystemTime {
+        system_time(self.vst_ctime, self.vst_ctimensec)
+    }
+
+    /// The time the file was created, which is not known on Linux
+    pub fn created(&self) -> Option<SystemTime> {
+        if self.vst_birthtime == 0 && self.vst_birthtimensec == 0 {
+            None
+        } else {
+            Some(system_time(self.vst_birthtime, self.vst_birthtimensec))
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl VInfoStat {
+    pub(crate) fn from_metadata(metadata: &fs::Metadata) -> VInfoStat {
+        use std::os::unix::fs::MetadataExt;
+
+        VInfoStat {
+            vst_dev: metadata.dev(),
+            vst_mode: metadata.mode() as u16,
+            vst_nlink: metadata.nlink() as u16,
+            vst_ino: metadata.ino(),
+            vst_uid: metadata.uid(),
+            vst_gid: metadata.gid(),
+            vst_atime: metadata.atime(),
+            vst_atimensec: metadata.atime_nsec(),
+            vst_mtime: metadata.mtime(),
+            vst_mtimensec: metadata.mtime_nsec(),
+            vst_ctime: metadata.ctime(),
+            vst_ctimensec: metadata.ctime_nsec(),
+            vst_size: metadata.size() as off_t,
+            vst_blocks: metadata.blocks() as i64,
+            vst_blksize: metadata.blksize() as i32,
+            vst_rdev: metadata.rdev(),
+            ..Default::default()
+        }
+    }
+}
+
 #[repr(C)]
 #[derive(Default)]
 pub struct SockBufInfo {
@@ -979,13 +2694,13 @@ pub struct UnSockInfo {
 #[derive(Copy, Clone)]
 pub union UnSIAddr {
     pub ua_sun: sockaddr_un,
-    pub ua_dummy: [c_char; SOCK_MAXADDRLEN as usize],
+    pub ua_dummy: [c_char; SOCK_MAXADDRLEN],
 }
 
 impl Default for UnSIAddr {
     fn default() -> UnSIAddr {
         UnSIAddr {
-            ua_dummy: [0; SOCK_MAXADDRLEN as usize],
+            ua_dummy: [0; SOCK_MAXADDRLEN],
         }
     }
 }
@@ -1045,7 +2760,7 @@ mod test {
                 assert!(pids.len() > 1);
                 println!("Found {} processes using listpids()", pids.len());
             }
-            Err(err) => assert!(false, "Error listing pids: {}", err),
+            Err(err) => panic!("Error listing pids: {}", err),
         }
     }
 
@@ -1057,7 +2772,7 @@ mod test {
                 assert!(pids.len() > 2);
                 println!("Found {} processes using listpids(uid)", pids.len());
             }
-            Err(err) => assert!(false, "Error listing pids: {}", err),
+            Err(err) => panic!("Error listing pids: {}", err),
         }
     }
 
@@ -1068,7 +2783,7 @@ mod test {
 
         match pidinfo::<BSDInfo>(pid, 0) {
             Ok(info) => assert_eq!(info.pbi_pid as i32, pid),
-            Err(err) => assert!(false, "Error retrieving process info: {}", err),
+            Err(err) => panic!("Error retrieving process info: {}", err),
         };
     }
 
@@ -1081,17 +2796,31 @@ mod test {
                 "Region Filename (at address = 0) of init process PID = 1 is '{}'",
                 regionfilename
             ),
-            Err(message) => assert!(true, message),
+            Err(message) => println!("Error: {}", message),
+        }
+    }
+
+    #[test]
+    // This checks that it can find the path of its own process
+    fn pidpath_test_own_pid() {
+        use std::process;
+        let pid = process::id() as i32;
+
+        match pidpath(pid) {
+            Ok(path) => assert_eq!(path, std::env::current_exe().unwrap().to_string_lossy()),
+            Err(message) => panic!("{}", message),
         }
     }
 
     #[test]
+    // On Linux only privileged users may find the path of other users' processes
+    #[cfg(target_os = "macos")]
     // This checks that it can find the path of the init process with PID 1
     fn pidpath_test_init_pid() {
         match pidpath(1) {
             // run tests with 'cargo test -- --nocapture' to see the test output
             Ok(path) => println!("Path of init process with PID = 1 is '{}'", path),
-            Err(message) => assert!(false, message),
+            Err(message) => panic!("{}", message),
         }
     }
 
@@ -1101,23 +2830,24 @@ mod test {
     fn pidpath_test_unknown_pid() {
         match pidpath(-1) {
             // run tests with 'cargo test -- --nocapture' to see the test output
-            Ok(path) => assert!(
-                false,
+            Ok(path) => panic!(
                 "It found the path of process Pwith ID = -1 (path = {}), that's not possible\n",
                 path
             ),
-            Err(message) => assert!(false, message),
+            Err(message) => panic!("{}", message),
         }
     }
 
     #[test]
+    // There is no libproc library on Linux, so only Mac OS X has a version
+    #[cfg(target_os = "macos")]
     fn libversion_test() {
         match libversion() {
             Ok((major, minor)) => {
                 // run tests with 'cargo test -- --nocapture' to see the test output
                 println!("Major = {}, Minor = {}", major, minor);
             }
-            Err(message) => assert!(false, message),
+            Err(message) => panic!("{}", message),
         }
     }
 
@@ -1128,10 +2858,482 @@ mod test {
         match pidpath(1) {
             // run tests with 'cargo test -- --nocapture' to see the test output
             Ok(path) => println!("Name of init process PID = 1 is '{}'", path),
-            Err(message) => assert!(true, message),
+            Err(message) => println!("Error: {}", message),
+        }
+    }
+
+    #[test]
+    fn c_chars_to_string_test() {
+        let chars = [b'l' as c_char, b's' as c_char, 0, b'x' as c_char];
+        assert_eq!(c_chars_to_string(&chars), "ls");
+        assert_eq!(c_chars_to_string(&chars[..2]), "ls");
+        assert_eq!(c_chars_to_string(&[0xff_u8 as c_char, 0]), "\u{fffd}");
+    }
+
+    #[test]
+    fn cmdline_test() {
+        use std::process;
+        let pid = process::id() as i32;
+
+        match cmdline(pid) {
+            Ok(cmdline) => assert!(!cmdline.is_empty()),
+            Err(err) => panic!("Error retrieving command line: {}", err),
+        }
+    }
+
+    fn procargs2_fixture(argc: i32, strings: &[&[u8]]) -> Vec<u8> {
+        let mut buffer = argc.to_ne_bytes().to_vec();
+        for string in strings {
+            buffer.extend_from_slice(string);
+            buffer.push(0);
+        }
+        buffer
+    }
+
+    #[test]
+    fn parse_procargs2_test() {
+        let buffer = procargs2_fixture(
+            2,
+            &[
+                b"/bin/sleep\0\0\0",
+                b"sleep",
+                b"10",
+                b"HOME=/Users/me",
+                b"EMPTY=",
+                b"NOT_A_VARIABLE",
+                b"A=b=c",
+                b"",
+                b"executable_path=/bin/sleep",
+            ],
+        );
+        let (args, env) = parse_procargs2(&buffer).unwrap();
+        assert_eq!(args.exec_path, Some(PathBuf::from("/bin/sleep")));
+        assert_eq!(
+            args.args,
+            vec![OsString::from("sleep"), OsString::from("10")]
+        );
+        assert_eq!(
+            env,
+            vec![
+                (OsString::from("HOME"), OsString::from("/Users/me")),
+                (OsString::from("EMPTY"), OsString::from("")),
+                (OsString::from("A"), OsString::from("b=c")),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_procargs2_non_utf8_test() {
+        let buffer = procargs2_fixture(1, &[b"/bin/\xff", b"\xfe\xff", b"K\xff=\xfe"]);
+        let (args, env) = parse_procargs2(&buffer).unwrap();
+        assert_eq!(
+            args.exec_path,
+            Some(PathBuf::from(OsString::from_vec(b"/bin/\xff".to_vec())))
+        );
+        assert_eq!(args.args, vec![OsString::from_vec(b"\xfe\xff".to_vec())]);
+        assert_eq!(
+            env,
+            vec![(
+                OsString::from_vec(b"K\xff".to_vec()),
+                OsString::from_vec(b"\xfe".to_vec())
+            )]
+        );
+    }
+
+    #[test]
+    fn parse_procargs2_truncated_test() {
+        assert!(parse_procargs2(&[1, 0]).is_err());
+        assert!(parse_procargs2(&procargs2_fixture(3, &[b"/bin/ls", b"ls"])).is_err());
+    }
+
+    #[test]
+    fn pidargs_and_pidenv_test() {
+        use std::process::Command;
+
+        let arg = OsString::from_vec(b"not \xffutf-8".to_vec());
+        let value = OsString::from_vec(b"value\xfe".to_vec());
+        // sh waits for input that never comes, with its arguments in place
+        let mut child = Command::new("sh")
+            .args(["-c", "read line", "name"])
+            .stdin(std::process::Stdio::piped())
+            .arg(&arg)
+            .env("LIBPROC_PIDENV_TEST", &value)
+            .spawn()
+            .unwrap();
+        let pid = child.id() as i32;
+
+        // Give the child time to exec, until then it has our arguments
+        let mut args = pidargs(pid).unwrap();
+        for _ in 0..100 {
+            if args.args.contains(&arg) {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            args = pidargs(pid).unwrap();
+        }
+        let env = pidenv(pid);
+        child.kill().unwrap();
+        child.wait().unwrap();
+
+        assert_eq!(
+            args.args,
+            vec![
+                OsString::from("sh"),
+                OsString::from("-c"),
+                OsString::from("read line"),
+                OsString::from("name"),
+                arg
+            ]
+        );
+        assert!(args.exec_path.is_some());
+        assert!(env
+            .unwrap()
+            .contains(&(OsString::from("LIBPROC_PIDENV_TEST"), value)));
+    }
+
+    #[test]
+    fn pidcwd_test() {
+        use std::env;
+        use std::process;
+
+        match pidcwd(process::id() as i32) {
+            Ok(cwd) => assert_eq!(cwd, env::current_dir().unwrap()),
+            Err(err) => panic!("Error retrieving working directory: {}", err),
+        }
+        match pidroot(process::id() as i32) {
+            Ok(root) => assert_eq!(root, PathBuf::from("/")),
+            Err(err) => panic!("Error retrieving root directory: {}", err),
+        }
+    }
+
+    // Paths longer than the MAXPATHLEN of Mac OS X are not truncated
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn pidcwd_long_path_test() {
+        use std::env;
+        use std::process::Command;
+
+        let mut dir = env::temp_dir().join(format!("pidcwd_long_path_test_{}", std::process::id()));
+        let top = dir.clone();
+        while dir.as_os_str().len() <= MAXPATHLEN {
+            dir.push("a".repeat(100));
+        }
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut child = Command::new("sleep")
+            .arg("5")
+            .current_dir(&dir)
+            .spawn()
+            .unwrap();
+        let cwd = pidcwd(child.id() as i32);
+        child.kill().unwrap();
+        child.wait().unwrap();
+        fs::remove_dir_all(&top).unwrap();
+
+        assert_eq!(cwd.unwrap(), dir);
+    }
+
+    #[test]
+    fn vnodepathinfo_test() {
+        use std::env;
+        use std::os::unix::fs::MetadataExt;
+        use std::process;
+
+        match pidinfo::<VnodePathInfo>(process::id() as i32, 0) {
+            Ok(info) => {
+                let metadata = env::current_dir().unwrap().metadata().unwrap();
+                assert_eq!(info.pvi_cdir.vip_vi.vi_stat.vst_ino, metadata.ino());
+                assert_eq!(info.pvi_cdir.vip_vi.vi_type, 2); // VDIR
+            }
+            Err(err) => panic!("Error retrieving directories: {}", err),
+        }
+    }
+
+    #[test]
+    fn vnodepathfdinfo_test() {
+        use std::fs::File;
+        use std::io::{Seek, SeekFrom, Write};
+        use std::os::unix::fs::MetadataExt;
+        use std::os::unix::io::AsRawFd;
+        use std::process;
+
+        let path = std::env::temp_dir().join(format!("vnodepathfdinfo_test.{}", process::id()));
+        let mut file = File::create(&path).unwrap();
+        file.write_all(b"0123456789").unwrap();
+        file.seek(SeekFrom::Start(4)).unwrap();
+        let metadata = file.metadata().unwrap();
+
+        let info = pidfdinfo::<VnodePathFDInfo>(process::id() as i32, file.as_raw_fd());
+        std::fs::remove_file(&path).unwrap();
+        match info {
+            Ok(info) => {
+                assert_eq!(info.pfi.fi_offset, 4);
+                assert_eq!(info.pvip.vip_vi.vi_type, 1); // VREG
+                let stat = &info.pvip.vip_vi.vi_stat;
+                assert_eq!(stat.vst_size, 10);
+                assert_eq!(stat.identity().1, metadata.ino());
+                assert_eq!(stat.modified(), metadata.modified().unwrap());
+                assert_eq!(info.pvip.path().file_name(), path.file_name());
+            }
+            Err(err) => panic!("Error retrieving file descriptor info: {}", err),
+        }
+    }
+
+    #[test]
+    fn system_time_test() {
+        assert_eq!(system_time(1, 500), UNIX_EPOCH + Duration::new(1, 500));
+        assert_eq!(
+            system_time(-2, 500_000_000),
+            UNIX_EPOCH - Duration::from_millis(1500)
+        );
+    }
+
+    #[test]
+    fn pidregions_test() {
+        use std::os::unix::fs::MetadataExt;
+        use std::process;
+
+        // The executable of this test is mapped into its address space, from its file
+        let exe = std::env::current_exe().unwrap();
+        let metadata = exe.metadata().unwrap();
+        let regions: Vec<MemoryRegion> = pidregions(process::id() as i32)
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert!(regions.windows(2).all(|pair| pair[0].end <= pair[1].start));
+
+        let code = regions
+            .iter()
+            .find(|region| region.path.as_ref() == Some(&exe) && region.protection.execute)
+            .expect("The executable is not mapped");
+        assert_eq!(code.inode, metadata.ino());
+        assert_eq!(code.dev, metadata.dev());
+        assert!(code.size() > 0);
+    }
+
+    // A mapped file whose path is not UTF-8 must not stop the regions being read
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn pidregions_non_utf8_path_test() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+        use std::os::unix::io::AsRawFd;
+        use std::process;
+
+        let mut name = b"pidregions_test_\xff.".to_vec();
+        name.extend_from_slice(process::id().to_string().as_bytes());
+        let path = std::env::temp_dir().join(OsStr::from_bytes(&name));
+        let file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        file.set_len(4096).unwrap();
+        let address = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                4096,
+                libc::PROT_READ,
+                libc::MAP_SHARED,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        assert_ne!(address, libc::MAP_FAILED);
+
+        let regions = pidregions(process::id() as i32).map(|regions| regions.count());
+        let usage = pidmemoryusage(process::id() as i32);
+        unsafe { libc::munmap(address, 4096) };
+        fs::remove_file(&path).unwrap();
+
+        assert!(regions.unwrap() > 0);
+        usage.unwrap();
+    }
+
+    #[test]
+    fn pidregionusage_test() {
+        use std::process;
+
+        match pidregionusage(process::id() as i32) {
+            Ok(regions) => {
+                assert!(!regions.is_empty());
+                assert!(regions.iter().any(|(_, usage)| usage.resident > 0));
+                assert!(regions
+                    .iter()
+                    .all(|(region, usage)| usage.resident <= region.size()));
+            }
+            Err(err) => panic!("Error retrieving region usage: {}", err),
+        }
+    }
+
+    #[test]
+    fn pidmemoryusage_test() {
+        use std::process;
+
+        let pid = process::id() as i32;
+        let usage = pidmemoryusage(pid).unwrap();
+        assert!(usage.resident > 0);
+        assert!(usage.private <= usage.resident);
+        #[cfg(target_os = "linux")]
+        assert_eq!(usage.private + usage.shared, usage.resident);
+        if let Some(proportional) = usage.proportional {
+            assert!(usage.private <= proportional && proportional <= usage.resident);
+        }
+
+        // The rollup is the total of the regions, give or take what changed in between
+        let mut total = RegionUsage::default();
+        for (_, region) in pidregionusage(pid).unwrap() {
+            total.add(&region);
+        }
+        let difference = (total.resident as i64 - usage.resident as i64).abs();
+        assert!(difference < usage.resident as i64 / 10);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn parse_procfs_diskio_test() {
+        let io = DiskIO::parse_procfs(
+            "rchar: 323934931\nwchar: 323929600\nsyscr: 632687\nsyscw: 632675\n\
+             read_bytes: 4096\nwrite_bytes: 323932160\ncancelled_write_bytes: 12\n",
+        )
+        .unwrap();
+        assert_eq!(io.read_bytes, 4096);
+        assert_eq!(io.write_bytes, 323_932_160);
+        assert_eq!(io.read_chars, Some(323_934_931));
+        assert_eq!(io.write_syscalls, Some(632_675));
+        assert_eq!(io.cancelled_write_bytes, Some(12));
+        assert_eq!(io.logical_write_bytes, None);
+
+        assert!(DiskIO::parse_procfs("rchar: 1\n").is_err());
+    }
+
+    #[test]
+    fn from_rusage_info_test() {
+        let mut buffer = vec![0u8; RUSAGE_INFO_V4_SIZE];
+        buffer[RI_DISKIO_BYTESREAD..RI_DISKIO_BYTESREAD + 8].copy_from_slice(&10u64.to_ne_bytes());
+        buffer[RI_DISKIO_BYTESWRITTEN..RI_DISKIO_BYTESWRITTEN + 8]
+            .copy_from_slice(&20u64.to_ne_bytes());
+        buffer[RI_LOGICAL_WRITES..RI_LOGICAL_WRITES + 8].copy_from_slice(&30u64.to_ne_bytes());
+
+        let io = DiskIO::from_rusage_info(&buffer).unwrap();
+        assert_eq!((io.read_bytes, io.write_bytes), (10, 20));
+        assert_eq!(io.logical_write_bytes, Some(30));
+        assert_eq!(io.read_syscalls, None);
+
+        // rusage_info_v2 has no logical writes, v1 no disk I/O at all
+        let io = DiskIO::from_rusage_info(&buffer[..RUSAGE_INFO_V2_SIZE]).unwrap();
+        assert_eq!(io.logical_write_bytes, None);
+        assert!(DiskIO::from_rusage_info(&buffer[..144]).is_err());
+    }
+
+    #[test]
+    fn diskio_rate_test() {
+        use std::time::Duration;
+
+        let earlier = DiskIO {
+            read_bytes: 1000,
+            write_bytes: 500,
+            read_syscalls: Some(10),
+            cancelled_write_bytes: Some(0),
+            ..Default::default()
+        };
+        let later = DiskIO {
+            read_bytes: 5000,
+            write_bytes: 500,
+            read_syscalls: Some(30),
+            cancelled_write_bytes: Some(4096),
+            ..Default::default()
+        };
+
+        let rate = later.rate(&earlier, Duration::from_secs(2));
+        assert_eq!(rate.read_bytes, 2000.0);
+        assert_eq!(rate.write_bytes, 0.0);
+        assert_eq!(rate.read_syscalls, Some(10.0));
+        assert_eq!(rate.write_syscalls, None);
+        assert_eq!(rate.cancelled_write_bytes, Some(2048.0));
+        assert_eq!(rate.logical_write_bytes, None);
+        assert_eq!(later.rate(&earlier, Duration::from_secs(0)).read_bytes, 0.0);
+        // Counters never go backwards, but a different process could be compared by mistake
+        assert_eq!(earlier.since(&later).read_bytes, 0);
+    }
+
+    #[test]
+    fn piddiskio_test() {
+        use std::io::Write;
+        use std::process;
+
+        let before = piddiskio(process::id() as i32).unwrap();
+        let path = std::env::temp_dir().join(format!("piddiskio_test.{}", process::id()));
+        let mut file = std::fs::File::create(&path).unwrap();
+        file.write_all(&[0; 8192]).unwrap();
+        file.sync_all().unwrap();
+        let after = piddiskio(process::id() as i32).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // Other tests run in the same process, so only check the counters did not go backwards
+        assert!(after.write_bytes >= before.write_bytes);
+        if let (Some(after), Some(before)) = (after.write_chars, before.write_chars) {
+            assert!(after >= before + 8192);
         }
     }
 
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn parse_schedstat_test() {
+        let info = ThreadSchedInfo::parse_schedstat("4317076 3149 4\n").unwrap();
+        assert_eq!(info.run_time, 4_317_076);
+        assert_eq!(info.wait_time, 3149);
+        assert_eq!(info.timeslices, 4);
+        assert_eq!(info.average_wait(), Some(Duration::from_nanos(787)));
+        assert!(info.wait_fraction() < 0.001);
+        assert!(ThreadSchedInfo::parse_schedstat("4317076 3149\n").is_err());
+        assert_eq!(ThreadSchedInfo::default().average_wait(), None);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn threadschedinfo_test() {
+        let pid = std::process::id() as i32;
+        let tid = unsafe { libc::syscall(libc::SYS_gettid) } as u64;
+        // The run time is brought up to date when the thread stops running
+        std::thread::sleep(Duration::from_millis(10));
+        let info = pidinfo::<ThreadSchedInfo>(pid, tid).unwrap();
+
+        assert!(info.run_time > 0);
+        assert!(info.timeslices > 0);
+        assert!(info.voluntary_switches > 0);
+        assert!(info.last_cpu >= 0);
+    }
+
+    #[test]
+    fn regionusage_add_test() {
+        let mut total = RegionUsage {
+            resident: 8,
+            proportional: Some(4),
+            ..Default::default()
+        };
+        total.add(&RegionUsage {
+            resident: 2,
+            proportional: Some(1),
+            anonymous: Some(2),
+            ..Default::default()
+        });
+        assert_eq!(total.resident, 10);
+        assert_eq!(total.proportional, Some(5));
+        assert_eq!(total.anonymous, Some(2));
+        assert_eq!(total.swapped_proportional, None);
+    }
+
+    #[test]
+    fn protection_test() {
+        assert_eq!(Protection::from(5).to_string(), "r-x");
+        assert_eq!(Protection::from(3).to_string(), "rw-");
+        assert_eq!(ShareMode::from(4), ShareMode::Shared);
+        assert_eq!(ShareMode::from(99), ShareMode::Unknown);
+    }
+
     #[test]
     fn listpidinfo_test() {
         use std::process;
@@ -1140,19 +3342,21 @@ mod test {
         match pidinfo::<TaskAllInfo>(pid, 0) {
             Ok(info) => {
                 match listpidinfo::<ListThreads>(pid, info.ptinfo.pti_threadnum as usize) {
-                    Ok(threads) => assert!(threads.len() > 0),
-                    Err(err) => assert!(false, "Error retrieving process info: {}", err),
+                    Ok(threads) => assert!(!threads.is_empty()),
+                    Err(err) => panic!("Error retrieving process info: {}", err),
                 }
                 match listpidinfo::<ListFDs>(pid, info.pbsd.pbi_nfiles as usize) {
-                    Ok(fds) => assert!(fds.len() > 0),
-                    Err(err) => assert!(false, "Error retrieving process info: {}", err),
+                    Ok(fds) => assert!(!fds.is_empty()),
+                    Err(err) => panic!("Error retrieving process info: {}", err),
                 }
             }
-            Err(err) => assert!(false, "Error retrieving process info: {}", err),
+            Err(err) => panic!("Error retrieving process info: {}", err),
         };
     }
 
     #[test]
+    // Only Mac OS X can return the details of a socket, so this checks the TCP port is found there
+    #[cfg(target_os = "macos")]
     fn pidfdinfo_test() {
         use std::net::TcpListener;
         use std::process;
@@ -1163,20 +3367,40 @@ mod test {
         let info = pidinfo::<BSDInfo>(pid, 0).unwrap();
         let fds = listpidinfo::<ListFDs>(pid, info.pbi_nfiles as usize).unwrap();
         for fd in fds {
-            match fd.proc_fdtype.into() {
-                ProcFDType::Socket => {
-                    let socket = pidfdinfo::<SocketFDInfo>(pid, fd.proc_fd).unwrap();
-                    match socket.psi.soi_kind.into() {
-                        SocketInfoKind::Tcp => unsafe {
-                            let info = socket.psi.soi_proto.pri_tcp;
-                            assert_eq!(socket.psi.soi_protocol, libc::IPPROTO_TCP);
-                            assert_eq!(info.tcpsi_ini.insi_lport as u32, 65535);
-                        },
-                        _ => (),
-                    }
+            if let ProcFDType::Socket = fd.proc_fdtype.into() {
+                let socket = pidfdinfo::<SocketFDInfo>(pid, fd.proc_fd).unwrap();
+                if let SocketInfoKind::Tcp = socket.psi.soi_kind.into() {
+                    let info = unsafe { socket.psi.soi_proto.pri_tcp };
+                    assert_eq!(socket.psi.soi_protocol, libc::IPPROTO_TCP);
+                    assert_eq!(info.tcpsi_ini.insi_lport as u32, 65535);
                 }
-                _ => (),
             }
         }
     }
+
+    #[test]
+    // On Linux the listening socket is found among the descriptors, but its details cannot be read
+    #[cfg(target_os = "linux")]
+    fn pidfdinfo_test() {
+        use std::net::TcpListener;
+        use std::os::unix::io::AsRawFd;
+        use std::process;
+        let pid = process::id() as i32;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let listener_fd = listener.as_raw_fd();
+
+        let info = pidinfo::<BSDInfo>(pid, 0).unwrap();
+        let fds = listpidinfo::<ListFDs>(pid, info.pbi_nfiles as usize).unwrap();
+        let fd = fds
+            .iter()
+            .find(|fd| fd.proc_fd == listener_fd)
+            .expect("Could not find the listening socket");
+        assert!(matches!(fd.proc_fdtype.into(), ProcFDType::Socket));
+
+        match pidfdinfo::<SocketFDInfo>(pid, listener_fd) {
+            Ok(_) => panic!("SocketFDInfo is not expected to be read from /proc"),
+            Err(err) => assert_eq!(err.kind(), ErrorKind::Unsupported),
+        }
+    }
 }
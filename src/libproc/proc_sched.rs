@@ -0,0 +1,496 @@
+// The scheduling policy, priority, nice value and CPU affinity of processes and their threads
+// See http://man7.org/linux/man-pages/man7/sched.7.html
+use std::io::{Error, Result};
+#[cfg(target_os = "linux")]
+use std::time::Duration;
+
+#[cfg(target_os = "macos")]
+use crate::libproc::proc_pid::{self, TaskInfo, ThreadInfo};
+#[cfg(target_os = "linux")]
+use crate::libproc::procfs;
+
+/// A scheduling policy
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SchedPolicy {
+    /// The default time sharing policy, SCHED_OTHER (POLICY_TIMESHARE on Mac OS X)
+    Other,
+    /// Real time first in, first out, SCHED_FIFO
+    Fifo,
+    /// Real time round robin, SCHED_RR
+    RoundRobin,
+    /// For CPU intensive batch jobs, SCHED_BATCH (Linux only)
+    Batch,
+    /// For jobs to run only when nothing else would, SCHED_IDLE (Linux only)
+    Idle,
+    /// Earliest deadline first, SCHED_DEADLINE (Linux only)
+    Deadline,
+}
+
+impl SchedPolicy {
+    /// Decode a raw policy, as in `TaskInfo::pti_policy` and `ThreadInfo::pth_policy`: one of the
+    /// SCHED_* values on Linux, or the POLICY_* values of Mach on Mac OS X
+    #[cfg(target_os = "linux")]
+    pub fn from_raw(policy: i32) -> Option<SchedPolicy> {
+        match policy & !libc::SCHED_RESET_ON_FORK {
+            libc::SCHED_OTHER => Some(SchedPolicy::Other),
+            libc::SCHED_FIFO => Some(SchedPolicy::Fifo),
+            libc::SCHED_RR => Some(SchedPolicy::RoundRobin),
+            libc::SCHED_BATCH => Some(SchedPolicy::Batch),
+            libc::SCHED_IDLE => Some(SchedPolicy::Idle),
+            libc::SCHED_DEADLINE => Some(SchedPolicy::Deadline),
+            _ => None,
+        }
+    }
+
+    /// Decode a raw policy, as in `TaskInfo::pti_policy` and `ThreadInfo::pth_policy`: one of the
+    /// SCHED_* values on Linux, or the POLICY_* values of Mach on Mac OS X
+    #[cfg(target_os = "macos")]
+    pub fn from_raw(policy: i32) -> Option<SchedPolicy> {
+        // From osfmk/mach/policy.h
+        match policy {
+            1 => Some(SchedPolicy::Other),      // POLICY_TIMESHARE
+            2 => Some(SchedPolicy::RoundRobin), // POLICY_RR
+            4 => Some(SchedPolicy::Fifo),       // POLICY_FIFO
+            _ => None,
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn raw(self) -> u32 {
+        (match self {
+            SchedPolicy::Other => libc::SCHED_OTHER,
+            SchedPolicy::Fifo => libc::SCHED_FIFO,
+            SchedPolicy::RoundRobin => libc::SCHED_RR,
+            SchedPolicy::Batch => libc::SCHED_BATCH,
+            SchedPolicy::Idle => libc::SCHED_IDLE,
+            SchedPolicy::Deadline => libc::SCHED_DEADLINE,
+        }) as u32
+    }
+
+    /// Whether this is a real time policy, which needs privileges to set
+    pub fn is_realtime(self) -> bool {
+        matches!(
+            self,
+            SchedPolicy::Fifo | SchedPolicy::RoundRobin | SchedPolicy::Deadline
+        )
+    }
+}
+
+/// The parameters of the `SchedPolicy::Deadline` policy: the thread gets `runtime` of CPU time
+/// in every `period`, finished by `deadline` from the start of the period
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg(target_os = "linux")]
+pub struct DeadlineParams {
+    pub runtime: Duration,
+    pub deadline: Duration,
+    pub period: Duration,
+}
+
+/// How a thread is scheduled
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Scheduling {
+    pub policy: SchedPolicy,
+    /// The real time priority, 1 (lowest) to 99 for `Fifo` and `RoundRobin` and 0 for the others.
+    /// On Mac OS X this is the Mach priority of the thread.
+    pub priority: i32,
+    /// The nice value, -20 (highest priority) to 19, used by `Other` and `Batch`
+    pub nice: i32,
+    /// The parameters of the `Deadline` policy
+    #[cfg(target_os = "linux")]
+    pub deadline: Option<DeadlineParams>,
+    /// Whether child processes are reset to `Other` with a nice value of 0 or more
+    #[cfg(target_os = "linux")]
+    pub reset_on_fork: bool,
+}
+
+impl Default for Scheduling {
+    fn default() -> Scheduling {
+        Scheduling {
+            policy: SchedPolicy::Other,
+            priority: 0,
+            nice: 0,
+            #[cfg(target_os = "linux")]
+            deadline: None,
+            #[cfg(target_os = "linux")]
+            reset_on_fork: false,
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+const SCHED_FLAG_RESET_ON_FORK: u64 = 0x01;
+
+// struct sched_attr from linux/sched/types.h, which the libc crate does not have
+#[cfg(target_os = "linux")]
+#[repr(C)]
+#[derive(Default)]
+struct SchedAttr {
+    size: u32,
+    sched_policy: u32,
+    sched_flags: u64,
+    sched_nice: i32,
+    sched_priority: u32,
+    sched_runtime: u64,
+    sched_deadline: u64,
+    sched_period: u64,
+}
+
+#[cfg(target_os = "linux")]
+impl Scheduling {
+    fn from_attr(attr: &SchedAttr) -> Result<Scheduling> {
+        let policy = SchedPolicy::from_raw(attr.sched_policy as i32)
+            .ok_or_else(|| Error::from_raw_os_error(libc::EINVAL))?;
+        let deadline = if policy == SchedPolicy::Deadline {
+            Some(DeadlineParams {
+                runtime: Duration::from_nanos(attr.sched_runtime),
+                deadline: Duration::from_nanos(attr.sched_deadline),
+                period: Duration::from_nanos(attr.sched_period),
+            })
+        } else {
+            None
+        };
+        Ok(Scheduling {
+            policy,
+            priority: attr.sched_priority as i32,
+            nice: attr.sched_nice,
+            deadline,
+            reset_on_fork: attr.sched_flags & SCHED_FLAG_RESET_ON_FORK != 0,
+        })
+    }
+
+    fn to_attr(self) -> SchedAttr {
+        let deadline = self.deadline.unwrap_or(DeadlineParams {
+            runtime: Duration::ZERO,
+            deadline: Duration::ZERO,
+            period: Duration::ZERO,
+        });
+        SchedAttr {
+            size: std::mem::size_of::<SchedAttr>() as u32,
+            sched_policy: self.policy.raw(),
+            sched_flags: if self.reset_on_fork {
+                SCHED_FLAG_RESET_ON_FORK
+            } else {
+                0
+            },
+            sched_nice: self.nice,
+            sched_priority: self.priority as u32,
+            sched_runtime: deadline.runtime.as_nanos() as u64,
+            sched_deadline: deadline.deadline.as_nanos() as u64,
+            sched_period: deadline.period.as_nanos() as u64,
+        }
+    }
+}
+
+// The id of a thread of a process, which the sched_* calls take, failing with ESRCH if it is not
+// a thread of that process
+#[cfg(target_os = "linux")]
+fn thread_id(pid: i32, thread: u64) -> Result<libc::pid_t> {
+    if procfs::tids(pid)?.contains(&(thread as i32)) {
+        Ok(thread as libc::pid_t)
+    } else {
+        Err(Error::from_raw_os_error(libc::ESRCH))
+    }
+}
+
+// Call `f` on every thread of a process. Threads that exit while we do so are left out.
+#[cfg(target_os = "linux")]
+fn each_thread<F>(pid: i32, f: F) -> Result<()>
+where
+    F: Fn(libc::pid_t) -> Result<()>,
+{
+    for tid in procfs::tids(pid)? {
+        match f(tid) {
+            Err(ref err) if err.raw_os_error() == Some(libc::ESRCH) && tid != pid => {}
+            result => result?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn sched_getattr(tid: libc::pid_t) -> Result<Scheduling> {
+    let mut attr = SchedAttr::default();
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_sched_getattr,
+            tid,
+            &mut attr as *mut SchedAttr,
+            std::mem::size_of::<SchedAttr>() as u32,
+            0,
+        )
+    };
+    if ret != 0 {
+        return Err(Error::last_os_error());
+    }
+    Scheduling::from_attr(&attr)
+}
+
+#[cfg(target_os = "linux")]
+fn sched_setattr(tid: libc::pid_t, scheduling: &Scheduling) -> Result<()> {
+    let attr = scheduling.to_attr();
+    let ret = unsafe { libc::syscall(libc::SYS_sched_setattr, tid, &attr as *const SchedAttr, 0) };
+    if ret != 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Returns how the process with the specified pid is scheduled, which is how its main thread is
+///
+/// # Examples
+///
+/// ```
+/// use std::process;
+/// use libproc::libproc::proc_sched::pidscheduling;
+///
+/// let scheduling = pidscheduling(process::id() as i32).unwrap();
+/// println!("{:?} with nice {}", scheduling.policy, scheduling.nice);
+/// ```
+#[cfg(target_os = "linux")]
+pub fn pidscheduling(pid: i32) -> Result<Scheduling> {
+    sched_getattr(pid).map_err(|err| procfs::pid_error(pid, err))
+}
+
+/// Returns how the process with the specified pid is scheduled, which is how its main thread is
+///
+/// # Examples
+///
+/// ```
+/// use std::process;
+/// use libproc::libproc::proc_sched::pidscheduling;
+///
+/// let scheduling = pidscheduling(process::id() as i32).unwrap();
+/// println!("{:?} with nice {}", scheduling.policy, scheduling.nice);
+/// ```
+#[cfg(target_os = "macos")]
+pub fn pidscheduling(pid: i32) -> Result<Scheduling> {
+    let info = proc_pid::pidinfo::<TaskInfo>(pid, 0)?;
+    Ok(Scheduling {
+        policy: info.policy().unwrap_or(SchedPolicy::Other),
+        priority: info.pti_priority,
+        nice: pidnice(pid)?,
+    })
+}
+
+/// Returns how a thread, as returned by `listpidinfo::<ListThreads>`, of the process with the
+/// specified pid is scheduled
+#[cfg(target_os = "linux")]
+pub fn threadscheduling(pid: i32, thread: u64) -> Result<Scheduling> {
+    sched_getattr(thread_id(pid, thread)?)
+}
+
+/// Returns how a thread, as returned by `listpidinfo::<ListThreads>`, of the process with the
+/// specified pid is scheduled
+#[cfg(target_os = "macos")]
+pub fn threadscheduling(pid: i32, thread: u64) -> Result<Scheduling> {
+    let info = proc_pid::pidinfo::<ThreadInfo>(pid, thread)?;
+    Ok(Scheduling {
+        policy: info.policy().unwrap_or(SchedPolicy::Other),
+        priority: info.pth_priority,
+        nice: pidnice(pid)?,
+    })
+}
+
+/// Sets how every thread of the process with the specified pid is scheduled. Threads it creates
+/// later are scheduled as the thread that creates them.
+///
+/// Real time policies, and lowering the nice value, need privileges (CAP_SYS_NICE on Linux).
+#[cfg(target_os = "linux")]
+pub fn setpidscheduling(pid: i32, scheduling: &Scheduling) -> Result<()> {
+    each_thread(pid, |tid| sched_setattr(tid, scheduling))
+}
+
+/// Sets how a thread, as returned by `listpidinfo::<ListThreads>`, of the process with the
+/// specified pid is scheduled
+#[cfg(target_os = "linux")]
+pub fn setthreadscheduling(pid: i32, thread: u64, scheduling: &Scheduling) -> Result<()> {
+    sched_setattr(thread_id(pid, thread)?, scheduling)
+}
+
+/// Returns the nice value of the process with the specified pid
+#[cfg(target_os = "linux")]
+pub fn pidnice(pid: i32) -> Result<i32> {
+    Ok(pidscheduling(pid)?.nice)
+}
+
+/// Returns the nice value of the process with the specified pid
+#[cfg(target_os = "macos")]
+pub fn pidnice(pid: i32) -> Result<i32> {
+    // getpriority returns -1 both for an error and as a nice value, so errno tells them apart
+    unsafe { *libc::__error() = 0 };
+    let nice = unsafe { libc::getpriority(libc::PRIO_PROCESS, pid as libc::id_t) };
+    let err = Error::last_os_error();
+    match err.raw_os_error() {
+        Some(0) => Ok(nice),
+        _ => Err(err),
+    }
+}
+
+fn setpriority(id: i32, nice: i32) -> Result<()> {
+    let ret = unsafe { libc::setpriority(libc::PRIO_PROCESS, id as libc::id_t, nice) };
+    if ret != 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Sets the nice value, -20 (highest priority) to 19, of the process with the specified pid. On
+/// Linux, where each thread has its own nice value, it is set for every thread.
+///
+/// Lowering the nice value needs privileges (CAP_SYS_NICE on Linux).
+#[cfg(target_os = "linux")]
+pub fn setpidnice(pid: i32, nice: i32) -> Result<()> {
+    each_thread(pid, |tid| setpriority(tid, nice))
+}
+
+/// Sets the nice value, -20 (highest priority) to 19, of the process with the specified pid
+#[cfg(target_os = "macos")]
+pub fn setpidnice(pid: i32, nice: i32) -> Result<()> {
+    setpriority(pid, nice)
+}
+
+/// Sets the nice value of a thread, as returned by `listpidinfo::<ListThreads>`, of the process
+/// with the specified pid
+#[cfg(target_os = "linux")]
+pub fn setthreadnice(pid: i32, thread: u64, nice: i32) -> Result<()> {
+    setpriority(thread_id(pid, thread)?, nice)
+}
+
+#[cfg(target_os = "linux")]
+fn getaffinity(tid: libc::pid_t) -> Result<Vec<usize>> {
+    let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    let ret =
+        unsafe { libc::sched_getaffinity(tid, std::mem::size_of::<libc::cpu_set_t>(), &mut set) };
+    if ret != 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok((0..libc::CPU_SETSIZE as usize)
+        .filter(|&cpu| unsafe { libc::CPU_ISSET(cpu, &set) })
+        .collect())
+}
+
+#[cfg(target_os = "linux")]
+fn setaffinity(tid: libc::pid_t, cpus: &[usize]) -> Result<()> {
+    let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    for &cpu in cpus {
+        if cpu >= libc::CPU_SETSIZE as usize {
+            return Err(Error::from_raw_os_error(libc::EINVAL));
+        }
+        unsafe { libc::CPU_SET(cpu, &mut set) };
+    }
+    let ret = unsafe { libc::sched_setaffinity(tid, std::mem::size_of::<libc::cpu_set_t>(), &set) };
+    if ret != 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Returns the CPUs, numbered from 0, that the process with the specified pid may run on, which
+/// are those its main thread may run on
+#[cfg(target_os = "linux")]
+pub fn pidaffinity(pid: i32) -> Result<Vec<usize>> {
+    getaffinity(pid).map_err(|err| procfs::pid_error(pid, err))
+}
+
+/// Returns the CPUs, numbered from 0, that a thread, as returned by
+/// `listpidinfo::<ListThreads>`, of the process with the specified pid may run on
+#[cfg(target_os = "linux")]
+pub fn threadaffinity(pid: i32, thread: u64) -> Result<Vec<usize>> {
+    getaffinity(thread_id(pid, thread)?)
+}
+
+/// Restricts every thread of the process with the specified pid to run on the CPUs, numbered
+/// from 0
+///
+/// # Examples
+///
+/// ```no_run
+/// use libproc::libproc::proc_sched::setpidaffinity;
+///
+/// // Keep a noisy process off all but the first two CPUs
+/// setpidaffinity(1234, &[0, 1]).unwrap();
+/// ```
+#[cfg(target_os = "linux")]
+pub fn setpidaffinity(pid: i32, cpus: &[usize]) -> Result<()> {
+    each_thread(pid, |tid| setaffinity(tid, cpus))
+}
+
+/// Restricts a thread, as returned by `listpidinfo::<ListThreads>`, of the process with the
+/// specified pid to run on the CPUs, numbered from 0
+#[cfg(target_os = "linux")]
+pub fn setthreadaffinity(pid: i32, thread: u64, cpus: &[usize]) -> Result<()> {
+    setaffinity(thread_id(pid, thread)?, cpus)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::process;
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn from_raw_test() {
+        assert_eq!(SchedPolicy::from_raw(0), Some(SchedPolicy::Other));
+        assert_eq!(SchedPolicy::from_raw(2), Some(SchedPolicy::RoundRobin));
+        assert_eq!(SchedPolicy::from_raw(6), Some(SchedPolicy::Deadline));
+        assert_eq!(
+            SchedPolicy::from_raw(libc::SCHED_FIFO | libc::SCHED_RESET_ON_FORK),
+            Some(SchedPolicy::Fifo)
+        );
+        assert_eq!(SchedPolicy::from_raw(4), None);
+        for &policy in &[SchedPolicy::Batch, SchedPolicy::Idle, SchedPolicy::Deadline] {
+            assert_eq!(SchedPolicy::from_raw(policy.raw() as i32), Some(policy));
+        }
+    }
+
+    #[test]
+    fn pidscheduling_test() {
+        let scheduling = pidscheduling(process::id() as i32).unwrap();
+        assert!(!scheduling.policy.is_realtime());
+        assert!((-20..20).contains(&scheduling.nice));
+    }
+
+    // Lowering the priority of a process, and choosing any of the CPUs it may already run on,
+    // needs no privileges
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn set_child_scheduling_test() {
+        use crate::libproc::proc_pid::{listpidinfo, ListThreads};
+        use std::process::Command;
+
+        let mut child = Command::new("sleep").arg("5").spawn().unwrap();
+        let pid = child.id() as i32;
+
+        let batch = Scheduling {
+            policy: SchedPolicy::Batch,
+            nice: 5,
+            ..Scheduling::default()
+        };
+        let set_scheduling = setpidscheduling(pid, &batch);
+        let scheduling = pidscheduling(pid);
+        let set_nice = setpidnice(pid, 10);
+        let nice = pidnice(pid);
+
+        let cpus = pidaffinity(pid);
+        let first_cpu = cpus.as_ref().ok().and_then(|cpus| cpus.first().copied());
+        let set_affinity = setpidaffinity(pid, &first_cpu.into_iter().collect::<Vec<_>>());
+        let thread = listpidinfo::<ListThreads>(pid, 1).unwrap()[0];
+        let thread_cpus = threadaffinity(pid, thread);
+        child.kill().unwrap();
+        child.wait().unwrap();
+
+        set_scheduling.unwrap();
+        assert_eq!(scheduling.unwrap(), batch);
+        set_nice.unwrap();
+        assert_eq!(nice.unwrap(), 10);
+        set_affinity.unwrap();
+        assert_eq!(thread_cpus.unwrap(), vec![first_cpu.unwrap()]);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn thread_of_other_process_test() {
+        let err = threadscheduling(process::id() as i32, 1).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ESRCH));
+    }
+}
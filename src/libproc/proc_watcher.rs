@@ -0,0 +1,360 @@
+use libc::uid_t;
+use std::collections::{HashMap, VecDeque};
+use std::io::Result;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::libproc::proc_pid::{self, c_chars_to_string, BSDInfo, ProcType};
+
+/// A change between two snapshots of the running processes, as reported by `ProcessWatcher`
+#[derive(Clone, Debug, PartialEq)]
+pub enum WatchEvent {
+    /// A process that was not running at the last snapshot is now
+    Started {
+        pid: i32,
+        ppid: i32,
+        uid: uid_t,
+        name: String,
+    },
+    /// A process that was running at the last snapshot is not any more
+    Exited { pid: i32, name: String },
+    /// The parent of a process changed, usually because the parent exited
+    Reparented { pid: i32, old_ppid: i32, ppid: i32 },
+    /// The name of a process changed, because it executed a new program or renamed itself
+    Renamed {
+        pid: i32,
+        old_name: String,
+        name: String,
+    },
+    /// The effective user id of a process changed
+    UidChanged {
+        pid: i32,
+        old_uid: uid_t,
+        uid: uid_t,
+    },
+}
+
+/// What is known about a process at a snapshot
+#[derive(Clone, Debug, PartialEq)]
+struct ProcessState {
+    start_time: (u64, u64),
+    ppid: i32,
+    uid: uid_t,
+    name: String,
+}
+
+type Snapshot = HashMap<i32, ProcessState>;
+
+impl From<BSDInfo> for ProcessState {
+    fn from(info: BSDInfo) -> ProcessState {
+        // pbi_name holds the longer name, when there is one
+        let name = match c_chars_to_string(&info.pbi_name) {
+            ref name if name.is_empty() => c_chars_to_string(&info.pbi_comm),
+            name => name,
+        };
+        ProcessState {
+            start_time: (info.pbi_start_tvsec, info.pbi_start_tvusec),
+            ppid: info.pbi_ppid as i32,
+            uid: info.pbi_uid,
+            name,
+        }
+    }
+}
+
+// Processes that exit while the snapshot is taken are left out. A process that is still running
+// but could not be read this time keeps its state from the last snapshot, rather than being
+// reported as exiting now and starting again at the next one.
+fn snapshot(last: &Snapshot) -> Result<Snapshot> {
+    let pids = proc_pid::listpids(ProcType::ProcAllPIDS, 0)?;
+    Ok(states(
+        pids.into_iter().map(|pid| pid as i32),
+        last,
+        |pid| proc_pid::pidinfo::<BSDInfo>(pid, 0).map(ProcessState::from),
+    ))
+}
+
+fn states<F>(pids: impl Iterator<Item = i32>, last: &Snapshot, read: F) -> Snapshot
+where
+    F: Fn(i32) -> Result<ProcessState>,
+{
+    pids.filter_map(|pid| match read(pid) {
+        Ok(state) => Some((pid, state)),
+        Err(ref err) if err.raw_os_error() == Some(libc::ESRCH) => None,
+        Err(_) => last.get(&pid).map(|state| (pid, state.clone())),
+    })
+    .collect()
+}
+
+// The events that turn the old snapshot into the new one, in pid order. A pid with a different
+// start time is a new process that has reused the pid of one that exited.
+fn diff(old: &Snapshot, new: &Snapshot) -> Vec<WatchEvent> {
+    let mut pids: Vec<i32> = old.keys().chain(new.keys()).copied().collect();
+    pids.sort_unstable();
+    pids.dedup();
+
+    let mut events = vec![];
+    for pid in pids {
+        match (old.get(&pid), new.get(&pid)) {
+            (Some(before), Some(after)) if before.start_time == after.start_time => {
+                if before.ppid != after.ppid {
+                    events.push(WatchEvent::Reparented {
+                        pid,
+                        old_ppid: before.ppid,
+                        ppid: after.ppid,
+                    });
+                }
+                if before.name != after.name {
+                    events.push(WatchEvent::Renamed {
+                        pid,
+                        old_name: before.name.clone(),
+                        name: after.name.clone(),
+                    });
+                }
+                if before.uid != after.uid {
+                    events.push(WatchEvent::UidChanged {
+                        pid,
+                        old_uid: before.uid,
+                        uid: after.uid,
+                    });
+                }
+            }
+            (before, after) => {
+                if let Some(before) = before {
+                    events.push(WatchEvent::Exited {
+                        pid,
+                        name: before.name.clone(),
+                    });
+                }
+                if let Some(after) = after {
+                    events.push(WatchEvent::Started {
+                        pid,
+                        ppid: after.ppid,
+                        uid: after.uid,
+                        name: after.name.clone(),
+                    });
+                }
+            }
+        }
+    }
+    events
+}
+
+/// Reports processes starting, exiting and changing by comparing snapshots of the running
+/// processes taken at an interval.
+///
+/// Unlike `proc_events::ProcEventSubscriber` this needs no privileges and works on every
+/// platform, but it misses processes that start and exit between two snapshots, and changes
+/// that are undone between them.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use libproc::libproc::proc_watcher::{ProcessWatcher, WatchEvent};
+///
+/// let mut watcher = ProcessWatcher::new(Duration::from_secs(1)).unwrap();
+/// watcher
+///     .watch(|event| {
+///         if let WatchEvent::Started { pid, ref name, .. } = *event {
+///             println!("{} started as pid {}", name, pid);
+///         }
+///         true
+///     })
+///     .unwrap();
+/// ```
+pub struct ProcessWatcher {
+    interval: Duration,
+    snapshot: Snapshot,
+    taken: Instant,
+    pending: VecDeque<WatchEvent>,
+}
+
+impl ProcessWatcher {
+    /// Create a watcher that takes a snapshot every `interval`, starting with one taken now that
+    /// later changes are reported against
+    pub fn new(interval: Duration) -> Result<ProcessWatcher> {
+        Ok(ProcessWatcher {
+            interval,
+            snapshot: snapshot(&Snapshot::new())?,
+            taken: Instant::now(),
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// Take a snapshot now and return the changes since the last one, without waiting for the
+    /// interval to pass
+    pub fn poll(&mut self) -> Result<Vec<WatchEvent>> {
+        let snapshot = snapshot(&self.snapshot)?;
+        self.taken = Instant::now();
+        let mut events: Vec<WatchEvent> = self.pending.drain(..).collect();
+        events.extend(diff(&self.snapshot, &snapshot));
+        self.snapshot = snapshot;
+        Ok(events)
+    }
+
+    /// Wait for the next change, taking snapshots at the interval until there is one
+    pub fn next_event(&mut self) -> Result<WatchEvent> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Ok(event);
+            }
+
+            if let Some(remaining) = self.interval.checked_sub(self.taken.elapsed()) {
+                thread::sleep(remaining);
+            }
+            let events = self.poll()?;
+            self.pending.extend(events);
+        }
+    }
+
+    /// Call `callback` with each change as it is seen, for as long as it returns true
+    pub fn watch<F>(&mut self, mut callback: F) -> Result<()>
+    where
+        F: FnMut(&WatchEvent) -> bool,
+    {
+        loop {
+            if !callback(&self.next_event()?) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl Iterator for ProcessWatcher {
+    type Item = Result<WatchEvent>;
+
+    /// Waits for the next change, this never returns `None`
+    fn next(&mut self) -> Option<Result<WatchEvent>> {
+        Some(self.next_event())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::process::Command;
+
+    fn state(start_time: u64, ppid: i32, uid: uid_t, name: &str) -> ProcessState {
+        ProcessState {
+            start_time: (start_time, 0),
+            ppid,
+            uid,
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn states_test() {
+        use std::io::Error;
+
+        let mut last = Snapshot::new();
+        last.insert(10, state(100, 1, 0, "unreadable"));
+        last.insert(11, state(100, 1, 0, "exited"));
+        let read = |pid| match pid {
+            10 | 12 => Err(Error::from_raw_os_error(libc::EPERM)),
+            11 => Err(Error::from_raw_os_error(libc::ESRCH)),
+            _ => Ok(state(200, 1, 0, "read")),
+        };
+
+        let new = states(vec![10, 11, 12, 13].into_iter(), &last, read);
+        assert_eq!(new.len(), 2);
+        assert_eq!(new.get(&10), last.get(&10));
+        assert_eq!(new.get(&13), Some(&state(200, 1, 0, "read")));
+    }
+
+    #[test]
+    fn diff_started_and_exited_test() {
+        let mut old = Snapshot::new();
+        old.insert(10, state(100, 1, 0, "old"));
+        let mut new = Snapshot::new();
+        new.insert(20, state(200, 1, 501, "new"));
+
+        assert_eq!(
+            diff(&old, &new),
+            vec![
+                WatchEvent::Exited {
+                    pid: 10,
+                    name: "old".to_string()
+                },
+                WatchEvent::Started {
+                    pid: 20,
+                    ppid: 1,
+                    uid: 501,
+                    name: "new".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_changes_test() {
+        let mut old = Snapshot::new();
+        old.insert(10, state(100, 5, 0, "sh"));
+        let mut new = Snapshot::new();
+        new.insert(10, state(100, 1, 501, "sleep"));
+
+        assert_eq!(
+            diff(&old, &new),
+            vec![
+                WatchEvent::Reparented {
+                    pid: 10,
+                    old_ppid: 5,
+                    ppid: 1
+                },
+                WatchEvent::Renamed {
+                    pid: 10,
+                    old_name: "sh".to_string(),
+                    name: "sleep".to_string()
+                },
+                WatchEvent::UidChanged {
+                    pid: 10,
+                    old_uid: 0,
+                    uid: 501
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_pid_reuse_test() {
+        let mut old = Snapshot::new();
+        old.insert(10, state(100, 1, 0, "same"));
+        let mut new = Snapshot::new();
+        new.insert(10, state(101, 1, 0, "same"));
+
+        let events = diff(&old, &new);
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[0], WatchEvent::Exited { pid: 10, .. }));
+        assert!(matches!(events[1], WatchEvent::Started { pid: 10, .. }));
+    }
+
+    #[test]
+    fn diff_unchanged_test() {
+        let mut old = Snapshot::new();
+        old.insert(10, state(100, 1, 0, "same"));
+        assert!(diff(&old, &old.clone()).is_empty());
+    }
+
+    #[test]
+    fn watch_child_test() {
+        let mut watcher = ProcessWatcher::new(Duration::from_millis(10)).unwrap();
+        let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+        let pid = child.id() as i32;
+
+        assert!(watcher.poll().unwrap().iter().any(
+            |event| matches!(*event, WatchEvent::Started { pid: started, .. } if started == pid)
+        ));
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+
+        let mut exited = false;
+        watcher
+            .watch(|event| {
+                exited = matches!(*event, WatchEvent::Exited { pid: gone, .. } if gone == pid);
+                !exited
+            })
+            .unwrap();
+        assert!(exited);
+    }
+}
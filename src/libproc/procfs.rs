@@ -0,0 +1,455 @@
+// Helpers for reading process information from /proc, used by the Linux implementations of the API
+// See http://man7.org/linux/man-pages/man5/proc.5.html
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+
+pub(crate) fn pid_path(pid: i32, file: &str) -> PathBuf {
+    Path::new("/proc").join(pid.to_string()).join(file)
+}
+
+// A missing file under /proc/<pid> means the process has gone away; report that the same way
+// libproc does on Mac OS X so callers can handle both platforms alike
+pub(crate) fn pid_error(pid: i32, err: Error) -> Error {
+    if err.kind() == ErrorKind::NotFound && !pid_path(pid, "").exists() {
+        Error::from_raw_os_error(libc::ESRCH)
+    } else {
+        err
+    }
+}
+
+pub(crate) fn read_pid_file(pid: i32, file: &str) -> Result<String> {
+    fs::read_to_string(pid_path(pid, file)).map_err(|err| pid_error(pid, err))
+}
+
+// For files with paths in them, which need not be UTF-8
+fn read_pid_file_lossy(pid: i32, file: &str) -> Result<String> {
+    let contents = fs::read(pid_path(pid, file)).map_err(|err| pid_error(pid, err))?;
+    Ok(String::from_utf8_lossy(&contents).into_owned())
+}
+
+pub(crate) fn unsupported(what: &str) -> Error {
+    Error::new(
+        ErrorKind::Unsupported,
+        format!("{} is not supported on this platform", what),
+    )
+}
+
+fn invalid_data(file: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("Could not parse {}", file))
+}
+
+/// The pids of all the processes, from the numeric entries in /proc
+pub(crate) fn pids() -> Result<Vec<i32>> {
+    numeric_entries(Path::new("/proc"))
+}
+
+/// The ids of the threads of a process, from /proc/<pid>/task
+pub(crate) fn tids(pid: i32) -> Result<Vec<i32>> {
+    numeric_entries(&pid_path(pid, "task")).map_err(|err| pid_error(pid, err))
+}
+
+pub(crate) fn numeric_entries(dir: &Path) -> Result<Vec<i32>> {
+    let mut ids: Vec<i32> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.parse().ok())
+        })
+        .collect();
+    ids.sort_unstable();
+    Ok(ids)
+}
+
+/// The fields of /proc/<pid>/stat (or /proc/<pid>/task/<tid>/stat) that are used
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Stat {
+    pub pid: i32,
+    pub comm: String,
+    pub state: char,
+    pub ppid: i32,
+    pub pgrp: i32,
+    pub session: i32,
+    pub tty_nr: i32,
+    pub tpgid: i32,
+    pub minflt: u64,
+    pub majflt: u64,
+    pub utime: u64,
+    pub stime: u64,
+    pub priority: i64,
+    pub nice: i64,
+    pub num_threads: i64,
+    pub starttime: u64,
+    pub vsize: u64,
+    pub rss: i64,
+    pub processor: i32,
+    pub rt_priority: u32,
+    pub policy: u32,
+}
+
+impl Stat {
+    pub(crate) fn parse(contents: &str) -> Result<Stat> {
+        // The command name is in parentheses and may itself contain spaces and parentheses
+        let comm_start = contents.find('(').ok_or_else(|| invalid_data("stat"))?;
+        let comm_end = contents.rfind(')').ok_or_else(|| invalid_data("stat"))?;
+        let pid = contents[..comm_start]
+            .trim()
+            .parse()
+            .map_err(|_| invalid_data("stat"))?;
+        let comm = contents[comm_start + 1..comm_end].to_string();
+
+        // fields[0] is field 3 (state) in proc(5)
+        let fields: Vec<&str> = contents[comm_end + 1..].split_whitespace().collect();
+        let field = |number: usize| fields.get(number - 3).copied().unwrap_or("0");
+        let number = |index: usize| -> Result<i64> {
+            field(index).parse().map_err(|_| invalid_data("stat"))
+        };
+        let unsigned = |index: usize| -> Result<u64> {
+            field(index).parse().map_err(|_| invalid_data("stat"))
+        };
+
+        if fields.len() < 22 {
+            return Err(invalid_data("stat"));
+        }
+
+        Ok(Stat {
+            pid,
+            comm,
+            state: field(3).chars().next().unwrap_or('?'),
+            ppid: number(4)? as i32,
+            pgrp: number(5)? as i32,
+            session: number(6)? as i32,
+            tty_nr: number(7)? as i32,
+            tpgid: number(8)? as i32,
+            minflt: unsigned(10)?,
+            majflt: unsigned(12)?,
+            utime: unsigned(14)?,
+            stime: unsigned(15)?,
+            priority: number(18)?,
+            nice: number(19)?,
+            num_threads: number(20)?,
+            starttime: unsigned(22)?,
+            vsize: unsigned(23)?,
+            rss: number(24)?,
+            processor: number(39)? as i32,
+            rt_priority: unsigned(40)? as u32,
+            policy: unsigned(41)? as u32,
+        })
+    }
+}
+
+pub(crate) fn stat(pid: i32) -> Result<Stat> {
+    Stat::parse(&read_pid_file(pid, "stat")?)
+}
+
+pub(crate) fn task_stat(pid: i32, tid: i32) -> Result<Stat> {
+    Stat::parse(&read_pid_file(pid, &format!("task/{}/stat", tid))?)
+}
+
+/// The "Key: value" lines of /proc/<pid>/status
+pub(crate) struct Status {
+    lines: Vec<(String, String)>,
+}
+
+impl Status {
+    pub(crate) fn parse(contents: &str) -> Status {
+        Status {
+            lines: contents
+                .lines()
+                .filter_map(|line| {
+                    let mut parts = line.splitn(2, ':');
+                    match (parts.next(), parts.next()) {
+                        (Some(key), Some(value)) => {
+                            Some((key.trim().to_string(), value.trim().to_string()))
+                        }
+                        _ => None,
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    pub(crate) fn get(&self, key: &str) -> Option<&str> {
+        self.lines
+            .iter()
+            .find(|(line_key, _)| line_key == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The whitespace separated numbers of a line, such as the real, effective, saved and
+    /// filesystem ids of the "Uid" line
+    pub(crate) fn numbers(&self, key: &str) -> Vec<u64> {
+        self.get(key)
+            .map(|value| {
+                value
+                    .split_whitespace()
+                    .filter_map(|number| number.parse().ok())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn number(&self, key: &str) -> Option<u64> {
+        self.numbers(key).first().copied()
+    }
+}
+
+pub(crate) fn status(pid: i32) -> Result<Status> {
+    Ok(Status::parse(&read_pid_file(pid, "status")?))
+}
+
+/// A line of /proc/<pid>/maps, one mapping of the address space of a process
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Mapping {
+    pub start: u64,
+    pub end: u64,
+    /// Such as "r-xp", where the last character is 'p' for private or 's' for shared
+    pub perms: String,
+    pub offset: u64,
+    pub dev_major: u32,
+    pub dev_minor: u32,
+    pub inode: u64,
+    /// The backing file, a pseudo-path such as "[heap]", or empty for anonymous mappings
+    pub path: String,
+}
+
+impl Mapping {
+    pub(crate) fn parse(line: &str) -> Result<Mapping> {
+        // start-end perms offset major:minor inode path, where the path may contain spaces
+        let mut fields = line.splitn(6, ' ');
+        let mut field = || fields.next().ok_or_else(|| invalid_data("maps"));
+        let range = field()?;
+        let perms = field()?;
+        let offset = field()?;
+        let dev = field()?;
+        let inode = field()?;
+        let path = fields.next().unwrap_or("").trim_start();
+
+        let hex = |value: &str| u64::from_str_radix(value, 16).map_err(|_| invalid_data("maps"));
+        let mut bounds = range.splitn(2, '-');
+        let mut devs = dev.splitn(2, ':');
+        Ok(Mapping {
+            start: hex(bounds.next().unwrap_or(""))?,
+            end: hex(bounds.next().unwrap_or(""))?,
+            perms: perms.to_string(),
+            offset: hex(offset)?,
+            dev_major: hex(devs.next().unwrap_or(""))? as u32,
+            dev_minor: hex(devs.next().unwrap_or(""))? as u32,
+            inode: inode.parse().map_err(|_| invalid_data("maps"))?,
+            path: path.to_string(),
+        })
+    }
+}
+
+/// A mapping of /proc/<pid>/smaps, with the "Key: N kB" lines describing its memory use
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct SmapsEntry {
+    pub mapping: Mapping,
+    sizes: Vec<(String, u64)>,
+}
+
+impl SmapsEntry {
+    /// The size of a line in bytes, zero if there is no such line (older kernels have fewer)
+    pub(crate) fn bytes(&self, key: &str) -> u64 {
+        self.sizes
+            .iter()
+            .find(|(line_key, _)| line_key == key)
+            .map_or(0, |(_, size)| size * 1024)
+    }
+}
+
+// The lines of a mapping are "Key: value", where the key has no spaces, unlike the line with the
+// address range that starts each mapping. Lines that are not sizes, such as "VmFlags", are skipped.
+pub(crate) fn parse_smaps(contents: &str) -> Result<Vec<SmapsEntry>> {
+    let mut entries: Vec<SmapsEntry> = vec![];
+    for line in contents.lines().filter(|line| !line.is_empty()) {
+        match line.split_once(':') {
+            Some((key, value)) if !key.contains(char::is_whitespace) => {
+                let size = value.trim().strip_suffix("kB").map(str::trim);
+                if let (Some(entry), Some(Ok(size))) = (entries.last_mut(), size.map(str::parse)) {
+                    entry.sizes.push((key.to_string(), size));
+                }
+            }
+            _ => entries.push(SmapsEntry {
+                mapping: Mapping::parse(line)?,
+                sizes: vec![],
+            }),
+        }
+    }
+    Ok(entries)
+}
+
+pub(crate) fn smaps(pid: i32) -> Result<Vec<SmapsEntry>> {
+    parse_smaps(&read_pid_file_lossy(pid, "smaps")?)
+}
+
+/// The device number for a major and minor number, as glibc's makedev(3) encodes it in st_dev
+pub(crate) fn makedev(major: u32, minor: u32) -> u64 {
+    let (major, minor) = (major as u64, minor as u64);
+    ((major & 0xffff_f000) << 32)
+        | ((major & 0x0000_0fff) << 8)
+        | ((minor & 0xffff_ff00) << 12)
+        | (minor & 0x0000_00ff)
+}
+
+pub(crate) fn maps(pid: i32) -> Result<Vec<Mapping>> {
+    read_pid_file_lossy(pid, "maps")?
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(Mapping::parse)
+        .collect()
+}
+
+/// Clock ticks per second, the unit of the times in /proc/<pid>/stat
+pub(crate) fn clock_ticks() -> u64 {
+    match unsafe { libc::sysconf(libc::_SC_CLK_TCK) } {
+        ticks if ticks > 0 => ticks as u64,
+        _ => 100,
+    }
+}
+
+pub(crate) fn page_size() -> u64 {
+    match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+        size if size > 0 => size as u64,
+        _ => 4096,
+    }
+}
+
+pub(crate) fn ticks_to_nanos(ticks: u64) -> u64 {
+    ticks.saturating_mul(1_000_000_000) / clock_ticks()
+}
+
+/// The time the system booted, in seconds since the epoch, from the "btime" line of /proc/stat
+pub(crate) fn boot_time() -> Result<u64> {
+    fs::read_to_string("/proc/stat")?
+        .lines()
+        .find(|line| line.starts_with("btime "))
+        .and_then(|line| line["btime ".len()..].trim().parse().ok())
+        .ok_or_else(|| invalid_data("/proc/stat"))
+}
+
+/// The time a process started as seconds and microseconds since the epoch, from the start time
+/// in clock ticks since boot in /proc/<pid>/stat
+pub(crate) fn start_time(stat: &Stat) -> Result<(u64, u64)> {
+    let ticks = clock_ticks();
+    let seconds = boot_time()? + stat.starttime / ticks;
+    let micros = (stat.starttime % ticks) * 1_000_000 / ticks;
+    Ok((seconds, micros))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_stat_test() {
+        let contents = "1234 (tmux: server) S 1 1234 1234 0 -1 4194368 2914 0 12 0 147 93 0 0 \
+                        20 0 1 0 4213 28012544 1050 18446744073709551615 1 1 0 0 0 0 0 4096 \
+                        134433283 0 0 0 17 3 0 0 0 0 0 0 0 0 0 0 0 0 0\n";
+        let stat = Stat::parse(contents).unwrap();
+        assert_eq!(stat.pid, 1234);
+        assert_eq!(stat.comm, "tmux: server");
+        assert_eq!(stat.state, 'S');
+        assert_eq!(stat.ppid, 1);
+        assert_eq!(stat.pgrp, 1234);
+        assert_eq!(stat.tty_nr, 0);
+        assert_eq!(stat.tpgid, -1);
+        assert_eq!(stat.minflt, 2914);
+        assert_eq!(stat.majflt, 12);
+        assert_eq!(stat.utime, 147);
+        assert_eq!(stat.stime, 93);
+        assert_eq!(stat.priority, 20);
+        assert_eq!(stat.num_threads, 1);
+        assert_eq!(stat.starttime, 4213);
+        assert_eq!(stat.vsize, 28012544);
+        assert_eq!(stat.rss, 1050);
+        assert_eq!(stat.processor, 3);
+        assert_eq!(stat.policy, 0);
+    }
+
+    #[test]
+    fn parse_stat_parenthesis_in_name_test() {
+        let contents = "42 (a) b (c)) R 1 42 42 0 -1 0 0 0 0 0 0 0 0 0 20 0 1 0 100 0 0";
+        let stat = Stat::parse(contents).unwrap();
+        assert_eq!(stat.comm, "a) b (c)");
+        assert_eq!(stat.state, 'R');
+        assert_eq!(stat.starttime, 100);
+    }
+
+    #[test]
+    fn parse_stat_truncated_test() {
+        assert!(Stat::parse("42 (truncated) R 1 42").is_err());
+    }
+
+    #[test]
+    fn parse_status_test() {
+        let status =
+            Status::parse("Name:\tbash\nUid:\t1000\t1001\t1002\t1003\nVmRSS:\t  5120 kB\n");
+        assert_eq!(status.get("Name"), Some("bash"));
+        assert_eq!(status.numbers("Uid"), vec![1000, 1001, 1002, 1003]);
+        assert_eq!(status.number("VmRSS"), Some(5120));
+        assert_eq!(status.get("Gid"), None);
+    }
+
+    #[test]
+    fn parse_maps_test() {
+        let mapping = Mapping::parse(
+            "7f2c4a5e1000-7f2c4a607000 r-xp 00002000 fd:01 1835093    /usr/lib/my lib.so",
+        )
+        .unwrap();
+        assert_eq!(
+            mapping,
+            Mapping {
+                start: 0x7f2c4a5e1000,
+                end: 0x7f2c4a607000,
+                perms: "r-xp".to_string(),
+                offset: 0x2000,
+                dev_major: 0xfd,
+                dev_minor: 1,
+                inode: 1835093,
+                path: "/usr/lib/my lib.so".to_string(),
+            }
+        );
+
+        let anonymous = Mapping::parse("7ffd1000-7ffd2000 rw-s 00000000 00:00 0 ").unwrap();
+        assert_eq!(anonymous.path, "");
+        assert_eq!(anonymous.perms, "rw-s");
+
+        assert!(Mapping::parse("7ffd1000-7ffd2000 rw-p").is_err());
+    }
+
+    #[test]
+    fn parse_smaps_test() {
+        let contents = "\
+55d0c8a00000-55d0c8a21000 r-xp 00000000 fd:01 1835093                    /usr/bin/cat
+Size:                132 kB
+Rss:                 128 kB
+Pss:                  64 kB
+Shared_Dirty:          4 kB
+Private_Dirty:         8 kB
+VmFlags: rd ex mr mw me dw
+7ffd1000-7ffd2000 rw-p 00000000 00:00 0                                  [stack]
+Size:                  4 kB
+Swap:                  4 kB
+";
+        let entries = parse_smaps(contents).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].mapping.path, "/usr/bin/cat");
+        assert_eq!(entries[0].bytes("Rss"), 128 * 1024);
+        assert_eq!(entries[0].bytes("Pss"), 64 * 1024);
+        assert_eq!(entries[0].bytes("Swap"), 0);
+        assert_eq!(entries[1].mapping.path, "[stack]");
+        assert_eq!(entries[1].bytes("Swap"), 4 * 1024);
+        assert_eq!(entries[1].bytes("VmFlags"), 0);
+    }
+
+    #[test]
+    fn missing_process_test() {
+        match stat(-1) {
+            Ok(_) => panic!("Found stat for pid -1"),
+            Err(err) => assert_eq!(err.raw_os_error(), Some(libc::ESRCH)),
+        }
+    }
+}
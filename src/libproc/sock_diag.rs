@@ -0,0 +1,221 @@
+// The peers of Unix domain sockets from the Linux kernel's sock_diag netlink interface
+// See https://man7.org/linux/man-pages/man7/sock_diag.7.html and linux/unix_diag.h
+use libc::{c_int, c_void};
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind, Result};
+
+use crate::libproc::bytes::{truncated, u16_at, u32_at};
+
+// From linux/netlink.h, linux/sock_diag.h and linux/unix_diag.h
+const NETLINK_SOCK_DIAG: c_int = 4;
+const SOCK_DIAG_BY_FAMILY: u16 = 20;
+const NLM_F_REQUEST: u16 = 0x01;
+const NLM_F_DUMP: u16 = 0x300;
+const NLMSG_ERROR: u16 = 2;
+const NLMSG_DONE: u16 = 3;
+const UDIAG_SHOW_PEER: u32 = 0x04;
+const UNIX_DIAG_PEER: u16 = 2;
+
+// Sizes of struct nlmsghdr, struct unix_diag_req, struct unix_diag_msg and struct rtattr
+const NLMSG_HDRLEN: usize = 16;
+const UNIX_DIAG_REQ_LEN: usize = 24;
+const UNIX_DIAG_MSG_LEN: usize = 16;
+const RTA_HDRLEN: usize = 4;
+
+const RECEIVE_BUFFER_SIZE: usize = 32768;
+
+// Netlink messages and attributes are aligned to 4 bytes
+fn align(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+// A request to dump every Unix domain socket, in any state, with its peer
+fn dump_request() -> Vec<u8> {
+    let len = NLMSG_HDRLEN + UNIX_DIAG_REQ_LEN;
+    let mut request = Vec::with_capacity(len);
+
+    // struct nlmsghdr
+    request.extend_from_slice(&(len as u32).to_ne_bytes());
+    request.extend_from_slice(&SOCK_DIAG_BY_FAMILY.to_ne_bytes());
+    request.extend_from_slice(&(NLM_F_REQUEST | NLM_F_DUMP).to_ne_bytes());
+    request.extend_from_slice(&0u32.to_ne_bytes()); // seq
+    request.extend_from_slice(&0u32.to_ne_bytes()); // pid
+
+    // struct unix_diag_req
+    request.push(libc::AF_UNIX as u8); // sdiag_family
+    request.push(0); // sdiag_protocol
+    request.extend_from_slice(&0u16.to_ne_bytes()); // pad
+    request.extend_from_slice(&u32::MAX.to_ne_bytes()); // udiag_states
+    request.extend_from_slice(&0u32.to_ne_bytes()); // udiag_ino
+    request.extend_from_slice(&UDIAG_SHOW_PEER.to_ne_bytes());
+    request.extend_from_slice(&[0u8; 8]); // udiag_cookie
+    request
+}
+
+// Add the sockets in a datagram of the dump to `sockets`, returning true when it is the last
+pub(crate) fn parse_dump(datagram: &[u8], sockets: &mut HashMap<u64, Option<u64>>) -> Result<bool> {
+    let mut offset = 0;
+    // The last message and attribute need not be padded, so the offsets can pass the end
+    while offset + NLMSG_HDRLEN <= datagram.len() {
+        let len = u32_at(datagram, offset) as usize;
+        let message_type = u16_at(datagram, offset + 4);
+        if len < NLMSG_HDRLEN || offset + len > datagram.len() {
+            return Err(truncated("sock_diag message"));
+        }
+
+        match message_type {
+            NLMSG_DONE => return Ok(true),
+            NLMSG_ERROR if len < NLMSG_HDRLEN + 4 => return Err(truncated("sock_diag message")),
+            NLMSG_ERROR => {
+                let errno = u32_at(datagram, offset + NLMSG_HDRLEN) as i32;
+                return Err(Error::from_raw_os_error(-errno));
+            }
+            SOCK_DIAG_BY_FAMILY => {
+                let message = &datagram[offset + NLMSG_HDRLEN..offset + len];
+                if message.len() < UNIX_DIAG_MSG_LEN {
+                    return Err(truncated("sock_diag message"));
+                }
+                let inode = u32_at(message, 4) as u64;
+
+                // The peer, if there is one, is in an attribute
+                let mut peer = None;
+                let mut attribute = UNIX_DIAG_MSG_LEN;
+                while attribute + RTA_HDRLEN <= message.len() {
+                    let attribute_len = u16_at(message, attribute) as usize;
+                    if attribute_len < RTA_HDRLEN || attribute + attribute_len > message.len() {
+                        return Err(truncated("sock_diag message"));
+                    }
+                    if u16_at(message, attribute + 2) == UNIX_DIAG_PEER
+                        && attribute_len >= RTA_HDRLEN + 4
+                    {
+                        peer = Some(u32_at(message, attribute + RTA_HDRLEN) as u64)
+                            .filter(|&peer| peer != 0);
+                    }
+                    attribute += align(attribute_len);
+                }
+                sockets.insert(inode, peer);
+            }
+            _ => {}
+        }
+
+        offset += align(len);
+    }
+    Ok(false)
+}
+
+/// The inode of every Unix domain socket in the network namespace of this process, with the
+/// inode of the socket it is connected to
+pub(crate) fn unix_sockets() -> Result<HashMap<u64, Option<u64>>> {
+    let fd = unsafe {
+        libc::socket(
+            libc::AF_NETLINK,
+            libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+            NETLINK_SOCK_DIAG,
+        )
+    };
+    if fd < 0 {
+        return Err(Error::last_os_error());
+    }
+
+    let result = dump(fd);
+    unsafe { libc::close(fd) };
+    result
+}
+
+fn dump(fd: c_int) -> Result<HashMap<u64, Option<u64>>> {
+    let request = dump_request();
+    let ret = unsafe { libc::send(fd, request.as_ptr() as *const c_void, request.len(), 0) };
+    if ret < 0 {
+        return Err(Error::last_os_error());
+    }
+
+    let mut sockets = HashMap::new();
+    let mut buffer = vec![0u8; RECEIVE_BUFFER_SIZE];
+    loop {
+        let ret = unsafe { libc::recv(fd, buffer.as_mut_ptr() as *mut c_void, buffer.len(), 0) };
+        if ret < 0 {
+            let err = Error::last_os_error();
+            if err.kind() == ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(err);
+        }
+        if ret == 0 || parse_dump(&buffer[..ret as usize], &mut sockets)? {
+            return Ok(sockets);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn message(message_type: u16, payload: &[u8]) -> Vec<u8> {
+        let mut message = vec![];
+        message.extend_from_slice(&((NLMSG_HDRLEN + payload.len()) as u32).to_ne_bytes());
+        message.extend_from_slice(&message_type.to_ne_bytes());
+        message.extend_from_slice(&[0u8; 10]);
+        message.extend_from_slice(payload);
+        message
+    }
+
+    fn unix_diag_msg(inode: u32, peer: Option<u32>) -> Vec<u8> {
+        let mut payload = vec![libc::AF_UNIX as u8, 1, 1, 0];
+        payload.extend_from_slice(&inode.to_ne_bytes());
+        payload.extend_from_slice(&[0u8; 8]);
+        if let Some(peer) = peer {
+            payload.extend_from_slice(&8u16.to_ne_bytes());
+            payload.extend_from_slice(&UNIX_DIAG_PEER.to_ne_bytes());
+            payload.extend_from_slice(&peer.to_ne_bytes());
+        }
+        message(SOCK_DIAG_BY_FAMILY, &payload)
+    }
+
+    #[test]
+    fn parse_dump_test() {
+        let mut datagram = unix_diag_msg(100, Some(101));
+        datagram.extend(unix_diag_msg(200, None));
+        let mut sockets = HashMap::new();
+        assert!(!parse_dump(&datagram, &mut sockets).unwrap());
+        assert_eq!(sockets.get(&100), Some(&Some(101)));
+        assert_eq!(sockets.get(&200), Some(&None));
+
+        assert!(parse_dump(&message(NLMSG_DONE, &[0u8; 4]), &mut sockets).unwrap());
+        assert!(parse_dump(&datagram[..20], &mut sockets).is_err());
+    }
+
+    #[test]
+    fn parse_dump_unaligned_test() {
+        // The last message ends with an attribute whose length is not a multiple of 4
+        let mut payload = unix_diag_msg(300, Some(301))[NLMSG_HDRLEN..].to_vec();
+        payload.extend_from_slice(&5u16.to_ne_bytes());
+        payload.extend_from_slice(&0u16.to_ne_bytes());
+        payload.push(0);
+        let mut datagram = unix_diag_msg(200, None);
+        datagram.extend(message(SOCK_DIAG_BY_FAMILY, &payload));
+        assert_eq!(datagram.len() % 4, 1);
+
+        let mut sockets = HashMap::new();
+        assert!(!parse_dump(&datagram, &mut sockets).unwrap());
+        assert_eq!(sockets.get(&200), Some(&None));
+        assert_eq!(sockets.get(&300), Some(&Some(301)));
+    }
+
+    #[test]
+    fn unix_sockets_test() {
+        use std::os::unix::fs::MetadataExt;
+        use std::os::unix::io::AsRawFd;
+        use std::os::unix::net::UnixStream;
+
+        let (one, other) = UnixStream::pair().unwrap();
+        let inode = |stream: &UnixStream| {
+            std::fs::metadata(format!("/proc/self/fd/{}", stream.as_raw_fd()))
+                .unwrap()
+                .ino()
+        };
+
+        let sockets = unix_sockets().unwrap();
+        assert_eq!(sockets.get(&inode(&one)), Some(&Some(inode(&other))));
+        assert_eq!(sockets.get(&inode(&other)), Some(&Some(inode(&one))));
+    }
+}